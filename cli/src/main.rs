@@ -1,6 +1,12 @@
-use clap::{Parser, Subcommand};
+use clap::{Args as ClapArgs, Parser, Subcommand};
 use clipboard_core::clipboard::ClipboardData;
 use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use tokio::io::AsyncWriteExt;
+
+/// A complete tar archive ends with two zero-filled 512-byte blocks
+const TAR_END_MARKER: usize = 1024;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
@@ -21,9 +27,119 @@ enum Commands {
     Set {
         /// Content to set
         content: String,
+        /// Mark as a secret: kept out of history and destroyed once every device has fetched it
+        #[arg(long)]
+        sensitive: bool,
+        /// Expire the entry after this many seconds
+        #[arg(long)]
+        ttl: Option<u64>,
+    },
+    /// Browse clipboard history on the server
+    History {
+        #[command(subcommand)]
+        command: HistoryCommands,
+    },
+    /// Export the server's history (with uploaded files) to an archive
+    Export {
+        /// Output archive path
+        #[arg(short, long, default_value = "syncclipboard-history.tar")]
+        output: PathBuf,
+    },
+    /// Import an archive produced by `export`, skipping duplicate items
+    Import {
+        /// Archive path
+        file: PathBuf,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum HistoryCommands {
+    /// List recent history items (pinned first)
+    List {
+        #[command(flatten)]
+        filters: HistoryFilters,
+    },
+    /// Full-text search history, most relevant first
+    Search {
+        /// Search terms (each term matches as a prefix)
+        query: String,
+        #[command(flatten)]
+        filters: HistoryFilters,
     },
 }
 
+#[derive(ClapArgs, Debug, Serialize)]
+struct HistoryFilters {
+    /// Only items of this type (Text, Image, File)
+    #[arg(long = "type")]
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    r#type: Option<String>,
+    /// Only items from this device
+    #[arg(long)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    device: Option<String>,
+    /// Only pinned (true) or unpinned (false) items
+    #[arg(long)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pinned: Option<bool>,
+    /// Only items at or after this time (e.g. 2026-01-01 or "2026-01-01 08:00:00", UTC)
+    #[arg(long)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    since: Option<String>,
+    /// Only items at or before this time (a bare date includes the whole day)
+    #[arg(long)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    until: Option<String>,
+    /// Maximum number of items
+    #[arg(short = 'n', long, default_value_t = 20)]
+    limit: u32,
+}
+
+#[derive(Deserialize)]
+struct ImportResult {
+    imported: usize,
+    skipped: usize,
+    blobs: usize,
+}
+
+#[derive(Deserialize)]
+struct HistoryItem {
+    id: i64,
+    r#type: String,
+    content: Option<String>,
+    file: Option<String>,
+    device: Option<String>,
+    timestamp: String,
+    pinned: bool,
+    snippet: Option<String>,
+}
+
+fn print_history(items: &[HistoryItem]) {
+    if items.is_empty() {
+        println!("No history items found");
+        return;
+    }
+    for item in items {
+        let body = item
+            .snippet
+            .as_deref()
+            .map(|s| s.replace("<mark>", "[").replace("</mark>", "]"))
+            .or_else(|| item.content.clone())
+            .or_else(|| item.file.clone())
+            .unwrap_or_default();
+        let body = body.split_whitespace().collect::<Vec<_>>().join(" ");
+        println!(
+            "{:>6} {} {:<5} {}{}  {}",
+            item.id,
+            item.timestamp,
+            item.r#type,
+            if item.pinned { "* " } else { "" },
+            item.device.as_deref().unwrap_or("-"),
+            body
+        );
+    }
+}
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args = Args::parse();
@@ -33,7 +149,9 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     match args.command {
         Commands::Get => {
             let resp = client.get(&api_url).send().await?;
-            if resp.status().is_success() {
+            if resp.status() == reqwest::StatusCode::NO_CONTENT {
+                eprintln!("The latest entry was destroyed or has expired");
+            } else if resp.status().is_success() {
                 let data: ClipboardData = resp.json().await?;
                 match data {
                     ClipboardData::Text { content, .. } => println!("{}", content),
@@ -43,8 +161,11 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 eprintln!("Failed to get clipboard: {}", resp.status());
             }
         }
-        Commands::Set { content } => {
-            let data = ClipboardData::new_text(content);
+        Commands::Set { content, sensitive, ttl } => {
+            let mut data = ClipboardData::new_text(content).with_ttl(ttl);
+            if sensitive {
+                data = data.into_sensitive();
+            }
             let resp = client.put(&api_url).json(&data).send().await?;
             if resp.status().is_success() {
                 println!("Clipboard set successfully");
@@ -52,6 +173,68 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 eprintln!("Failed to set clipboard: {}", resp.status());
             }
         }
+        Commands::History { command } => {
+            let history_url = format!("{}/history", args.url);
+            let (query, filters) = match command {
+                HistoryCommands::List { filters } => (None, filters),
+                HistoryCommands::Search { query, filters } => (Some(query), filters),
+            };
+            let mut req = client.get(&history_url).query(&filters);
+            if let Some(q) = &query {
+                req = req.query(&[("q", q)]);
+            }
+            let resp = req.send().await?;
+            if resp.status().is_success() {
+                let items: Vec<HistoryItem> = resp.json().await?;
+                print_history(&items);
+            } else {
+                eprintln!("Failed to fetch history: {}", resp.status());
+            }
+        }
+        Commands::Export { output } => {
+            let mut resp = client.get(format!("{}/api/export", args.url)).send().await?;
+            if resp.status().is_success() {
+                let mut file = tokio::fs::File::create(&output).await?;
+                let mut size = 0;
+                // The last bytes received, to check the archive's end marker
+                let mut tail = Vec::with_capacity(2 * TAR_END_MARKER);
+                while let Some(chunk) = resp.chunk().await? {
+                    size += chunk.len();
+                    file.write_all(&chunk).await?;
+                    tail.extend_from_slice(&chunk);
+                    if tail.len() > TAR_END_MARKER {
+                        tail.drain(..tail.len() - TAR_END_MARKER);
+                    }
+                }
+                file.flush().await?;
+                if size % 512 != 0 || tail.len() < TAR_END_MARKER || tail.iter().any(|&b| b != 0) {
+                    drop(file);
+                    tokio::fs::remove_file(&output).await?;
+                    return Err(format!("Export was interrupted: received {} bytes without the archive end marker", size).into());
+                }
+                println!("Exported history to {} ({} bytes)", output.display(), size);
+            } else {
+                eprintln!("Failed to export history: {}", resp.status());
+            }
+        }
+        Commands::Import { file } => {
+            let archive = tokio::fs::File::open(&file).await?;
+            let resp = client
+                .post(format!("{}/api/import", args.url))
+                .header(reqwest::header::CONTENT_TYPE, "application/x-tar")
+                .body(archive)
+                .send()
+                .await?;
+            if resp.status().is_success() {
+                let result: ImportResult = resp.json().await?;
+                println!(
+                    "Imported {} items ({} duplicates skipped, {} files)",
+                    result.imported, result.skipped, result.blobs
+                );
+            } else {
+                eprintln!("Failed to import history: {}", resp.status());
+            }
+        }
     }
     Ok(())
 }
@@ -12,6 +12,12 @@ pub enum ClipboardData {
         file: Option<String>,
         #[serde(rename = "Device", alias = "device", default)]
         device: Option<String>,
+        /// 存活时间（秒），过期后服务端清除该条目
+        #[serde(rename = "Ttl", default, skip_serializing_if = "Option::is_none")]
+        ttl: Option<u64>,
+        /// 敏感内容（如密码）：不进入历史记录，所有设备取走后即销毁
+        #[serde(rename = "Sensitive", default, skip_serializing_if = "is_false")]
+        sensitive: bool,
     },
     Image {
         #[serde(rename = "Clipboard")]
@@ -20,6 +26,12 @@ pub enum ClipboardData {
         filename: String,
         #[serde(rename = "Device", alias = "device", default)]
         device: Option<String>,
+        /// 存活时间（秒），过期后服务端清除该条目
+        #[serde(rename = "Ttl", default, skip_serializing_if = "Option::is_none")]
+        ttl: Option<u64>,
+        /// 敏感内容（如密码）：不进入历史记录，所有设备取走后即销毁
+        #[serde(rename = "Sensitive", default, skip_serializing_if = "is_false")]
+        sensitive: bool,
     },
     File {
         #[serde(rename = "Clipboard")]
@@ -28,9 +40,19 @@ pub enum ClipboardData {
         filename: String,
         #[serde(rename = "Device", alias = "device", default)]
         device: Option<String>,
+        /// 存活时间（秒），过期后服务端清除该条目
+        #[serde(rename = "Ttl", default, skip_serializing_if = "Option::is_none")]
+        ttl: Option<u64>,
+        /// 敏感内容（如密码）：不进入历史记录，所有设备取走后即销毁
+        #[serde(rename = "Sensitive", default, skip_serializing_if = "is_false")]
+        sensitive: bool,
     },
 }
 
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
 impl ClipboardData {
     pub fn new_text(content: String) -> Self {
         ClipboardData::Text {
@@ -38,8 +60,85 @@ impl ClipboardData {
             html: None,
             file: None,
             device: None,
+            ttl: None,
+            sensitive: false,
+        }
+    }
+
+    /// 存活时间（秒）
+    pub fn ttl(&self) -> Option<u64> {
+        match self {
+            ClipboardData::Text { ttl, .. }
+            | ClipboardData::Image { ttl, .. }
+            | ClipboardData::File { ttl, .. } => *ttl,
         }
     }
 
+    pub fn is_sensitive(&self) -> bool {
+        match self {
+            ClipboardData::Text { sensitive, .. }
+            | ClipboardData::Image { sensitive, .. }
+            | ClipboardData::File { sensitive, .. } => *sensitive,
+        }
+    }
+
+    /// 设置存活时间（秒）
+    pub fn with_ttl(mut self, ttl_secs: Option<u64>) -> Self {
+        match &mut self {
+            ClipboardData::Text { ttl, .. }
+            | ClipboardData::Image { ttl, .. }
+            | ClipboardData::File { ttl, .. } => *ttl = ttl_secs,
+        }
+        self
+    }
+
+    /// 标记为敏感内容
+    pub fn into_sensitive(mut self) -> Self {
+        match &mut self {
+            ClipboardData::Text { sensitive, .. }
+            | ClipboardData::Image { sensitive, .. }
+            | ClipboardData::File { sensitive, .. } => *sensitive = true,
+        }
+        self
+    }
+
     // TODO: Hash calculation for images and files
 }
+
+/// 从 HTML 中提取纯文本（用于搜索与上传过滤）：去掉标签（以及 script/style 内容），解码常见实体，合并空白
+pub fn html_to_text(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        text.push_str(&rest[..start]);
+        text.push(' ');
+        let tag = &rest[start..];
+        let Some(end) = tag.find('>') else {
+            rest = "";
+            break;
+        };
+        let name = tag[1..end]
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .next()
+            .unwrap_or("")
+            .to_ascii_lowercase();
+        rest = &tag[end + 1..];
+        if name == "script" || name == "style" {
+            let close = format!("</{}", name);
+            rest = match rest.to_ascii_lowercase().find(&close) {
+                Some(pos) => &rest[pos..],
+                None => "",
+            };
+        }
+    }
+    text.push_str(rest);
+
+    let decoded = text
+        .replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&");
+    decoded.split_whitespace().collect::<Vec<_>>().join(" ")
+}
@@ -3,6 +3,15 @@ use std::sync::{Arc, Mutex};
 use anyhow::Result;
 use image::DynamicImage;
 
+/// KDE Klipper / KeePassXC 等在 Linux 上使用的密码提示格式，值为 `secret` 时表示隐藏内容
+const KDE_PASSWORD_HINT: &str = "x-kde-passwordManagerHint";
+/// macOS 上 nspasteboard.org 约定的隐藏内容格式
+const MACOS_CONCEALED: &str = "org.nspasteboard.ConcealedType";
+/// Windows 上要求剪贴板监视程序忽略该内容
+const WINDOWS_EXCLUDE_MONITOR: &str = "ExcludeClipboardContentFromMonitorProcessing";
+/// Windows 上值为 0 时表示内容不应进入剪贴板历史或云剪贴板
+const WINDOWS_NO_HISTORY: [&str; 2] = ["CanIncludeInClipboardHistory", "CanUploadToCloudClipboard"];
+
 pub struct ClipboardHandler {
     backend: Arc<Mutex<Box<dyn Clipboard>>>,
 }
@@ -10,9 +19,14 @@ pub struct ClipboardHandler {
 impl ClipboardHandler {
     pub fn new() -> Result<Self> {
         let clipboard = ClipboardContext::new().map_err(|e| anyhow::anyhow!("{}", e))?;
-        Ok(Self {
-            backend: Arc::new(Mutex::new(Box::new(clipboard))),
-        })
+        Ok(Self::with_backend(Box::new(clipboard)))
+    }
+
+    /// 使用指定的剪贴板实现，例如没有图形界面时的内存剪贴板
+    pub fn with_backend(backend: Box<dyn Clipboard>) -> Self {
+        Self {
+            backend: Arc::new(Mutex::new(backend)),
+        }
     }
 
     pub fn get_text(&self) -> Result<String> {
@@ -53,4 +67,24 @@ impl ClipboardHandler {
         }
         clipboard.set_html(html).map_err(|e| anyhow::anyhow!("{}", e))
     }
+
+    /// 当前内容是否被密码管理器标记为隐藏（如 KeePassXC 复制的密码）
+    pub fn is_concealed(&self) -> bool {
+        let clipboard = self.backend.lock().unwrap();
+        let Ok(formats) = clipboard.available_formats() else {
+            return false;
+        };
+        formats.iter().any(|format| match format.as_str() {
+            KDE_PASSWORD_HINT => clipboard
+                .get_buffer(format)
+                .map(|value| value.trim_ascii() == b"secret")
+                .unwrap_or(true),
+            MACOS_CONCEALED | WINDOWS_EXCLUDE_MONITOR => true,
+            f if WINDOWS_NO_HISTORY.contains(&f) => clipboard
+                .get_buffer(format)
+                .map(|value| value.iter().all(|b| *b == 0))
+                .unwrap_or(false),
+            _ => false,
+        })
+    }
 }
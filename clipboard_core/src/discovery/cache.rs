@@ -1,8 +1,11 @@
-use super::types::{Device, DeviceEntry, DiscoveryMethod};
+use super::types::{address_rank, Device, DeviceEntry, DiscoveryEvent, DiscoveryMethod};
 use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
-use tokio::sync::Mutex;
+use tokio::sync::{broadcast, Mutex};
+
+/// 事件通道容量，订阅者落后更多时收到 `Lagged`
+const EVENT_CAPACITY: usize = 64;
 
 /// 设备缓存管理器
 pub struct DeviceCache {
@@ -10,6 +13,8 @@ pub struct DeviceCache {
     devices: Arc<Mutex<HashMap<String, DeviceEntry>>>,
     /// 缓存生存时间（TTL）
     ttl: Duration,
+    /// 设备变化事件
+    events: broadcast::Sender<DiscoveryEvent>,
 }
 
 impl DeviceCache {
@@ -18,39 +23,79 @@ impl DeviceCache {
         Self {
             devices: Arc::new(Mutex::new(HashMap::new())),
             ttl,
+            events: broadcast::channel(EVENT_CAPACITY).0,
         }
     }
 
-    /// 注册或更新设备
-    pub async fn register_device(&self, device: Device, method: DiscoveryMethod) {
+    /// 订阅设备变化事件。事件在缓存更新后发出，收到时查询缓存即可得到新状态
+    pub fn subscribe(&self) -> broadcast::Receiver<DiscoveryEvent> {
+        self.events.subscribe()
+    }
+
+    fn emit(&self, event: DiscoveryEvent) {
+        // No subscribers is fine
+        let _ = self.events.send(event);
+    }
+
+    /// 注册或更新设备。
+    ///
+    /// 同一设备可能经多种方式、多个地址族被发现：在同一实例的当前地址仍有效（TTL 内被报告过）时，
+    /// 不以优先级更低的地址（如链路本地 IPv6）替换它，只刷新在线时间。
+    pub async fn register_device(&self, mut device: Device, method: DiscoveryMethod) {
         let mut devices = self.devices.lock().await;
+        let now = Instant::now();
+        let mut address_seen = now;
         
+        // A source that does not know the instance ID says nothing about restarts
+        if device.instance_id == 0 {
+            if let Some(existing) = devices.get(&device.id) {
+                device.instance_id = existing.device.instance_id;
+            }
+        }
+
         // 检查是否为重启设备
-        if let Some(existing) = devices.get(&device.id) {
-            if existing.device.instance_id != device.instance_id {
+        let event = match devices.get(&device.id) {
+            Some(existing) if existing.device.instance_id != 0 && existing.device.instance_id != device.instance_id => {
                 tracing::info!(
                     "Device '{}' restarted (instance_id: {} -> {})",
                     device.name,
                     existing.device.instance_id,
                     device.instance_id
                 );
+                Some(DiscoveryEvent::DeviceRestarted(device.clone()))
             }
-        } else {
-            tracing::info!(
-                "Discovered new device '{}' ({}) via {:?}",
-                device.name,
-                device.ip,
-                method
-            );
-        }
+            Some(existing) => {
+                if address_rank(&existing.device.ip) < address_rank(&device.ip)
+                    && now.duration_since(existing.address_seen) < self.ttl
+                {
+                    device.ip = existing.device.ip.clone();
+                    address_seen = existing.address_seen;
+                }
+                // Periodic announcements only refresh the entry
+                (existing.device != device).then(|| DiscoveryEvent::DeviceUpdated(device.clone()))
+            }
+            None => {
+                tracing::info!(
+                    "Discovered new device '{}' ({}) via {:?}",
+                    device.name,
+                    device.ip,
+                    method
+                );
+                Some(DiscoveryEvent::DeviceAdded(device.clone()))
+            }
+        };
         
         let entry = DeviceEntry {
             device,
-            last_seen: Instant::now(),
+            last_seen: now,
+            address_seen,
             discovery_method: method,
         };
         
         devices.insert(entry.device.id.clone(), entry);
+        if let Some(event) = event {
+            self.emit(event);
+        }
     }
 
     /// 获取所有在线设备
@@ -62,28 +107,35 @@ impl DeviceCache {
             .collect()
     }
 
+    /// 按设备 ID 查找在线设备
+    pub async fn get_device(&self, id: &str) -> Option<Device> {
+        self.devices.lock().await.get(id).map(|entry| entry.device.clone())
+    }
+
     /// 清理过期设备
     pub async fn cleanup_expired(&self) {
         let mut devices = self.devices.lock().await;
         let now = Instant::now();
         let ttl = self.ttl;
         
-        let before_count = devices.len();
-        devices.retain(|id, entry| {
-            let expired = now.duration_since(entry.last_seen) >= ttl;
-            if expired {
+        let expired: Vec<String> = devices
+            .iter()
+            .filter(|(_, entry)| now.duration_since(entry.last_seen) >= ttl)
+            .map(|(id, _)| id.clone())
+            .collect();
+        for id in &expired {
+            if let Some(entry) = devices.remove(id) {
                 tracing::info!(
                     "Device '{}' ({}) expired from cache",
                     entry.device.name,
                     id
                 );
+                self.emit(DiscoveryEvent::DeviceExpired(entry.device));
             }
-            !expired
-        });
+        }
         
-        let removed = before_count - devices.len();
-        if removed > 0 {
-            tracing::debug!("Cleaned up {} expired device(s)", removed);
+        if !expired.is_empty() {
+            tracing::debug!("Cleaned up {} expired device(s)", expired.len());
         }
     }
 
@@ -0,0 +1,147 @@
+use super::types::{Device, DiscoveryError, DiscoveryInfo, Result};
+use futures_util::stream::{self, StreamExt};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::Duration;
+
+/// 未配置端口时探测的端口
+pub const DEFAULT_SCAN_PORTS: &[u16] = &[5033];
+
+/// 单次扫描最多探测的地址数（地址数 × 端口数）
+const MAX_TARGETS: usize = 65536;
+
+/// 默认并发探测数
+const DEFAULT_CONCURRENCY: usize = 64;
+
+/// 默认单个地址的超时
+const DEFAULT_TIMEOUT: Duration = Duration::from_millis(800);
+
+/// 跨网段 HTTP 扫描：并发请求指定网段内各地址的 `/api/discovery`
+pub struct HttpScanner {
+    targets: Vec<SocketAddr>,
+    concurrency: usize,
+    timeout: Duration,
+}
+
+impl HttpScanner {
+    /// 按 CIDR 网段（如 `192.168.2.0/24`，单个地址也可）与端口创建扫描器，端口为空时使用 5033
+    pub fn new(ranges: &[String], ports: &[u16]) -> Result<Self> {
+        let ports = if ports.is_empty() { DEFAULT_SCAN_PORTS } else { ports };
+        let mut targets = Vec::new();
+        for range in ranges {
+            for ip in parse_cidr(range)? {
+                if targets.len() + ports.len() > MAX_TARGETS {
+                    return Err(DiscoveryError::InvalidConfig(format!(
+                        "scan ranges cover more than {} addresses",
+                        MAX_TARGETS
+                    )));
+                }
+                targets.extend(ports.iter().map(|port| SocketAddr::new(ip, *port)));
+            }
+        }
+        Ok(Self {
+            targets,
+            concurrency: DEFAULT_CONCURRENCY,
+            timeout: DEFAULT_TIMEOUT,
+        })
+    }
+
+    /// 设置并发探测数与单个地址的超时
+    pub fn with_limits(mut self, concurrency: usize, timeout: Duration) -> Self {
+        self.concurrency = concurrency.max(1);
+        self.timeout = timeout;
+        self
+    }
+
+    /// 要探测的地址数
+    pub fn target_count(&self) -> usize {
+        self.targets.len()
+    }
+
+    /// 探测所有地址，返回响应了 SyncClipboard 服务信息的设备
+    pub async fn scan(&self) -> Vec<Device> {
+        // Probes stay on the LAN; certificates are checked when actually connecting
+        let client = match reqwest::Client::builder()
+            .no_proxy()
+            .connect_timeout(self.timeout)
+            .timeout(self.timeout * 2)
+            .tls_danger_accept_invalid_certs(true)
+            .build()
+        {
+            Ok(client) => client,
+            Err(e) => {
+                tracing::error!("Failed to build HTTP scan client: {}", e);
+                return Vec::new();
+            }
+        };
+        tracing::info!("HTTP scanning {} address(es)", self.targets.len());
+
+        // Owned items keep the future Send for callers such as Tauri commands
+        stream::iter(self.targets.clone())
+            .map(|addr| probe(client.clone(), addr))
+            .buffer_unordered(self.concurrency)
+            .filter_map(std::future::ready)
+            .collect()
+            .await
+    }
+}
+
+/// 探测单个地址，先用 HTTP，能建立连接但不是 HTTP 服务时再试 HTTPS
+async fn probe(client: reqwest::Client, addr: SocketAddr) -> Option<Device> {
+    let mut result = None;
+    for scheme in ["http", "https"] {
+        match client.get(format!("{}://{}/api/discovery", scheme, addr)).send().await {
+            Ok(response) => {
+                result = response.json::<DiscoveryInfo>().await.ok();
+                break;
+            }
+            // Nothing is listening, no point trying TLS
+            Err(e) if e.is_connect() || e.is_timeout() => return None,
+            Err(_) => {}
+        }
+    }
+    let info = result.filter(|info| info.service == "SyncClipboard")?;
+    tracing::debug!("HTTP scan found '{}' at {}", info.name(), addr);
+    Some(Device {
+        name: info.name().to_string(),
+        id: info.identity.device_id,
+        ip: addr.ip().to_string(),
+        port: addr.port(),
+        instance_id: info.instance_id,
+        capabilities: info.identity.capabilities,
+    })
+}
+
+/// 解析 CIDR 网段为主机地址。IPv4 网段不含网络地址与广播地址（/31、/32 除外）；
+/// IPv6 网段前缀不能短于 /112
+pub fn parse_cidr(range: &str) -> Result<Vec<IpAddr>> {
+    let invalid = || DiscoveryError::InvalidConfig(format!("invalid scan range '{}'", range));
+    let (ip, prefix) = match range.trim().split_once('/') {
+        Some((ip, prefix)) => (ip.parse::<IpAddr>().map_err(|_| invalid())?, Some(prefix.parse::<u32>().map_err(|_| invalid())?)),
+        None => (range.trim().parse::<IpAddr>().map_err(|_| invalid())?, None),
+    };
+    match ip {
+        IpAddr::V4(ip) => {
+            let prefix = prefix.unwrap_or(32);
+            if prefix > 32 {
+                return Err(invalid());
+            }
+            let mask = u32::MAX.checked_shl(32 - prefix).unwrap_or(0);
+            let network = u32::from(ip) & mask;
+            let broadcast = network | !mask;
+            let (first, last) = if prefix >= 31 { (network, broadcast) } else { (network + 1, broadcast - 1) };
+            if (last - first) as usize >= MAX_TARGETS {
+                return Err(invalid());
+            }
+            Ok((first..=last).map(|ip| IpAddr::V4(Ipv4Addr::from(ip))).collect())
+        }
+        IpAddr::V6(ip) => {
+            let prefix = prefix.unwrap_or(128);
+            if !(112..=128).contains(&prefix) {
+                return Err(invalid());
+            }
+            let mask = u128::MAX.checked_shl(128 - prefix).unwrap_or(0);
+            let network = u128::from(ip) & mask;
+            Ok((network..=network | !mask).map(|ip| IpAddr::V6(Ipv6Addr::from(ip))).collect())
+        }
+    }
+}
@@ -0,0 +1,200 @@
+use super::multicast::ANNOUNCEMENT_INTERVAL_SECS;
+use super::types::{address_rank, Device, DiscoveryError, Result};
+use mdns_sd::{ResolvedService, ScopedIp, ServiceDaemon, ServiceEvent, ServiceInfo};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// mDNS 服务类型
+pub const SERVICE_TYPE: &str = "_syncclipboard._tcp.local.";
+
+/// TXT 记录中的键
+const TXT_VERSION: &str = "version";
+const TXT_DEVICE_ID: &str = "device_id";
+const TXT_NAME: &str = "name";
+const TXT_INSTANCE_ID: &str = "instance_id";
+const TXT_CAPABILITIES: &str = "capabilities";
+
+/// mDNS 注册与浏览服务
+pub struct MdnsService {
+    daemon: ServiceDaemon,
+    /// 本机设备信息
+    my_device: Device,
+    /// TXT 记录中的附加属性（如证书指纹），修改后重新注册
+    extra: Mutex<BTreeMap<String, String>>,
+    /// 是否已注册本机服务
+    registered: AtomicBool,
+    /// 守护进程是否已关闭
+    closed: AtomicBool,
+}
+
+impl MdnsService {
+    /// 创建新的 mDNS 服务
+    pub fn new(my_device: Device) -> Result<Self> {
+        let daemon = ServiceDaemon::new().map_err(|e| DiscoveryError::Mdns(e.to_string()))?;
+        Ok(Self {
+            daemon,
+            my_device,
+            extra: Mutex::new(BTreeMap::new()),
+            registered: AtomicBool::new(false),
+            closed: AtomicBool::new(false),
+        })
+    }
+
+    fn service_info(&self) -> Result<ServiceInfo> {
+        let device = &self.my_device;
+        // Host labels may only hold letters, digits and hyphens
+        let host: String = device.name
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+            .collect();
+        let host_name = format!("{}.local.", host);
+
+        let instance_id = device.instance_id.to_string();
+        let capabilities = device.capabilities.join(",");
+        let extra = self.extra.lock().unwrap_or_else(|e| e.into_inner());
+        let mut properties = vec![
+            (TXT_VERSION, "1.0"),
+            (TXT_DEVICE_ID, device.id.as_str()),
+            (TXT_NAME, device.name.as_str()),
+            (TXT_INSTANCE_ID, instance_id.as_str()),
+            (TXT_CAPABILITIES, capabilities.as_str()),
+        ];
+        properties.extend(extra.iter().map(|(k, v)| (k.as_str(), v.as_str())));
+
+        // Addresses are filled in (and kept up to date) by mdns-sd
+        ServiceInfo::new(SERVICE_TYPE, &device.name, &host_name, "", device.port, &properties[..])
+            .map(ServiceInfo::enable_addr_auto)
+            .map_err(|e| DiscoveryError::Mdns(format!("Invalid mDNS service info: {}", e)))
+    }
+
+    /// 注册本机服务
+    pub fn register(&self) -> Result<()> {
+        self.daemon
+            .register(self.service_info()?)
+            .map_err(|e| DiscoveryError::Mdns(format!("Failed to register mDNS service: {}", e)))?;
+        self.registered.store(true, Ordering::SeqCst);
+        tracing::info!("mDNS registered: '{}' ({}) port {}", self.my_device.name, SERVICE_TYPE, self.my_device.port);
+        Ok(())
+    }
+
+    /// 设置或移除 TXT 记录中的附加属性；已注册时立即重新公告
+    pub fn set_property(&self, key: &str, value: Option<String>) -> Result<()> {
+        {
+            let mut extra = self.extra.lock().unwrap_or_else(|e| e.into_inner());
+            match value {
+                Some(value) => extra.insert(key.to_string(), value),
+                None => extra.remove(key),
+            };
+        }
+        if self.registered.load(Ordering::SeqCst) {
+            self.register()?;
+        }
+        Ok(())
+    }
+
+    /// 注销本机服务（向局域网发送下线通知）并关闭守护进程，之后不能再注册或浏览
+    pub fn shutdown(&self) {
+        if self.closed.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        if self.registered.swap(false, Ordering::SeqCst) {
+            match self.service_info() {
+                Ok(info) => {
+                    if let Err(e) = self.daemon.unregister(info.get_fullname()) {
+                        tracing::warn!("Failed to unregister mDNS service: {}", e);
+                    }
+                }
+                Err(e) => tracing::warn!("{}", e),
+            }
+        }
+        if let Err(e) = self.daemon.shutdown() {
+            tracing::warn!("Failed to shut down mDNS daemon: {}", e);
+        }
+    }
+
+    /// 浏览局域网中的服务，解析出的设备交给回调。
+    ///
+    /// mDNS 只在服务变化时通知，因此仍在线的设备会按公告间隔重新交给回调，
+    /// 以免在缓存中过期；服务下线后不再刷新，由缓存按 TTL 清除。
+    pub async fn browse<F>(&self, callback: F) -> Result<()>
+    where
+        F: Fn(Device) + Send + 'static,
+    {
+        let events = self.daemon
+            .browse(SERVICE_TYPE)
+            .map_err(|e| DiscoveryError::Mdns(format!("Failed to browse mDNS services: {}", e)))?;
+        // fullname -> device, services currently online
+        let mut online: HashMap<String, Device> = HashMap::new();
+        let mut refresh = tokio::time::interval(Duration::from_secs(ANNOUNCEMENT_INTERVAL_SECS));
+        refresh.tick().await;
+
+        loop {
+            tokio::select! {
+                event = events.recv_async() => match event {
+                    Ok(ServiceEvent::ServiceResolved(service)) => {
+                        let Some(device) = device_from_service(&service) else {
+                            continue;
+                        };
+                        if device.id == self.my_device.id {
+                            continue;
+                        }
+                        online.insert(service.fullname.clone(), device.clone());
+                        callback(device);
+                    }
+                    Ok(ServiceEvent::ServiceRemoved(_, fullname)) => {
+                        if let Some(device) = online.remove(&fullname) {
+                            tracing::debug!("mDNS service '{}' ({}) removed", device.name, device.id);
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(e) => {
+                        return Err(DiscoveryError::Mdns(format!("mDNS browse stopped: {}", e)));
+                    }
+                },
+                _ = refresh.tick() => {
+                    for device in online.values() {
+                        callback(device.clone());
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Drop for MdnsService {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+/// 从解析出的 mDNS 服务构造设备信息。旧版服务端没有 `device_id` 时以服务全名代替。
+fn device_from_service(service: &ResolvedService) -> Option<Device> {
+    // Prefer IPv4, then routable IPv6, and pick deterministically when a host has several addresses
+    let mut addresses: Vec<String> = service.addresses
+        .iter()
+        .filter(|ip| !ip.is_loopback() && !ip.to_ip_addr().is_unspecified())
+        .map(|ip| match ip {
+            ScopedIp::V6(v6) if v6.scope_id().index != 0 => format!("{}%{}", v6.addr(), v6.scope_id().index),
+            ip => ip.to_ip_addr().to_string(),
+        })
+        .collect();
+    addresses.sort_by(|a, b| address_rank(a).cmp(&address_rank(b)).then_with(|| a.cmp(b)));
+    let ip = addresses.into_iter().next()?;
+
+    let txt = |key: &str| service.txt_properties.get_property_val_str(key);
+    let name = txt(TXT_NAME)
+        .map(str::to_string)
+        .unwrap_or_else(|| service.fullname.trim_end_matches(SERVICE_TYPE).trim_end_matches('.').to_string());
+    Some(Device {
+        id: txt(TXT_DEVICE_ID).unwrap_or(&service.fullname).to_string(),
+        name,
+        ip,
+        port: service.port,
+        instance_id: txt(TXT_INSTANCE_ID).and_then(|id| id.parse().ok()).unwrap_or(0),
+        capabilities: txt(TXT_CAPABILITIES)
+            .map(|caps| caps.split(',').filter(|c| !c.is_empty()).map(str::to_string).collect())
+            .unwrap_or_default(),
+    })
+}
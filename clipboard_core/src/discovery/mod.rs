@@ -1,8 +1,17 @@
 mod types;
+mod auth;
 mod cache;
 mod multicast;
+mod mdns;
 mod service;
+mod http_scan;
 
-pub use types::{Device, AnnouncementPacket, DiscoveryMethod, DiscoveryError, Result};
-pub use service::DiscoveryService;
-pub use multicast::{MULTICAST_ADDR, MULTICAST_PORT};
+pub use types::{
+    Device, AnnouncementPacket, AuthRequirements, DeviceIdentity, DiscoveryEvent, DiscoveryInfo, DiscoveryPacket, DiscoveryMethod,
+    DiscoveryError, Result, PROTOCOL_VERSION,
+};
+pub use service::{local_device, DiscoveryService};
+pub use auth::{DiscoveryAuth, MAX_CLOCK_SKEW};
+pub use http_scan::{parse_cidr, HttpScanner, DEFAULT_SCAN_PORTS};
+pub use multicast::{network_interfaces, JoinedInterfaces, NetworkInterface, MULTICAST_ADDR, MULTICAST_ADDR_V6, MULTICAST_PORT};
+pub use mdns::SERVICE_TYPE as MDNS_SERVICE_TYPE;
@@ -1,26 +1,105 @@
-use super::types::{DiscoveryPacket, AnnouncementPacket, Device, Result};
-use std::sync::Arc;
+use super::auth::DiscoveryAuth;
+use super::types::{scoped_ip, DiscoveryPacket, AnnouncementPacket, Device, DeviceIdentity, DiscoveryError, Result};
+use std::collections::HashSet;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV6};
+use std::sync::{Arc, Mutex};
 use tokio::net::UdpSocket;
 
 /// UDP Multicast 配置
 pub const MULTICAST_ADDR: &str = "224.0.0.168";
+/// IPv6 链路本地组播地址，与 IPv4 使用同一端口
+pub const MULTICAST_ADDR_V6: &str = "ff02::168";
 pub const MULTICAST_PORT: u16 = 5354;
 pub const ANNOUNCEMENT_INTERVAL_SECS: u64 = 30;
+/// 检查网卡变化的间隔
+pub const INTERFACE_CHECK_INTERVAL_SECS: u64 = 5;
 
-/// UDP Multicast 发现服务
+/// 非环回网卡上的一个地址
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NetworkInterface {
+    /// 网卡名称
+    pub name: String,
+    /// 网卡地址
+    pub ip: IpAddr,
+    /// 网卡序号，IPv6 组播组按序号加入
+    pub index: Option<u32>,
+}
+
+/// 已加入组播组的网卡
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct JoinedInterfaces {
+    /// IPv4 网卡地址，升序
+    pub v4: Vec<Ipv4Addr>,
+    /// IPv6 网卡序号，升序
+    pub v6: Vec<u32>,
+}
+
+/// UDP Multicast 发现服务，同时使用 IPv4 与 IPv6 组播
 pub struct MulticastService {
-    /// UDP Socket
-    socket: Arc<UdpSocket>,
+    /// IPv4 UDP Socket，不可用时为 None
+    socket_v4: Option<Arc<UdpSocket>>,
+    /// IPv6 UDP Socket，不可用时为 None
+    socket_v6: Option<Arc<UdpSocket>>,
     /// 本机设备信息
     my_device: Device,
+    /// 公告签名与校验
+    auth: Arc<DiscoveryAuth>,
+    /// 已加入组播组的网卡
+    membership: Mutex<Membership>,
+}
+
+/// 组播组成员关系，网卡变化时与当前网卡同步
+#[derive(Default)]
+struct Membership {
+    /// 已加入 IPv4 组播组的网卡地址
+    v4: HashSet<Ipv4Addr>,
+    /// 已加入 IPv6 组播组的网卡序号
+    v6: HashSet<u32>,
+    /// 上次枚举到的非环回地址，用于判断地址是否变化
+    addrs: HashSet<IpAddr>,
 }
 
 impl MulticastService {
-    /// 创建新的 Multicast 服务
-    pub async fn new(my_device: Device) -> Result<Self> {
-        use socket2::{Socket, Domain, Type, Protocol};
-        
+    /// 创建新的 Multicast 服务并在当前网卡上加入组播组。IPv4 与 IPv6 中有一个可用即可，都不可用时返回 IPv4 的错误。
+    /// 启动时没有可用网卡也不会失败，网卡出现后由 `refresh_interfaces` 加入
+    pub async fn new(my_device: Device, auth: Arc<DiscoveryAuth>) -> Result<Self> {
         tracing::info!("[Multicast] Initializing service for device: {} (port {})", my_device.name, my_device.port);
+
+        let socket_v4 = Self::bind_v4();
+        let socket_v6 = match Self::bind_v6() {
+            Ok(socket) => Some(Arc::new(socket)),
+            Err(e) => {
+                tracing::warn!("[Multicast] IPv6 multicast unavailable: {}", e);
+                None
+            }
+        };
+        let socket_v4 = match socket_v4 {
+            Ok(socket) => Some(Arc::new(socket)),
+            Err(e) if socket_v6.is_none() => return Err(e.into()),
+            Err(e) => {
+                tracing::warn!("[Multicast] IPv4 multicast unavailable, using IPv6 only: {}", e);
+                None
+            }
+        };
+
+        let service = Self {
+            socket_v4,
+            socket_v6,
+            my_device,
+            auth,
+            membership: Mutex::new(Membership::default()),
+        };
+        if !service.refresh_interfaces() {
+            tracing::warn!("[Multicast] No network interface available yet, joining once one appears");
+        }
+
+        tracing::info!("[Multicast] Service initialization complete");
+        Ok(service)
+    }
+
+    /// 创建 IPv4 Socket，组播组由 `refresh_interfaces` 加入
+    fn bind_v4() -> std::io::Result<UdpSocket> {
+        use socket2::{Socket, Domain, Type, Protocol};
         
         // 创建原始 socket2 Socket
         let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
@@ -42,64 +121,90 @@ impl MulticastService {
         
         // 转换为标准库 UdpSocket，然后再转为 TokioUdpSocket
         let std_socket: std::net::UdpSocket = socket.into();
-        let socket = tokio::net::UdpSocket::from_std(std_socket)?;
-        
-        // 收集所有非环回 IPv4 地址并加入组播组
-        tracing::info!("[Multicast] Joining multicast group on all interfaces...");
-        use local_ip_address::list_afinet_netifas;
-        
-        if let Ok(ifaces) = list_afinet_netifas() {
-            let multicast_addr: std::net::Ipv4Addr = MULTICAST_ADDR.parse()
-                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
-            let mut join_count = 0;
-            
-            for (name, ip) in ifaces {
-                if let std::net::IpAddr::V4(v4_addr) = ip {
-                    if !v4_addr.is_loopback() {
-                        match socket.join_multicast_v4(multicast_addr, v4_addr) {
-                            Ok(_) => {
-                                tracing::info!("  ✓ Joined multicast on interface {} ({})", name, v4_addr);
-                                join_count += 1;
-                            }
-                            Err(e) => {
-                                tracing::warn!("  ✗ Failed to join multicast on {} ({}): {}", name, v4_addr, e);
-                            }
-                        }
-                    }
-                }
-            }
-            
-            if join_count == 0 {
-                tracing::error!("[Multicast] Failed to join multicast on ANY interface!");
-                return Err(std::io::Error::new(std::io::ErrorKind::NotFound, "No valid network interfaces found for multicast").into());
-            } else {
-                tracing::info!("[Multicast] Successfully joined multicast on {} interface(s)", join_count);
+        tokio::net::UdpSocket::from_std(std_socket)
+    }
+
+    /// 创建 IPv6 Socket，链路本地组播组由 `refresh_interfaces` 加入
+    fn bind_v6() -> std::io::Result<UdpSocket> {
+        use socket2::{Socket, Domain, Type, Protocol};
+
+        let socket = Socket::new(Domain::IPV6, Type::DGRAM, Some(Protocol::UDP))?;
+        socket.set_reuse_address(true)?;
+        #[cfg(not(target_os = "windows"))]
+        socket.set_reuse_port(true)?;
+        // Leave IPv4 to the IPv4 socket bound to the same port
+        socket.set_only_v6(true)?;
+
+        let addr = SocketAddr::from((Ipv6Addr::UNSPECIFIED, MULTICAST_PORT));
+        tracing::info!("[Multicast] Binding to {}...", addr);
+        socket.bind(&addr.into())?;
+        socket.set_nonblocking(true)?;
+        UdpSocket::from_std(socket.into())
+    }
+
+    /// 重新枚举网卡并同步组播组成员，返回是否有变化
+    fn refresh_interfaces(&self) -> bool {
+        match network_interfaces() {
+            Ok(interfaces) => self.apply_interfaces(&interfaces),
+            Err(e) => {
+                tracing::warn!("[Multicast] Failed to list network interfaces: {}", e);
+                false
             }
-        } else {
-            tracing::error!("[Multicast] Failed to enumerate network interfaces!");
-            return Err(std::io::Error::new(std::io::ErrorKind::Other, "Failed to list network interfaces").into());
         }
-        
-        tracing::info!("[Multicast] Service initialization complete");
-        
-        Ok(Self {
-            socket: Arc::new(socket),
-            my_device,
-        })
+    }
+
+    /// 按网卡列表同步组播组成员：在新出现的网卡上加入组播组，退出已消失的网卡，并重试之前加入失败的网卡。
+    /// 返回非环回地址集合是否变化或组播组成员是否变化
+    fn apply_interfaces(&self, interfaces: &[NetworkInterface]) -> bool {
+        let addrs: HashSet<IpAddr> = interfaces.iter().map(|iface| iface.ip).collect();
+        let mut membership = self.membership.lock().unwrap_or_else(|e| e.into_inner());
+        let moved = addrs != membership.addrs;
+        let mut changed = false;
+        // Only successful joins are recorded, so failed ones are retried on every check
+        if let Some(socket) = &self.socket_v4 {
+            changed |= sync_v4(socket, &mut membership.v4, ipv4_interfaces(interfaces), !moved);
+        }
+        if let Some(socket) = &self.socket_v6 {
+            changed |= sync_v6(socket, &mut membership.v6, ipv6_interfaces(interfaces), !moved);
+        }
+        membership.addrs = addrs;
+        moved || changed
+    }
+
+    /// 当前已加入组播组的网卡
+    pub fn joined_interfaces(&self) -> JoinedInterfaces {
+        let membership = self.membership.lock().unwrap_or_else(|e| e.into_inner());
+        let mut joined = JoinedInterfaces {
+            v4: membership.v4.iter().copied().collect(),
+            v6: membership.v6.iter().copied().collect(),
+        };
+        joined.v4.sort();
+        joined.v6.sort();
+        joined
     }
 
     /// 发送设备公告到所有网卡
     pub async fn send_announcement(&self) -> Result<()> {
-        let packet = DiscoveryPacket::Announcement(AnnouncementPacket {
-            device_id: self.my_device.id.clone(),
-            alias: self.my_device.name.clone(),
+        // Each address family carries its own nonce, so a copy received over both is not a replay
+        let v4 = DiscoveryPacket::Announcement(Box::new(self.announcement()));
+        let v6 = DiscoveryPacket::Announcement(Box::new(self.announcement()));
+        self.broadcast_packet(&v4, &v6).await
+    }
+
+    /// 构造并签名本机公告
+    fn announcement(&self) -> AnnouncementPacket {
+        let mut packet = AnnouncementPacket {
+            identity: DeviceIdentity::new(&self.my_device),
             port: self.my_device.port,
             instance_id: self.my_device.instance_id,
-            version: env!("CARGO_PKG_VERSION").to_string(),
-            capabilities: self.my_device.capabilities.clone(),
-        });
-        
-        self.broadcast_packet(packet).await
+            timestamp: None,
+            nonce: None,
+            public_key: None,
+            hmac: None,
+            signature: None,
+        };
+        self.auth.sign(&mut packet);
+        packet
     }
 
     /// 发送搜索请求 (主动扫描) 到所有网卡
@@ -108,12 +213,13 @@ impl MulticastService {
             version: Some(env!("CARGO_PKG_VERSION").to_string()),
         });
         
-        self.broadcast_packet(packet).await
+        self.broadcast_packet(&packet, &packet).await
     }
 
-    /// 辅助方法：通过所有网卡广播数据包
-    async fn broadcast_packet(&self, packet: DiscoveryPacket) -> Result<()> {
-        let json = serde_json::to_vec(&packet)?;
+    /// 辅助方法：通过所有网卡广播数据包，IPv4 与 IPv6 分别发送各自的数据包
+    async fn broadcast_packet(&self, v4: &DiscoveryPacket, v6: &DiscoveryPacket) -> Result<()> {
+        let json = serde_json::to_vec(v4)?;
+        let json_v6 = serde_json::to_vec(v6)?;
         let target = format!("{}:{}", MULTICAST_ADDR, MULTICAST_PORT);
         
         // 使用 spawn_blocking 在阻塞线程池中执行多网卡发送
@@ -121,33 +227,37 @@ impl MulticastService {
         let json_clone = json.clone();
         
         tokio::task::spawn_blocking(move || {
-            use local_ip_address::list_afinet_netifas;
-            use std::net::{UdpSocket, IpAddr};
+            use std::net::UdpSocket;
             
             // 多路广播：在每个非环回接口上显式发送
-            if let Ok(ifaces) = list_afinet_netifas() {
-                for (name, ip) in ifaces {
-                    if let IpAddr::V4(v4_addr) = ip {
-                        if !v4_addr.is_loopback() {
-                            // 为每个接口创建一个临时阻塞式 Socket 发送
-                            match UdpSocket::bind((v4_addr, 0)) {
-                                Ok(socket) => {
-                                    socket.set_multicast_ttl_v4(32).ok();
-                                    if let Err(e) = socket.send_to(&json_clone, &target_clone) {
-                                        tracing::warn!("Failed to send discovery on interface {} ({}): {}", name, v4_addr, e);
-                                    } else {
-                                        tracing::debug!("Sent discovery on interface {} ({})", name, v4_addr);
-                                    }
-                                },
-                                Err(e) => {
-                                    tracing::warn!("Could not bind to interface {} ({}) for broadcast: {}", name, v4_addr, e);
-                                }
-                            }
+            let interfaces = match network_interfaces() {
+                Ok(interfaces) => ipv4_interfaces(&interfaces),
+                Err(e) => {
+                    tracing::warn!("Failed to list IPv4 interfaces: {}", e);
+                    return;
+                }
+            };
+            for (name, v4_addr) in interfaces {
+                // 为每个接口创建一个临时阻塞式 Socket 发送
+                match UdpSocket::bind((v4_addr, 0)) {
+                    Ok(socket) => {
+                        socket.set_multicast_ttl_v4(32).ok();
+                        if let Err(e) = socket.send_to(&json_clone, &target_clone) {
+                            tracing::warn!("Failed to send discovery on interface {} ({}): {}", name, v4_addr, e);
+                        } else {
+                            tracing::debug!("Sent discovery on interface {} ({})", name, v4_addr);
                         }
+                    },
+                    Err(e) => {
+                        tracing::warn!("Could not bind to interface {} ({}) for broadcast: {}", name, v4_addr, e);
                     }
                 }
             }
         }).await.ok();
+
+        if self.socket_v6.is_some() {
+            tokio::task::spawn_blocking(move || broadcast_v6(&json_v6)).await.ok();
+        }
         
         Ok(())
     }
@@ -157,26 +267,44 @@ impl MulticastService {
     where
         F: Fn(Device) + Send + 'static,
     {
-        let mut buf = vec![0u8; 2048];
+        let mut buf_v4 = vec![0u8; 2048];
+        let mut buf_v6 = vec![0u8; 2048];
         
         loop {
-            match self.socket.recv_from(&mut buf).await {
-                Ok((len, addr)) => {
+            let received = tokio::select! {
+                result = recv_from(self.socket_v4.as_deref(), &mut buf_v4) => result.map(|(len, addr)| (len, addr, false)),
+                result = recv_from(self.socket_v6.as_deref(), &mut buf_v6) => result.map(|(len, addr)| (len, addr, true)),
+            };
+            match received {
+                Ok((len, addr, v6)) => {
+                    let buf = if v6 { &buf_v6 } else { &buf_v4 };
                     // 解析 JSON 发现包
                     match serde_json::from_slice::<DiscoveryPacket>(&buf[..len]) {
                         Ok(DiscoveryPacket::Announcement(packet)) => {
                             // 忽略自己的公告
-                            if packet.device_id == self.my_device.id {
+                            if packet.identity.device_id == self.my_device.id {
                                 continue;
                             }
+                            match self.auth.verify(&packet) {
+                                Ok(()) => {}
+                                // The same packet arrives once per shared interface
+                                Err(e @ DiscoveryError::Replayed(_)) => {
+                                    tracing::debug!("{} ({})", e, addr);
+                                    continue;
+                                }
+                                Err(e) => {
+                                    tracing::warn!("{} ({})", e, addr);
+                                    continue;
+                                }
+                            }
                             
                             let device = Device {
-                                id: packet.device_id,
-                                name: packet.alias,
-                                ip: addr.ip().to_string(),
+                                id: packet.identity.device_id,
+                                name: packet.identity.alias,
+                                ip: scoped_ip(&addr),
                                 port: packet.port,
                                 instance_id: packet.instance_id,
-                                capabilities: packet.capabilities,
+                                capabilities: packet.identity.capabilities,
                             };
                             
                             callback(device);
@@ -216,4 +344,186 @@ impl MulticastService {
             }
         }
     }
+
+    /// 定期检查网卡变化任务
+    pub async fn watch_interfaces_task(self: Arc<Self>) {
+        let mut interval = tokio::time::interval(
+            std::time::Duration::from_secs(INTERFACE_CHECK_INTERVAL_SECS)
+        );
+        // The first tick completes immediately and `new` already joined the current interfaces
+        interval.tick().await;
+
+        loop {
+            interval.tick().await;
+            self.check_interfaces().await;
+        }
+    }
+
+    /// 检查网卡变化，地址变化后立即公告 (仅当 port > 0 时) 并搜索新网络上的设备。返回是否有变化
+    pub async fn check_interfaces(&self) -> bool {
+        if !self.refresh_interfaces() {
+            return false;
+        }
+        self.announce_change().await;
+        true
+    }
+
+    /// 按给定的网卡列表同步组播组成员，有变化时与 `check_interfaces` 一样公告并搜索。返回是否有变化
+    pub async fn update_interfaces(&self, interfaces: &[NetworkInterface]) -> bool {
+        if !self.apply_interfaces(interfaces) {
+            return false;
+        }
+        self.announce_change().await;
+        true
+    }
+
+    async fn announce_change(&self) {
+        tracing::info!("[Multicast] Network interfaces changed, announcing again");
+        if self.my_device.port > 0 {
+            if let Err(e) = self.send_announcement().await {
+                tracing::error!("Failed to send announcement after interface change: {}", e);
+            }
+        }
+        if let Err(e) = self.send_search().await {
+            tracing::error!("Failed to send search after interface change: {}", e);
+        }
+    }
+}
+
+/// 让 IPv4 Socket 的组播组成员与网卡列表一致，返回成员是否变化。`retrying` 时加入失败只记录调试日志
+fn sync_v4(socket: &UdpSocket, joined: &mut HashSet<Ipv4Addr>, mut interfaces: Vec<(String, Ipv4Addr)>, retrying: bool) -> bool {
+    let Ok(group) = MULTICAST_ADDR.parse::<Ipv4Addr>() else {
+        return false;
+    };
+    let before = joined.clone();
+    let current: HashSet<Ipv4Addr> = interfaces.iter().map(|(_, addr)| *addr).collect();
+    for addr in joined.difference(&current).copied().collect::<Vec<_>>() {
+        // The kernel drops the membership itself when the interface goes away
+        match socket.leave_multicast_v4(group, addr) {
+            Ok(()) => tracing::info!("  ✓ Left multicast on vanished address {}", addr),
+            Err(e) => tracing::debug!("  Leaving multicast on vanished address {}: {}", addr, e),
+        }
+        joined.remove(&addr);
+    }
+    interfaces.retain(|(_, addr)| !joined.contains(addr));
+    for (name, addr) in interfaces {
+        match socket.join_multicast_v4(group, addr) {
+            Ok(()) => tracing::info!("  ✓ Joined multicast on interface {} ({})", name, addr),
+            // A new address on an interface that already joined
+            Err(e) if e.kind() == std::io::ErrorKind::AddrInUse => {
+                tracing::debug!("  Multicast already joined on interface {} ({})", name, addr)
+            }
+            Err(e) if retrying => {
+                tracing::debug!("  ✗ Still failing to join multicast on {} ({}): {}", name, addr, e);
+                continue;
+            }
+            Err(e) => {
+                tracing::warn!("  ✗ Failed to join multicast on {} ({}): {}", name, addr, e);
+                continue;
+            }
+        }
+        joined.insert(addr);
+    }
+    *joined != before
+}
+
+/// 让 IPv6 Socket 的组播组成员与网卡列表中带 IPv6 地址的网卡一致，返回成员是否变化
+fn sync_v6(socket: &UdpSocket, joined: &mut HashSet<u32>, mut interfaces: Vec<(String, u32)>, retrying: bool) -> bool {
+    let Ok(group) = MULTICAST_ADDR_V6.parse::<Ipv6Addr>() else {
+        return false;
+    };
+    let before = joined.clone();
+    let current: HashSet<u32> = interfaces.iter().map(|(_, index)| *index).collect();
+    for index in joined.difference(&current).copied().collect::<Vec<_>>() {
+        match socket.leave_multicast_v6(&group, index) {
+            Ok(()) => tracing::info!("  ✓ Left IPv6 multicast on vanished interface (index {})", index),
+            Err(e) => tracing::debug!("  Leaving IPv6 multicast on vanished interface (index {}): {}", index, e),
+        }
+        joined.remove(&index);
+    }
+    interfaces.retain(|(_, index)| !joined.contains(index));
+    for (name, index) in interfaces {
+        match socket.join_multicast_v6(&group, index) {
+            Ok(()) => tracing::info!("  ✓ Joined IPv6 multicast on interface {} (index {})", name, index),
+            Err(e) if e.kind() == std::io::ErrorKind::AddrInUse => {
+                tracing::debug!("  IPv6 multicast already joined on interface {} (index {})", name, index)
+            }
+            Err(e) if retrying => {
+                tracing::debug!("  ✗ Still failing to join IPv6 multicast on {} (index {}): {}", name, index, e);
+                continue;
+            }
+            Err(e) => {
+                tracing::warn!("  ✗ Failed to join IPv6 multicast on {} (index {}): {}", name, index, e);
+                continue;
+            }
+        }
+        joined.insert(index);
+    }
+    *joined != before
+}
+
+/// 从可能不存在的 Socket 接收数据，不存在时永不返回
+async fn recv_from(socket: Option<&UdpSocket>, buf: &mut [u8]) -> std::io::Result<(usize, SocketAddr)> {
+    match socket {
+        Some(socket) => socket.recv_from(buf).await,
+        None => std::future::pending().await,
+    }
+}
+
+/// 当前非环回网卡的地址，每个地址一项。
+/// 不使用 `local_ip_address`：它每次调用都会遗留一个 netlink Socket 与线程
+pub fn network_interfaces() -> std::io::Result<Vec<NetworkInterface>> {
+    Ok(if_addrs::get_if_addrs()?
+        .into_iter()
+        .filter(|iface| !iface.is_loopback())
+        .map(|iface| NetworkInterface {
+            ip: iface.ip(),
+            name: iface.name,
+            index: iface.index,
+        })
+        .collect())
+}
+
+/// 网卡的 IPv4 地址（网卡名称与地址）
+fn ipv4_interfaces(interfaces: &[NetworkInterface]) -> Vec<(String, Ipv4Addr)> {
+    interfaces
+        .iter()
+        .filter_map(|iface| match iface.ip {
+            IpAddr::V4(ip) => Some((iface.name.clone(), ip)),
+            IpAddr::V6(_) => None,
+        })
+        .collect()
+}
+
+/// 带 IPv6 地址的网卡（名称与序号），每张网卡一项
+fn ipv6_interfaces(interfaces: &[NetworkInterface]) -> Vec<(String, u32)> {
+    let mut result: Vec<(String, u32)> = Vec::new();
+    for iface in interfaces.iter().filter(|iface| iface.ip.is_ipv6()) {
+        if let Some(index) = iface.index.filter(|i| !result.iter().any(|(_, j)| j == i)) {
+            result.push((iface.name.clone(), index));
+        }
+    }
+    result
+}
+
+/// 在每张带 IPv6 地址的网卡上发送到链路本地组播组，组播地址的接口序号决定出口网卡
+fn broadcast_v6(json: &[u8]) {
+    let Ok(group) = MULTICAST_ADDR_V6.parse::<Ipv6Addr>() else {
+        return;
+    };
+    let interfaces = match network_interfaces() {
+        Ok(interfaces) => ipv6_interfaces(&interfaces),
+        Err(e) => {
+            tracing::warn!("Failed to list IPv6 interfaces: {}", e);
+            return;
+        }
+    };
+    for (name, index) in interfaces {
+        let result = std::net::UdpSocket::bind((Ipv6Addr::UNSPECIFIED, 0))
+            .and_then(|socket| socket.send_to(json, SocketAddrV6::new(group, MULTICAST_PORT, 0, index)));
+        match result {
+            Ok(_) => tracing::debug!("Sent discovery on interface {} (IPv6)", name),
+            Err(e) => tracing::warn!("Failed to send IPv6 discovery on interface {}: {}", name, e),
+        }
+    }
 }
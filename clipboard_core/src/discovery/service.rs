@@ -1,32 +1,73 @@
+use super::auth::DiscoveryAuth;
 use super::cache::DeviceCache;
-use super::multicast::{MulticastService, ANNOUNCEMENT_INTERVAL_SECS};
-use super::types::{Device, DiscoveryMethod, Result};
-use std::sync::Arc;
+use super::http_scan::HttpScanner;
+use super::mdns::MdnsService;
+use super::multicast::{JoinedInterfaces, MulticastService, NetworkInterface, ANNOUNCEMENT_INTERVAL_SECS, INTERFACE_CHECK_INTERVAL_SECS};
+use super::types::{Device, DiscoveryEvent, DiscoveryMethod, Result};
+use crate::config::Config;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
 
 /// 设备缓存 TTL（90秒，3倍公告间隔）
 const CACHE_TTL_SECS: u64 = ANNOUNCEMENT_INTERVAL_SECS * 3;
 
-/// 缓存清理间隔（60秒）
-const CLEANUP_INTERVAL_SECS: u64 = 60;
+/// 缓存清理间隔（10秒），决定设备过期事件的延迟
+const CLEANUP_INTERVAL_SECS: u64 = 10;
 
-/// 混合发现服务
+/// 混合发现服务：UDP Multicast 与 mDNS 发现的设备汇入同一缓存，按设备 ID 去重
 pub struct DiscoveryService {
-    /// UDP Multicast 服务
-    multicast: Arc<MulticastService>,
+    /// UDP Multicast 服务，不可用时为 None
+    multicast: Option<Arc<MulticastService>>,
+    /// mDNS 注册与浏览服务，不可用时为 None
+    mdns: Option<Arc<MdnsService>>,
     /// 设备缓存
     cache: Arc<DeviceCache>,
-    /// 本机设备信息（保留用于未来功能扩展）
-    #[allow(dead_code)]
+    /// 本机设备信息
     my_device: Device,
+    /// 公告认证
+    auth: Arc<DiscoveryAuth>,
+    /// 取消所有后台任务，`shutdown` 或释放服务时触发
+    cancel: CancellationToken,
+    /// `start` 启动的后台任务
+    tasks: Mutex<Vec<JoinHandle<()>>>,
 }
 
 impl DiscoveryService {
-    /// 创建新的发现服务
+    /// 创建新的发现服务，不认证公告
     pub async fn new(device: Device) -> Result<Self> {
-        // 创建 UDP Multicast 服务
-        let multicast = Arc::new(MulticastService::new(device.clone()).await?);
-        
+        Self::with_auth(device, DiscoveryAuth::default()).await
+    }
+
+    /// 按配置创建本机的发现服务。`port` 为服务端口，为 0 时只发现其他设备，不公告自己
+    pub async fn from_config(config: &Config, port: u16) -> Result<Self> {
+        let auth = DiscoveryAuth::from_config(&config.discovery)?;
+        Self::with_auth(local_device(config, port), auth).await
+    }
+
+    /// 创建使用公告认证的发现服务。两种发现方式中有一种可用即可，都不可用时返回 UDP Multicast 的错误
+    pub async fn with_auth(device: Device, auth: DiscoveryAuth) -> Result<Self> {
+        let auth = Arc::new(auth);
+        // 创建 UDP Multicast 服务与 mDNS 服务
+        let multicast = MulticastService::new(device.clone(), auth.clone()).await;
+        let mdns = match MdnsService::new(device.clone()) {
+            Ok(mdns) => Some(Arc::new(mdns)),
+            Err(e) => {
+                tracing::warn!("mDNS discovery unavailable: {}", e);
+                None
+            }
+        };
+        let multicast = match multicast {
+            Ok(multicast) => Some(Arc::new(multicast)),
+            Err(e) if mdns.is_none() => return Err(e),
+            Err(e) => {
+                tracing::warn!("UDP multicast discovery unavailable, using mDNS only: {}", e);
+                None
+            }
+        };
+
         // 创建设备缓存（90秒 TTL）
         let cache = Arc::new(DeviceCache::new(Duration::from_secs(CACHE_TTL_SECS)));
         
@@ -34,55 +75,156 @@ impl DiscoveryService {
         
         Ok(Self {
             multicast,
+            mdns,
             cache,
             my_device: device,
+            auth,
+            cancel: CancellationToken::new(),
+            tasks: Mutex::new(Vec::new()),
         })
     }
 
-    /// 启动所有发现任务
+    /// 本机设备信息
+    pub fn device(&self) -> &Device {
+        &self.my_device
+    }
+
+    /// 公告认证设置
+    pub fn auth(&self) -> &DiscoveryAuth {
+        &self.auth
+    }
+
+    /// 设置或移除 mDNS TXT 记录中的附加属性（如证书指纹），已注册时立即重新公告
+    pub fn set_property(&self, key: &str, value: Option<String>) -> Result<()> {
+        match &self.mdns {
+            Some(mdns) => mdns.set_property(key, value),
+            None => Ok(()),
+        }
+    }
+
+    /// 启动所有发现任务。重复调用不会重复启动，`shutdown` 之后不能再启动
     pub async fn start(self: Arc<Self>) {
-        tracing::info!("Starting discovery service tasks...");
+        if self.cancel.is_cancelled() {
+            tracing::warn!("Discovery service was shut down, not starting it again");
+            return;
+        }
+        {
+            let mut tasks = self.tasks.lock().unwrap_or_else(|e| e.into_inner());
+            if !tasks.is_empty() {
+                tracing::debug!("Discovery service already started");
+                return;
+            }
+            tracing::info!("Starting discovery service tasks...");
+            self.spawn_tasks(&mut tasks);
+        }
         
-        // 任务 1: UDP Multicast 定期公告 (仅当 port > 0 时)
-        if self.my_device.port > 0 {
-            let multicast_clone = self.multicast.clone();
-            tokio::spawn(async move {
-                tracing::info!("Starting periodic announcement task (every {}s)", ANNOUNCEMENT_INTERVAL_SECS);
-                multicast_clone.periodic_announcement_task().await;
-            });
+        // 立即发送一次公告 (仅当 port > 0 时)
+        if let Some(multicast) = self.multicast.as_ref().filter(|_| self.my_device.port > 0) {
+            if let Err(e) = multicast.send_announcement().await {
+                tracing::error!("Failed to send initial announcement: {}", e);
+            }
         }
         
-        // 任务 2: UDP Multicast 监听 (包括对搜索请求的响应)
-        let multicast_clone = self.multicast.clone();
-        let cache_clone = self.cache.clone();
-        tokio::spawn(async move {
-            tracing::info!("Starting UDP multicast discovery listener...");
-            let _ = multicast_clone.listen_discovery(move |device| {
-                let cache = cache_clone.clone();
-                tokio::spawn(async move {
-                    cache.register_device(device, DiscoveryMethod::UdpMulticast).await;
+        tracing::info!("All discovery tasks started successfully");
+    }
+
+    /// 在后台运行任务，服务关闭时取消
+    fn spawn(&self, tasks: &mut Vec<JoinHandle<()>>, task: impl Future<Output = ()> + Send + 'static) {
+        let cancel = self.cancel.clone();
+        tasks.push(tokio::spawn(async move {
+            tokio::select! {
+                _ = cancel.cancelled() => {}
+                _ = task => {}
+            }
+        }));
+    }
+
+    fn spawn_tasks(&self, tasks: &mut Vec<JoinHandle<()>>) {
+        if let Some(multicast) = &self.multicast {
+            // 任务 1: UDP Multicast 定期公告 (仅当 port > 0 时)
+            if self.my_device.port > 0 {
+                let multicast_clone = multicast.clone();
+                self.spawn(tasks, async move {
+                    tracing::info!("Starting periodic announcement task (every {}s)", ANNOUNCEMENT_INTERVAL_SECS);
+                    multicast_clone.periodic_announcement_task().await;
                 });
-            }).await;
-        });
+            }
+
+            // 任务 2: UDP Multicast 监听 (包括对搜索请求的响应)
+            let multicast_clone = multicast.clone();
+            let cache_clone = self.cache.clone();
+            self.spawn(tasks, async move {
+                tracing::info!("Starting UDP multicast discovery listener...");
+                let _ = multicast_clone.listen_discovery(move |device| {
+                    let cache = cache_clone.clone();
+                    tokio::spawn(async move {
+                        cache.register_device(device, DiscoveryMethod::UdpMulticast).await;
+                    });
+                }).await;
+            });
+
+            // 任务 3: 网卡变化时重新加入组播组并立即公告
+            let multicast_clone = multicast.clone();
+            self.spawn(tasks, async move {
+                tracing::info!("Watching network interfaces (every {}s)", INTERFACE_CHECK_INTERVAL_SECS);
+                multicast_clone.watch_interfaces_task().await;
+            });
+        }
+
+        if let Some(mdns) = &self.mdns {
+            // 任务 4: mDNS 注册 (仅当 port > 0 时) 与浏览
+            if self.my_device.port > 0 {
+                if let Err(e) = mdns.register() {
+                    tracing::error!("{}", e);
+                }
+            }
+        }
+        // mDNS records cannot be authenticated, so only signed multicast announcements count
+        if self.auth.is_required() {
+            tracing::info!("Discovery authentication enabled, ignoring devices found via mDNS");
+        }
+        if let Some(mdns) = self.mdns.as_ref().filter(|_| !self.auth.is_required()) {
+            let mdns_clone = mdns.clone();
+            let cache_clone = self.cache.clone();
+            self.spawn(tasks, async move {
+                tracing::info!("Starting mDNS browser...");
+                let result = mdns_clone.browse(move |device| {
+                    let cache = cache_clone.clone();
+                    tokio::spawn(async move {
+                        cache.register_device(device, DiscoveryMethod::Mdns).await;
+                    });
+                }).await;
+                if let Err(e) = result {
+                    tracing::error!("{}", e);
+                }
+            });
+        }
         
-        // 任务 3: 定期清理过期缓存
+        // 任务 5: 定期清理过期缓存
         let cache_clone = self.cache.clone();
-        tokio::spawn(async move {
+        self.spawn(tasks, async move {
             let mut interval = tokio::time::interval(Duration::from_secs(CLEANUP_INTERVAL_SECS));
             loop {
                 interval.tick().await;
                 cache_clone.cleanup_expired().await;
             }
         });
-        
-        // 立即发送一次公告 (仅当 port > 0 时)
-        if self.my_device.port > 0 {
-            if let Err(e) = self.multicast.send_announcement().await {
-                tracing::error!("Failed to send initial announcement: {}", e);
-            }
+    }
+
+    /// 停止所有后台任务并注销 mDNS 服务，等待任务结束后返回。Socket 在服务释放时关闭
+    pub async fn shutdown(&self) {
+        self.cancel.cancel();
+        let tasks = std::mem::take(&mut *self.tasks.lock().unwrap_or_else(|e| e.into_inner()));
+        futures_util::future::join_all(tasks).await;
+        if let Some(mdns) = &self.mdns {
+            mdns.shutdown();
         }
-        
-        tracing::info!("All discovery tasks started successfully");
+        tracing::info!("Discovery service for device '{}' shut down", self.my_device.name);
+    }
+
+    /// 订阅设备的发现、变化、重启与过期事件。只会收到订阅之后的事件，已发现的设备用 `get_devices` 获取
+    pub fn subscribe(&self) -> tokio::sync::broadcast::Receiver<DiscoveryEvent> {
+        self.cache.subscribe()
     }
 
     /// 获取已发现的设备列表
@@ -90,10 +232,56 @@ impl DiscoveryService {
         self.cache.get_devices().await
     }
 
-    /// 手动触发扫描（发送搜索请求）
+    /// 按设备 ID 查找已发现的设备
+    pub async fn get_device(&self, id: &str) -> Option<Device> {
+        self.cache.get_device(id).await
+    }
+
+    /// 手动触发扫描（发送搜索请求）。mDNS 浏览持续进行，无需触发
     pub async fn scan(&self) -> Result<()> {
         tracing::debug!("Manual scan triggered");
-        self.multicast.send_search().await
+        match &self.multicast {
+            Some(multicast) => multicast.send_search().await,
+            None => Ok(()),
+        }
+    }
+
+    /// 立即检查网卡变化，不必等待下一次定期检查（如收到系统的网络切换通知时）。
+    /// 地址变化时重新加入组播组并公告，返回地址是否变化
+    pub async fn refresh_interfaces(&self) -> bool {
+        match &self.multicast {
+            Some(multicast) => multicast.check_interfaces().await,
+            None => false,
+        }
+    }
+
+    /// 按给定的网卡列表同步组播组成员（如平台无法枚举网卡时由调用方提供），有变化时重新公告并搜索。
+    /// 下一次定期检查仍以枚举到的网卡为准。返回是否有变化
+    pub async fn update_interfaces(&self, interfaces: &[NetworkInterface]) -> bool {
+        match &self.multicast {
+            Some(multicast) => multicast.update_interfaces(interfaces).await,
+            None => false,
+        }
+    }
+
+    /// 当前已加入组播组的网卡
+    pub fn joined_interfaces(&self) -> JoinedInterfaces {
+        self.multicast.as_ref().map(|multicast| multicast.joined_interfaces()).unwrap_or_default()
+    }
+
+    /// 跨网段 HTTP 扫描，响应的设备加入缓存，返回发现的设备数。
+    /// 启用公告认证时扫描结果无法认证，不加入缓存
+    pub async fn scan_http(&self, scanner: &HttpScanner) -> usize {
+        let devices = scanner.scan().await;
+        let found = devices.len();
+        if self.auth.is_required() {
+            tracing::info!("Discovery authentication enabled, ignoring {} device(s) found by HTTP scan", found);
+            return 0;
+        }
+        for device in devices.into_iter().filter(|d| d.id != self.my_device.id) {
+            self.cache.register_device(device, DiscoveryMethod::HttpScan).await;
+        }
+        found
     }
 
     /// 获取设备数量
@@ -101,3 +289,34 @@ impl DiscoveryService {
         self.cache.count().await
     }
 }
+
+impl Drop for DiscoveryService {
+    fn drop(&mut self) {
+        // Background tasks hold the sockets; stop them even without `shutdown`
+        self.cancel.cancel();
+    }
+}
+
+/// 按配置生成本机设备信息，实例 ID 取当前时间。`port` 为 0 时只发现其他设备，不公告自己；
+/// 启用 mesh 的服务端附带 `mesh` 能力
+pub fn local_device(config: &Config, port: u16) -> Device {
+    let instance_id = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let mut capabilities = Vec::new();
+    if port > 0 {
+        capabilities.extend(["clipboard", "file", "history"].map(String::from));
+        if config.mesh.enabled {
+            capabilities.push(crate::mesh::MESH_CAPABILITY.to_string());
+        }
+    }
+    Device {
+        id: config.general.device_id.clone(),
+        name: config.general.device_name.clone(),
+        ip: "0.0.0.0".to_string(),
+        port,
+        instance_id,
+        capabilities,
+    }
+}
@@ -1,23 +1,69 @@
 use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, Ipv6Addr, SocketAddr, SocketAddrV6};
 use std::time::Instant;
 
 /// 设备信息
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Device {
     /// 设备唯一标识
     pub id: String,
     /// 设备名称
     pub name: String,
-    /// IP 地址
+    /// IP 地址。IPv6 链路本地地址带 `%` 与接口序号，如 `fe80::1%3`
     pub ip: String,
     /// 服务端口
     pub port: u16,
-    /// 实例 ID（用于重启检测）
+    /// 实例 ID（用于重启检测），0 表示未知（如旧版服务端）
     pub instance_id: u64,
     /// 能力列表
     pub capabilities: Vec<String>,
 }
 
+impl Device {
+    /// 该设备服务的访问地址，如 `http://192.168.1.10:5033`。
+    /// 带接口序号的 IPv6 地址按 RFC 6874 写作 `[fe80::1%253]`
+    pub fn url(&self, scheme: &str) -> String {
+        match self.socket_addr() {
+            Some(SocketAddr::V6(addr)) if addr.scope_id() != 0 => {
+                format!("{}://[{}%25{}]:{}", scheme, addr.ip(), addr.scope_id(), self.port)
+            }
+            Some(SocketAddr::V6(addr)) => format!("{}://[{}]:{}", scheme, addr.ip(), self.port),
+            _ => format!("{}://{}:{}", scheme, self.ip, self.port),
+        }
+    }
+
+    /// 该设备服务的套接字地址，IPv6 链路本地地址保留接口序号
+    pub fn socket_addr(&self) -> Option<SocketAddr> {
+        match self.ip.split_once('%') {
+            Some((ip, scope)) => {
+                let ip: Ipv6Addr = ip.parse().ok()?;
+                Some(SocketAddrV6::new(ip, self.port, 0, scope.parse().ok()?).into())
+            }
+            None => Some(SocketAddr::new(self.ip.parse().ok()?, self.port)),
+        }
+    }
+}
+
+/// 数据包来源的 IP 地址，IPv6 带接口序号时写作 `fe80::1%3`
+pub(crate) fn scoped_ip(addr: &SocketAddr) -> String {
+    match addr {
+        SocketAddr::V6(addr) if addr.scope_id() != 0 => format!("{}%{}", addr.ip(), addr.scope_id()),
+        addr => addr.ip().to_string(),
+    }
+}
+
+/// 地址的优先级，越小越优先：IPv4，其次可路由的 IPv6，
+/// 需要接口序号的链路本地地址最后（多数 HTTP 客户端无法在 URL 中使用接口序号）
+pub(crate) fn address_rank(ip: &str) -> u8 {
+    let (ip, _) = ip.split_once('%').unwrap_or((ip, ""));
+    match ip.parse::<IpAddr>() {
+        Ok(IpAddr::V4(_)) => 0,
+        Ok(IpAddr::V6(ip)) if ip.segments()[0] & 0xffc0 != 0xfe80 => 1,
+        Ok(IpAddr::V6(_)) => 2,
+        Err(_) => 3,
+    }
+}
+
 /// UDP 发现数据包封装
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type")]
@@ -27,7 +73,7 @@ pub enum DiscoveryPacket {
     Search(SearchPacket),
     /// 设备公告（由服务端发起）
     #[serde(rename = "announcement")]
-    Announcement(AnnouncementPacket),
+    Announcement(Box<AnnouncementPacket>),
 }
 
 /// 搜索数据包
@@ -37,21 +83,132 @@ pub struct SearchPacket {
     pub version: Option<String>,
 }
 
-/// UDP 公告数据包
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct AnnouncementPacket {
+/// 当前发现协议版本，公告或认证格式不兼容地变化时递增
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// 设备身份，UDP 公告与 `/api/discovery` 共用
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeviceIdentity {
     /// 设备唯一标识
     pub device_id: String,
     /// 设备别名/名称
+    #[serde(default)]
     pub alias: String,
+    /// 软件版本
+    pub version: String,
+    /// 发现协议版本，旧版本没有此字段时为 0
+    #[serde(default)]
+    pub protocol_version: u32,
+    /// 能力列表
+    #[serde(default)]
+    pub capabilities: Vec<String>,
+}
+
+impl DeviceIdentity {
+    /// 本机设备的身份
+    pub fn new(device: &Device) -> Self {
+        Self {
+            device_id: device.id.clone(),
+            alias: device.name.clone(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            protocol_version: PROTOCOL_VERSION,
+            capabilities: device.capabilities.clone(),
+        }
+    }
+}
+
+/// UDP 公告数据包
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnnouncementPacket {
+    /// 设备身份
+    #[serde(flatten)]
+    pub identity: DeviceIdentity,
     /// 服务端口
     pub port: u16,
     /// 实例 ID（用于重启检测）
     pub instance_id: u64,
-    /// 协议版本
-    pub version: String,
-    /// 能力列表
-    pub capabilities: Vec<String>,
+    /// 发送时间（Unix 毫秒），用于拒绝过期公告
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<u64>,
+    /// 随机数，用于拒绝重放
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+    /// 发送者的 Ed25519 公钥（Base64）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub public_key: Option<String>,
+    /// 以共享密钥计算的 HMAC-SHA256（Base64）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hmac: Option<String>,
+    /// 以发送者私钥计算的 Ed25519 签名（Base64）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+}
+
+/// `/api/discovery` 的响应
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiscoveryInfo {
+    /// 服务标识，固定为 `SyncClipboard`
+    pub service: String,
+    /// 设备身份
+    #[serde(flatten)]
+    pub identity: DeviceIdentity,
+    /// 设备名称，与 `alias` 相同，保留给旧客户端
+    pub device_name: String,
+    /// 实例 ID（用于重启检测），旧版服务端没有此字段时为 0
+    #[serde(default)]
+    pub instance_id: u64,
+    /// TLS 证书的 SHA-256 指纹，用于固定证书（仅 HTTPS）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls_fingerprint: Option<String>,
+    /// 为本机公告签名的 Ed25519 公钥（Base64），用于 `discovery.trusted_devices`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub public_key: Option<String>,
+    /// 连接所需的认证
+    #[serde(default)]
+    pub auth: AuthRequirements,
+}
+
+impl DiscoveryInfo {
+    /// 设备名称，旧版服务端只返回 `device_name`
+    pub fn name(&self) -> &str {
+        if self.identity.alias.is_empty() { &self.device_name } else { &self.identity.alias }
+    }
+}
+
+/// 服务端要求的认证
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AuthRequirements {
+    /// 访问 API 需要 Bearer Token
+    pub token: bool,
+    /// 只接受通过认证的局域网公告（共享密钥或受信任公钥）
+    pub discovery: bool,
+}
+
+/// 设备缓存的变化事件，序列化为 `{"kind": "device_added", "device": {...}}`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", content = "device", rename_all = "snake_case")]
+pub enum DiscoveryEvent {
+    /// 发现新设备
+    DeviceAdded(Device),
+    /// 设备的地址、名称或能力变化
+    DeviceUpdated(Device),
+    /// 设备重启（实例 ID 变化）
+    DeviceRestarted(Device),
+    /// 设备超过 TTL 未出现，已从缓存移除
+    DeviceExpired(Device),
+}
+
+impl DiscoveryEvent {
+    /// 事件涉及的设备
+    pub fn device(&self) -> &Device {
+        match self {
+            Self::DeviceAdded(device)
+            | Self::DeviceUpdated(device)
+            | Self::DeviceRestarted(device)
+            | Self::DeviceExpired(device) => device,
+        }
+    }
 }
 
 /// 设备缓存条目
@@ -61,6 +218,8 @@ pub(crate) struct DeviceEntry {
     pub device: Device,
     /// 最后发现时间
     pub last_seen: Instant,
+    /// 当前地址最后一次被报告的时间
+    pub address_seen: Instant,
     /// 发现方法（保留用于未来统计和调试）
     #[allow(dead_code)]
     pub discovery_method: DiscoveryMethod,
@@ -73,6 +232,8 @@ pub enum DiscoveryMethod {
     Mdns,
     /// UDP Multicast 发现
     UdpMulticast,
+    /// 跨网段 HTTP 扫描发现
+    HttpScan,
 }
 
 /// 发现服务错误类型
@@ -89,6 +250,12 @@ pub enum DiscoveryError {
     
     #[error("Invalid configuration: {0}")]
     InvalidConfig(String),
+
+    #[error("Rejected announcement: {0}")]
+    Rejected(String),
+
+    #[error("Replayed announcement: {0}")]
+    Replayed(String),
 }
 
 pub type Result<T> = std::result::Result<T, DiscoveryError>;
@@ -0,0 +1,116 @@
+//! 上传前的内容过滤
+//!
+//! 按 `[filters]` 配置判断本地剪贴板内容是否应该同步：类型开关、文本（及其 HTML）大小上限、
+//! 正则拒绝列表，以及两次上传之间的最小间隔。
+
+use crate::clipboard::html_to_text;
+use crate::config::FilterConfig;
+use regex::Regex;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// 内容被拦截的原因
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum Blocked {
+    #[error("{0} sync is disabled")]
+    TypeDisabled(&'static str),
+
+    #[error("text is {size} bytes, limit is {max}")]
+    TooLarge { size: usize, max: usize },
+
+    /// 只记录命中的规则，不记录内容本身
+    #[error("matches deny pattern '{0}'")]
+    Denied(String),
+
+    #[error("last upload was less than {0:?} ago")]
+    TooSoon(Duration),
+}
+
+pub struct SyncFilter {
+    config: FilterConfig,
+    deny: Vec<Regex>,
+    last_upload: Mutex<Option<Instant>>,
+}
+
+impl SyncFilter {
+    /// 非法的正则会被忽略并记录警告，不影响其他规则
+    pub fn new(config: &FilterConfig) -> Self {
+        let deny = config
+            .deny_patterns
+            .iter()
+            .filter_map(|pattern| match Regex::new(pattern) {
+                Ok(re) => Some(re),
+                Err(e) => {
+                    tracing::warn!("Ignoring invalid deny pattern '{}': {}", pattern, e);
+                    None
+                }
+            })
+            .collect();
+        Self {
+            config: config.clone(),
+            deny,
+            last_upload: Mutex::new(None),
+        }
+    }
+
+    pub fn check_text(&self, text: &str) -> Result<(), Blocked> {
+        if !self.config.text {
+            return Err(Blocked::TypeDisabled("text"));
+        }
+        self.check_size(text)?;
+        self.check_denied(text)
+    }
+
+    /// 检查文本附带的 HTML：大小上限作用于 HTML 本身；拒绝列表同时匹配源码与纯文本，
+    /// 被标签或实体拆开的内容也会命中
+    pub fn check_html(&self, html: &str) -> Result<(), Blocked> {
+        if html.is_empty() {
+            return Ok(());
+        }
+        self.check_size(html)?;
+        self.check_denied(html)?;
+        self.check_denied(&html_to_text(html))
+    }
+
+    fn check_size(&self, text: &str) -> Result<(), Blocked> {
+        let max = self.config.max_text_bytes;
+        if max > 0 && text.len() > max {
+            return Err(Blocked::TooLarge { size: text.len(), max });
+        }
+        Ok(())
+    }
+
+    fn check_denied(&self, text: &str) -> Result<(), Blocked> {
+        match self.deny.iter().find(|re| re.is_match(text)) {
+            Some(re) => Err(Blocked::Denied(re.as_str().to_string())),
+            None => Ok(()),
+        }
+    }
+
+    /// 是否随文本一起上传 HTML 格式
+    pub fn allow_html(&self) -> bool {
+        self.config.html
+    }
+
+    pub fn check_image(&self) -> Result<(), Blocked> {
+        if self.config.image { Ok(()) } else { Err(Blocked::TypeDisabled("image")) }
+    }
+
+    pub fn check_file(&self) -> Result<(), Blocked> {
+        if self.config.file { Ok(()) } else { Err(Blocked::TypeDisabled("file")) }
+    }
+
+    /// 距离上次上传是否已超过最小间隔
+    pub fn check_interval(&self) -> Result<(), Blocked> {
+        let min = Duration::from_millis(self.config.min_interval_ms);
+        match *self.last_upload.lock().unwrap() {
+            Some(last) if last.elapsed() < min => Err(Blocked::TooSoon(min)),
+            _ => Ok(()),
+        }
+    }
+
+    /// 记录一次成功上传，用于最小间隔判断
+    pub fn record_upload(&self) {
+        *self.last_upload.lock().unwrap() = Some(Instant::now());
+    }
+}
@@ -0,0 +1,230 @@
+//! 全局快捷键
+//!
+//! 解析 `[hotkeys]` 中 `Ctrl+Alt+V` 形式的组合键，并通过 rdev 监听系统级按键事件，
+//! 命中绑定时把对应动作发送给调用方。
+
+use crate::config::HotkeyConfig;
+use rdev::EventType;
+pub use rdev::Key;
+use serde::Serialize;
+use std::str::FromStr;
+use tokio::sync::mpsc;
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum HotkeyError {
+    #[error("unknown key '{key}' in '{combo}'")]
+    UnknownKey { combo: String, key: String },
+
+    #[error("'{0}' needs at least one modifier (Ctrl, Alt, Shift, Meta)")]
+    NoModifier(String),
+
+    #[error("'{0}' must contain exactly one non-modifier key")]
+    KeyCount(String),
+
+    #[error("'{combo}' is bound to both {first} and {second}")]
+    Duplicate { combo: String, first: &'static str, second: &'static str },
+}
+
+/// 快捷键触发的动作
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HotkeyAction {
+    Push,
+    Pull,
+    TogglePause,
+    HistoryPicker,
+}
+
+impl HotkeyAction {
+    /// 对应的配置项名称
+    pub fn name(self) -> &'static str {
+        match self {
+            HotkeyAction::Push => "push",
+            HotkeyAction::Pull => "pull",
+            HotkeyAction::TogglePause => "toggle_pause",
+            HotkeyAction::HistoryPicker => "history_picker",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Modifiers {
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+    pub meta: bool,
+}
+
+impl Modifiers {
+    fn is_empty(&self) -> bool {
+        !(self.ctrl || self.alt || self.shift || self.meta)
+    }
+
+    /// 按键为修饰键时更新状态并返回 true
+    fn update(&mut self, key: Key, pressed: bool) -> bool {
+        let slot = match key {
+            Key::ControlLeft | Key::ControlRight => &mut self.ctrl,
+            Key::Alt | Key::AltGr => &mut self.alt,
+            Key::ShiftLeft | Key::ShiftRight => &mut self.shift,
+            Key::MetaLeft | Key::MetaRight => &mut self.meta,
+            _ => return false,
+        };
+        *slot = pressed;
+        true
+    }
+}
+
+/// 一个组合键：若干修饰键加一个普通键
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Hotkey {
+    pub modifiers: Modifiers,
+    pub key: Key,
+}
+
+impl FromStr for Hotkey {
+    type Err = HotkeyError;
+
+    fn from_str(combo: &str) -> Result<Self, Self::Err> {
+        let mut modifiers = Modifiers::default();
+        let mut key = None;
+        for part in combo.split('+').map(str::trim) {
+            match part.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => modifiers.ctrl = true,
+                "alt" | "option" => modifiers.alt = true,
+                "shift" => modifiers.shift = true,
+                "meta" | "super" | "win" | "cmd" | "command" => modifiers.meta = true,
+                name => {
+                    let parsed = parse_key(name).ok_or_else(|| HotkeyError::UnknownKey {
+                        combo: combo.to_string(),
+                        key: part.to_string(),
+                    })?;
+                    if key.replace(parsed).is_some() {
+                        return Err(HotkeyError::KeyCount(combo.to_string()));
+                    }
+                }
+            }
+        }
+        let key = key.ok_or_else(|| HotkeyError::KeyCount(combo.to_string()))?;
+        if modifiers.is_empty() {
+            return Err(HotkeyError::NoModifier(combo.to_string()));
+        }
+        Ok(Self { modifiers, key })
+    }
+}
+
+fn parse_key(name: &str) -> Option<Key> {
+    const LETTERS: [Key; 26] = [
+        Key::KeyA, Key::KeyB, Key::KeyC, Key::KeyD, Key::KeyE, Key::KeyF, Key::KeyG,
+        Key::KeyH, Key::KeyI, Key::KeyJ, Key::KeyK, Key::KeyL, Key::KeyM, Key::KeyN,
+        Key::KeyO, Key::KeyP, Key::KeyQ, Key::KeyR, Key::KeyS, Key::KeyT, Key::KeyU,
+        Key::KeyV, Key::KeyW, Key::KeyX, Key::KeyY, Key::KeyZ,
+    ];
+    const DIGITS: [Key; 10] = [
+        Key::Num0, Key::Num1, Key::Num2, Key::Num3, Key::Num4,
+        Key::Num5, Key::Num6, Key::Num7, Key::Num8, Key::Num9,
+    ];
+    const FUNCTION: [Key; 12] = [
+        Key::F1, Key::F2, Key::F3, Key::F4, Key::F5, Key::F6,
+        Key::F7, Key::F8, Key::F9, Key::F10, Key::F11, Key::F12,
+    ];
+
+    if let [c] = name.as_bytes() {
+        match c {
+            b'a'..=b'z' => return Some(LETTERS[(c - b'a') as usize]),
+            b'0'..=b'9' => return Some(DIGITS[(c - b'0') as usize]),
+            _ => {}
+        }
+    }
+    if let Some(n) = name.strip_prefix('f').and_then(|n| n.parse::<usize>().ok()) {
+        return FUNCTION.get(n.checked_sub(1)?).copied();
+    }
+    let key = match name {
+        "up" => Key::UpArrow,
+        "down" => Key::DownArrow,
+        "left" => Key::LeftArrow,
+        "right" => Key::RightArrow,
+        "space" => Key::Space,
+        "enter" | "return" => Key::Return,
+        "tab" => Key::Tab,
+        "esc" | "escape" => Key::Escape,
+        "backspace" => Key::Backspace,
+        "delete" | "del" => Key::Delete,
+        "insert" | "ins" => Key::Insert,
+        "home" => Key::Home,
+        "end" => Key::End,
+        "pageup" => Key::PageUp,
+        "pagedown" => Key::PageDown,
+        "," | "comma" => Key::Comma,
+        "." | "period" => Key::Dot,
+        "/" | "slash" => Key::Slash,
+        "\\" | "backslash" => Key::BackSlash,
+        ";" | "semicolon" => Key::SemiColon,
+        "'" | "quote" => Key::Quote,
+        "`" | "backquote" => Key::BackQuote,
+        "-" | "minus" => Key::Minus,
+        "=" | "equal" => Key::Equal,
+        "[" => Key::LeftBracket,
+        "]" => Key::RightBracket,
+        _ => return None,
+    };
+    Some(key)
+}
+
+impl HotkeyConfig {
+    /// 解析所有非空绑定。任一组合键非法或被绑定到多个动作时返回错误。
+    pub fn bindings(&self) -> Result<Vec<(Hotkey, HotkeyAction)>, HotkeyError> {
+        let entries = [
+            (&self.push, HotkeyAction::Push),
+            (&self.pull, HotkeyAction::Pull),
+            (&self.toggle_pause, HotkeyAction::TogglePause),
+            (&self.history_picker, HotkeyAction::HistoryPicker),
+        ];
+        let mut bindings: Vec<(Hotkey, HotkeyAction)> = Vec::new();
+        for (combo, action) in entries {
+            if combo.trim().is_empty() {
+                continue;
+            }
+            let hotkey: Hotkey = combo.parse()?;
+            if let Some((_, first)) = bindings.iter().find(|(bound, _)| *bound == hotkey) {
+                return Err(HotkeyError::Duplicate {
+                    combo: combo.clone(),
+                    first: first.name(),
+                    second: action.name(),
+                });
+            }
+            bindings.push((hotkey, action));
+        }
+        Ok(bindings)
+    }
+}
+
+/// 在独立线程中监听全局按键，命中绑定时发送对应动作。
+///
+/// rdev 的监听会一直阻塞所在线程且无法取消，线程随进程退出；
+/// 接收端被丢弃后按键事件会被直接忽略。
+pub fn spawn_listener(bindings: Vec<(Hotkey, HotkeyAction)>, actions: mpsc::UnboundedSender<HotkeyAction>) {
+    std::thread::spawn(move || {
+        let mut modifiers = Modifiers::default();
+        // Key auto-repeat sends repeated presses; fire once until the key is released
+        let mut fired: Option<Key> = None;
+        let result = rdev::listen(move |event| match event.event_type {
+            EventType::KeyPress(key) => {
+                if modifiers.update(key, true) || fired == Some(key) {
+                    return;
+                }
+                let hotkey = Hotkey { modifiers, key };
+                if let Some((_, action)) = bindings.iter().find(|(bound, _)| *bound == hotkey) {
+                    fired = Some(key);
+                    let _ = actions.send(*action);
+                }
+            }
+            EventType::KeyRelease(key) if !modifiers.update(key, false) && fired == Some(key) => {
+                fired = None;
+            }
+            _ => {}
+        });
+        if let Err(e) = result {
+            tracing::error!("Global hotkey listener failed: {:?}", e);
+        }
+    });
+}
@@ -2,8 +2,13 @@ pub mod config;
 pub mod clipboard;
 pub mod clipboard_handler;
 pub mod sync;
+pub mod remote;
+pub mod tls;
+pub mod filter;
+pub mod hotkey;
 pub mod mobile_api;
 pub mod discovery;
+pub mod mesh;
 
 uniffi::setup_scaffolding!();
 pub mod crypto;
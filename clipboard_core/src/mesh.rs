@@ -0,0 +1,186 @@
+//! 无中心服务器的点对点同步（mesh）
+//!
+//! 启用 `[mesh]` 的设备由各自的服务端给本机写入的条目打上 Lamport 时间戳，并直接推送给
+//! 局域网内发现的其他节点。节点只接受比当前条目更新的时间戳，所有设备按同一顺序取舍，
+//! 最终保留同一份最新内容，任何一台设备离线都不影响其他设备之间的同步。
+
+use crate::clipboard::ClipboardData;
+use crate::config::{ClientTlsConfig, Config, MeshConfig};
+use crate::discovery::{Device, MAX_CLOCK_SKEW};
+use crate::tls::{self, TlsError};
+use anyhow::Result;
+use reqwest::{Client, StatusCode};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::fs::File;
+use tokio_util::io::ReaderStream;
+
+/// 启用 mesh 的设备在发现公告中附带的能力
+pub const MESH_CAPABILITY: &str = "mesh";
+/// 推送请求中时间戳的时钟值
+pub const HEADER_CLOCK: &str = "X-Mesh-Clock";
+/// 推送请求中时间戳的来源设备 ID
+pub const HEADER_ORIGIN: &str = "X-Mesh-Origin";
+
+/// 连接节点的超时，离线节点不会拖住推送
+const CONNECT_TIMEOUT_SECS: u64 = 5;
+
+/// 条目的 Lamport 时间戳。先比较时钟，相同时比较来源设备 ID，因此所有设备的排序一致
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Stamp {
+    pub clock: u64,
+    pub origin: String,
+}
+
+impl fmt::Display for Stamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}@{}", self.clock, self.origin)
+    }
+}
+
+/// Lamport 时钟。本地事件的时钟不小于当前毫秒时间戳，重启后不会落后于重启前发出的时间戳
+#[derive(Debug, Default)]
+pub struct LamportClock {
+    value: u64,
+}
+
+impl LamportClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 从持久化的时钟值继续，如重启后从数据库中最新的时间戳恢复
+    pub fn resume(value: u64) -> Self {
+        Self { value }
+    }
+
+    /// 当前时钟值
+    pub fn value(&self) -> u64 {
+        self.value
+    }
+
+    /// 本地事件：推进时钟并返回新值
+    pub fn tick(&mut self) -> u64 {
+        // Observed clocks are bounded by wall time, so this cannot overflow
+        self.value = (self.value + 1).max(unix_millis());
+        self.value
+    }
+
+    /// 收到其他节点的时间戳，之后的本地事件排在它之后。
+    /// 超前本机时间超过 `MAX_CLOCK_SKEW` 的时钟会把所有节点推到尽头，拒绝并返回 false
+    pub fn observe(&mut self, clock: u64) -> bool {
+        if clock > unix_millis() + MAX_CLOCK_SKEW.as_millis() as u64 {
+            return false;
+        }
+        self.value = self.value.max(clock);
+        true
+    }
+}
+
+fn unix_millis() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// 能否确认节点身份：发现公告经过认证，或以 https 连接并固定了证书指纹。
+/// 否则任何主机都能以启用 mesh 的节点身份出现，收到令牌与敏感条目，因此不能启用 mesh
+pub fn peers_authenticated(config: &MeshConfig, discovery_authenticated: bool) -> bool {
+    discovery_authenticated || (config.scheme == "https" && config.tls.fingerprint.is_some())
+}
+
+/// 推送结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PushOutcome {
+    /// 节点接受了条目
+    Accepted,
+    /// 节点已有更新的条目
+    Stale,
+}
+
+/// 向其他节点推送条目的 HTTP 客户端
+pub struct MeshClient {
+    my_id: String,
+    scheme: String,
+    tls: ClientTlsConfig,
+    token: Option<String>,
+    /// 允许的节点，为空时接受所有节点
+    devices: Vec<String>,
+    /// 按节点 `host:port` 缓存的客户端
+    clients: Mutex<HashMap<String, Client>>,
+}
+
+impl MeshClient {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            my_id: config.general.device_id.clone(),
+            scheme: config.mesh.scheme.clone(),
+            tls: config.mesh.tls.clone(),
+            token: config.auth.token.clone(),
+            devices: config.mesh.devices.clone(),
+            clients: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 设备是否为可推送的节点：启用了 mesh 的其他服务端，且在允许列表中
+    pub fn is_peer(&self, device: &Device) -> bool {
+        device.id != self.my_id
+            && device.port > 0
+            && device.capabilities.iter().any(|c| c == MESH_CAPABILITY)
+            && (self.devices.is_empty() || self.devices.contains(&device.id))
+    }
+
+    /// 节点的 HTTP 客户端。`server` 为 `host:port`，同时用作首次信任记录的键
+    fn client(&self, server: &str) -> Result<Client, TlsError> {
+        let mut clients = self.clients.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(client) = clients.get(server) {
+            return Ok(client.clone());
+        }
+        let builder = Client::builder().connect_timeout(Duration::from_secs(CONNECT_TIMEOUT_SECS));
+        let client = tls::configure(builder, &self.tls, server)?.build()?;
+        clients.insert(server.to_string(), client.clone());
+        Ok(client)
+    }
+
+    fn authorize(&self, req: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        match &self.token {
+            Some(token) => req.header("Authorization", format!("Bearer {}", token)),
+            None => req,
+        }
+    }
+
+    /// 把条目推送到节点。`blob` 为图片或文件条目在本机的文件，先于条目上传
+    pub async fn push(&self, peer: &Device, data: &ClipboardData, stamp: &Stamp, blob: Option<&Path>) -> Result<PushOutcome> {
+        let client = self.client(&format!("{}:{}", peer.ip, peer.port))?;
+        let base_url = peer.url(&self.scheme);
+
+        if let Some(path) = blob {
+            let filename = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .ok_or_else(|| anyhow::anyhow!("Invalid file name: {:?}", path))?;
+            let body = reqwest::Body::wrap_stream(ReaderStream::new(File::open(path).await?));
+            self.authorize(client.put(format!("{}/file/{}", base_url, filename)))
+                .body(body)
+                .send()
+                .await?
+                .error_for_status()?;
+        }
+
+        let response = self.authorize(client.put(format!("{}/SyncClipboard.json", base_url)))
+            .header(HEADER_CLOCK, stamp.clock.to_string())
+            .header(HEADER_ORIGIN, &stamp.origin)
+            .json(data)
+            .send()
+            .await?;
+        if response.status() == StatusCode::CONFLICT {
+            return Ok(PushOutcome::Stale);
+        }
+        response.error_for_status()?;
+        Ok(PushOutcome::Accepted)
+    }
+}
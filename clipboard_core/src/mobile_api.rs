@@ -47,6 +47,13 @@ impl MobileSyncManager {
                 enabled: true,
                 remote_host: server_url,
                 remote_port: server_port,
+                concealed: Default::default(),
+                mode: Default::default(),
+                push_mode: Default::default(),
+                remotes: Default::default(),
+                scheme: "http".to_string(),
+                tls: Default::default(),
+                server_id: None,
             },
             auth: AuthConfig {
                 username: None,
@@ -58,11 +65,17 @@ impl MobileSyncManager {
                 max_count: 100,
                 log_retention_days: 7,
                 db_path: "mobile_history.db".to_string(),
+                database_url: None,
+                retention: Default::default(),
             },
             general: crate::config::GeneralConfig {
                 device_name: "Mobile".to_string(),
                 device_id: uuid::Uuid::new_v4().to_string(),
             },
+            filters: Default::default(),
+            hotkeys: Default::default(),
+            discovery: Default::default(),
+            mesh: Default::default(),
         };
 
         let clipboard = ClipboardHandler::new().map_err(|e| anyhow!("Failed to init clipboard: {}", e))?;
@@ -0,0 +1,313 @@
+//! 单个远端服务器的 HTTP 客户端
+//!
+//! 负责与一个远端（`[[client.remotes]]` 中的一项）之间的上传、下载与端到端加解密，
+//! 并按路由规则判断本地内容是否应发送到该远端。
+
+use crate::clipboard::ClipboardData;
+use crate::config::{ConcealedConfig, ContentKind, RemoteConfig, RouteConfig, SyncDirection};
+use crate::crypto;
+use crate::tls::{self, TlsError};
+use anyhow::Result;
+use base64::{engine::general_purpose, Engine as _};
+use regex::Regex;
+use reqwest::Client;
+use std::path::Path;
+use std::sync::RwLock;
+use tokio::fs::File;
+use tokio_util::io::ReaderStream;
+
+/// 待发送的本地内容，用于路由判断
+#[derive(Debug, Clone, Copy)]
+pub enum Outgoing<'a> {
+    Text(&'a str),
+    Image,
+    File,
+}
+
+impl Outgoing<'_> {
+    fn kind(&self) -> ContentKind {
+        match self {
+            Outgoing::Text(_) => ContentKind::Text,
+            Outgoing::Image => ContentKind::Image,
+            Outgoing::File => ContentKind::File,
+        }
+    }
+}
+
+/// 编译后的路由规则
+pub struct Route {
+    types: Vec<ContentKind>,
+    include: Vec<Regex>,
+    exclude: Vec<Regex>,
+}
+
+impl Route {
+    /// 非法的正则会被忽略并记录警告，不影响其他规则
+    pub fn new(config: &RouteConfig) -> Self {
+        let compile = |patterns: &[String]| {
+            patterns
+                .iter()
+                .filter_map(|pattern| match Regex::new(pattern) {
+                    Ok(re) => Some(re),
+                    Err(e) => {
+                        tracing::warn!("Ignoring invalid route pattern '{}': {}", pattern, e);
+                        None
+                    }
+                })
+                .collect::<Vec<_>>()
+        };
+        Self {
+            types: config.types.clone(),
+            include: compile(&config.include),
+            exclude: compile(&config.exclude),
+        }
+    }
+
+    pub fn accepts(&self, item: Outgoing) -> bool {
+        if !self.types.is_empty() && !self.types.contains(&item.kind()) {
+            return false;
+        }
+        match item {
+            Outgoing::Text(text) => {
+                (self.include.is_empty() || self.include.iter().any(|re| re.is_match(text)))
+                    && !self.exclude.iter().any(|re| re.is_match(text))
+            }
+            _ => true,
+        }
+    }
+}
+
+/// 隐藏内容以敏感条目发送：服务端不写入历史，过期或所有设备取走后销毁
+fn conceal(data: ClipboardData, concealed: &ConcealedConfig) -> ClipboardData {
+    let ttl = Some(concealed.ttl_secs).filter(|ttl| *ttl > 0);
+    data.into_sensitive().with_ttl(ttl)
+}
+
+/// 同步客户端发送的设备 ID 请求头
+pub const HEADER_DEVICE_ID: &str = "X-Device-Id";
+
+pub struct Remote {
+    pub name: String,
+    pub direction: SyncDirection,
+    /// 要跟随的服务端设备 ID
+    pub server_id: Option<String>,
+    route: Route,
+    client: Client,
+    /// 配置中的地址，跟随的服务端未被发现时使用
+    configured_url: String,
+    base_url: RwLock<String>,
+    token: Option<String>,
+    encrypt_password: Option<String>,
+    device_name: String,
+}
+
+impl Remote {
+    /// `device_id` 随每个请求发送，服务端以它区分客户端。CA 证书无法读取、指纹格式错误等情况下返回错误
+    pub fn new(config: &RemoteConfig, device_name: &str, device_id: &str) -> Result<Self, TlsError> {
+        let url = config.url.trim().trim_end_matches('/');
+        let base_url = if url.contains("://") { url.to_string() } else { format!("http://{}", url) };
+        // Advertise the sync direction so the server can list it under connected devices
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert("X-Sync-Direction", reqwest::header::HeaderValue::from_static(config.direction.as_str()));
+        // Identifies this client to the server, e.g. to tell when every device has fetched a secret
+        if let Ok(value) = reqwest::header::HeaderValue::from_str(device_id) {
+            headers.insert(HEADER_DEVICE_ID, value);
+        }
+        // Trust-on-first-use records are keyed by host:port
+        let server = reqwest::Url::parse(&base_url)
+            .ok()
+            .and_then(|u| Some(format!("{}:{}", u.host_str()?, u.port_or_known_default()?)))
+            .unwrap_or_else(|| base_url.clone());
+        let builder = tls::configure(Client::builder().default_headers(headers), &config.tls, &server)?;
+        let client = builder.build()?;
+        Ok(Self {
+            name: config.name.clone(),
+            direction: config.direction,
+            server_id: config.server_id.clone(),
+            route: Route::new(&config.route),
+            client,
+            configured_url: base_url.clone(),
+            base_url: RwLock::new(base_url),
+            token: config.token.clone(),
+            encrypt_password: config.encrypt_password.clone(),
+            device_name: device_name.to_string(),
+        })
+    }
+
+    /// 是否应把该内容上传到此远端（考虑同步方向与路由规则）
+    pub fn wants(&self, item: Outgoing) -> bool {
+        self.direction.can_send() && self.route.accepts(item)
+    }
+
+    /// 当前使用的服务地址
+    pub fn base_url(&self) -> String {
+        self.base_url.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// 配置地址使用的协议，跟随发现的服务端时沿用
+    pub fn scheme(&self) -> &str {
+        self.configured_url.split_once("://").map_or("http", |(scheme, _)| scheme)
+    }
+
+    /// 切换到发现的服务地址，`None` 表示回到配置中的地址。地址有变化时返回 true，
+    /// 进行中的请求不受影响。
+    pub fn relocate(&self, url: Option<&str>) -> bool {
+        let url = url.unwrap_or(&self.configured_url);
+        let mut current = self.base_url.write().unwrap_or_else(|e| e.into_inner());
+        if *current == url {
+            return false;
+        }
+        *current = url.to_string();
+        true
+    }
+
+    fn clipboard_url(&self) -> String {
+        format!("{}/SyncClipboard.json", self.base_url())
+    }
+
+    fn file_url(&self, filename: &str) -> String {
+        format!("{}/file/{}", self.base_url(), filename)
+    }
+
+    fn authorize(&self, req: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        match &self.token {
+            Some(token) => req.header("Authorization", format!("Bearer {}", token)),
+            None => req,
+        }
+    }
+
+    fn encrypt(&self, plain: String) -> Result<String> {
+        match &self.encrypt_password {
+            Some(password) => {
+                let encrypted = crypto::encrypt(plain.as_bytes(), password)
+                    .map_err(|e| anyhow::anyhow!("Encryption failed: {}", e))?;
+                Ok(format!("E2EE::{}", general_purpose::STANDARD.encode(encrypted)))
+            }
+            None => Ok(plain),
+        }
+    }
+
+    /// 解密 `E2EE::` 前缀的内容，失败时保持原样
+    fn decrypt(&self, value: &mut String) {
+        let (Some(password), Some(b64)) = (&self.encrypt_password, value.strip_prefix("E2EE::")) else {
+            return;
+        };
+        let decrypted = general_purpose::STANDARD
+            .decode(b64)
+            .ok()
+            .and_then(|bytes| crypto::decrypt(&bytes, password).ok())
+            .and_then(|bytes| String::from_utf8(bytes).ok());
+        if let Some(plain) = decrypted {
+            *value = plain;
+        }
+    }
+
+    async fn put_metadata(&self, data: &ClipboardData) -> Result<()> {
+        self.authorize(self.client.put(self.clipboard_url()))
+            .json(data)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    /// `concealed` 不为空时以敏感条目发送
+    pub async fn upload_text(&self, text: String, html: Option<String>, concealed: Option<&ConcealedConfig>) -> Result<()> {
+        let content = self.encrypt(text)?;
+        let html = match html {
+            Some(raw) => match self.encrypt(raw) {
+                Ok(h) => Some(h),
+                Err(e) => {
+                    tracing::error!("HTML {}", e);
+                    None
+                }
+            },
+            None => None,
+        };
+
+        let mut data = ClipboardData::new_text(content);
+        if let ClipboardData::Text { html: h, device: d, .. } = &mut data {
+            *h = html;
+            *d = Some(self.device_name.clone());
+        }
+        if let Some(concealed) = concealed {
+            data = conceal(data, concealed);
+        }
+        self.put_metadata(&data).await
+    }
+
+    pub async fn upload_image(&self, filename: String, bytes: Vec<u8>, hash: String, concealed: Option<&ConcealedConfig>) -> Result<()> {
+        // 1. Upload file
+        self.authorize(self.client.put(self.file_url(&filename)))
+            .body(bytes)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        // 2. Update metadata
+        let mut data = ClipboardData::Image {
+            hash: Some(hash),
+            filename,
+            device: Some(self.device_name.clone()),
+            ttl: None,
+            sensitive: false,
+        };
+        if let Some(concealed) = concealed {
+            data = conceal(data, concealed);
+        }
+        self.put_metadata(&data).await
+    }
+
+    /// 以流式上传文件，服务端文件名为哈希加原扩展名
+    pub async fn upload_file_stream(&self, path: &Path, hash: String) -> Result<()> {
+        let file = File::open(path).await?;
+        let body = reqwest::Body::wrap_stream(ReaderStream::new(file));
+
+        let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+        let remote_filename = if extension.is_empty() { hash.clone() } else { format!("{}.{}", hash, extension) };
+
+        // Chunked transfer encoding, the server handles streaming bodies without Content-Length
+        self.authorize(self.client.put(self.file_url(&remote_filename)))
+            .body(body)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        let data = ClipboardData::File {
+            hash: Some(hash),
+            filename: remote_filename,
+            device: Some(self.device_name.clone()),
+            ttl: None,
+            sensitive: false,
+        };
+        self.put_metadata(&data).await
+    }
+
+    /// 获取服务端最新内容。`wait` 大于 0 时长轮询，直到 ID 不同于 `last_id` 或超时。
+    /// 最新条目已销毁或过期时服务端返回 `204`，此时只更新 ID，不返回内容。
+    pub async fn download(&self, wait: u64, last_id: i64) -> Result<(Option<ClipboardData>, i64)> {
+        let url = format!("{}?wait={}&last_id={}", self.clipboard_url(), wait, last_id);
+        let resp = self.authorize(self.client.get(&url)).send().await?;
+        if !resp.status().is_success() {
+            return Ok((None, last_id));
+        }
+
+        let id = resp.headers()
+            .get("X-Clipboard-Id")
+            .and_then(|h| h.to_str().ok())
+            .and_then(|s| s.parse::<i64>().ok())
+            .unwrap_or(0);
+        if resp.status() == reqwest::StatusCode::NO_CONTENT {
+            return Ok((None, id));
+        }
+        let mut data: ClipboardData = resp.json().await?;
+
+        if let ClipboardData::Text { content, html, .. } = &mut data {
+            self.decrypt(content);
+            if let Some(h) = html {
+                self.decrypt(h);
+            }
+        }
+        Ok((Some(data), id))
+    }
+}
@@ -1,296 +1,712 @@
 use crate::clipboard_handler::ClipboardHandler;
 use crate::clipboard::ClipboardData;
-use crate::config::Config;
+use crate::config::{ConcealedConfig, ConcealedMode, Config, DiscoveryConfig, SyncDirection, SyncMode};
+use crate::discovery::{local_device, Device, DiscoveryAuth, DiscoveryService};
+use crate::filter::{Blocked, SyncFilter};
+use crate::remote::{Outgoing, Remote};
+use futures_util::future::{join_all, select_all};
+use serde::Serialize;
 use std::sync::Arc;
-use tokio::time::{sleep, Duration};
-use reqwest::Client;
+use tokio::sync::{broadcast, mpsc, oneshot, watch};
+use tokio::time::{sleep, Duration, Instant};
 use anyhow::Result;
 // use uuid::Uuid;
 // use image::ImageEncoder;
 use sha2::{Sha256, Digest};
-use base64::{Engine as _, engine::general_purpose};
-use crate::crypto;
 use std::path::PathBuf;
-use tokio::fs::File;
-use tokio_util::io::ReaderStream;
+
+/// 按设备 ID 跟随发现缓存中的服务端。地址变化或服务端重启（`instance_id` 变化）时通知同步循环，
+/// 设备从缓存中过期后回到配置地址。`targets` 为 (远端下标, 设备 ID, 协议)。
+async fn follow_servers(discovery: Arc<DiscoveryService>, targets: Vec<(usize, String, String)>, commands: mpsc::Sender<Command>) {
+    let mut events = discovery.subscribe();
+    // Last (url, instance_id) applied for each target
+    let mut applied: Vec<Option<(String, u64)>> = vec![None; targets.len()];
+    // Devices found before subscribing, or all of them after missing events
+    let mut changed: Option<String> = None;
+    loop {
+        for ((remote, server_id, scheme), applied) in targets.iter().zip(applied.iter_mut()) {
+            if changed.as_ref().is_some_and(|id| id != server_id) {
+                continue;
+            }
+            let found = discovery.get_device(server_id).await.map(|d| (d.url(scheme), d.instance_id));
+            let command = match (&found, &*applied) {
+                (Some(now), Some(before)) if now == before => continue,
+                (Some((url, instance_id)), before) => {
+                    let restarted = before.as_ref().is_some_and(|(_, id)| id != instance_id);
+                    if restarted {
+                        tracing::info!("Followed server '{}' restarted", server_id);
+                    }
+                    Command::Relocate { remote: *remote, url: Some(url.clone()), restarted }
+                }
+                (None, Some(_)) => {
+                    tracing::info!("Followed server '{}' is gone, using the configured address", server_id);
+                    Command::Relocate { remote: *remote, url: None, restarted: false }
+                }
+                (None, None) => continue,
+            };
+            *applied = found;
+            if commands.send(command).await.is_err() {
+                return;
+            }
+        }
+        changed = match events.recv().await {
+            Ok(event) => Some(event.device().id.clone()),
+            Err(broadcast::error::RecvError::Lagged(_)) => None,
+            Err(broadcast::error::RecvError::Closed) => return,
+        };
+    }
+}
+
+/// 同步客户端的运行状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SyncState {
+    Running,
+    Paused,
+    /// `run` 已退出，不能再恢复
+    Stopped,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SyncStatus {
+    pub state: SyncState,
+    pub push_mode: SyncMode,
+    pub mode: SyncDirection,
+}
+
+enum Command {
+    Push(oneshot::Sender<Result<()>>),
+    Pull(oneshot::Sender<Result<()>>),
+    /// 跟随的服务端地址变化（`None` 为回到配置地址）或已重启
+    Relocate { remote: usize, url: Option<String>, restarted: bool },
+}
+
+/// 本地剪贴板与服务端的已同步内容，用于判断变化、避免回环上传
+#[derive(Default)]
+struct Synced {
+    text: String,
+    html: String,
+    image_hash: String,
+    /// 以下按下标与 `SyncManager::remotes` 对应：各远端最后看到的条目 ID
+    last_ids: Vec<i64>,
+    /// 下载失败的远端在此时间之前不再轮询
+    retry_at: Vec<Option<Instant>>,
+}
+
+/// `SyncManager` 的控制句柄，可克隆并跨线程使用
+#[derive(Clone)]
+pub struct SyncHandle {
+    state: Arc<watch::Sender<SyncState>>,
+    push_mode: Arc<watch::Sender<SyncMode>>,
+    direction: SyncDirection,
+    commands: mpsc::Sender<Command>,
+}
+
+impl SyncHandle {
+    /// 暂停自动同步。暂停期间复制的内容在恢复后也不会被上传。
+    pub fn pause(&self) {
+        self.state.send_if_modified(|state| {
+            let changed = *state == SyncState::Running;
+            if changed {
+                *state = SyncState::Paused;
+            }
+            changed
+        });
+    }
+
+    pub fn resume(&self) {
+        self.state.send_if_modified(|state| {
+            let changed = *state == SyncState::Paused;
+            if changed {
+                *state = SyncState::Running;
+            }
+            changed
+        });
+    }
+
+    /// 停止同步，`run` 会在当前操作结束后返回
+    pub fn stop(&self) {
+        self.state.send_replace(SyncState::Stopped);
+    }
+
+    pub fn set_push_mode(&self, mode: SyncMode) {
+        self.push_mode.send_replace(mode);
+    }
+
+    pub fn status(&self) -> SyncStatus {
+        SyncStatus {
+            state: *self.state.borrow(),
+            push_mode: *self.push_mode.borrow(),
+            mode: self.direction,
+        }
+    }
+
+    /// 立即发送当前剪贴板（暂停或手动模式下同样有效），返回上传结果
+    pub async fn push_now(&self) -> Result<()> {
+        self.request(Command::Push).await
+    }
+
+    /// 立即从服务端取回最新内容并写入本地剪贴板，即使内容与上次同步相同。
+    /// 暂停期间同样可用。
+    pub async fn pull_now(&self) -> Result<()> {
+        self.request(Command::Pull).await
+    }
+
+    async fn request(&self, command: fn(oneshot::Sender<Result<()>>) -> Command) -> Result<()> {
+        if *self.state.borrow() == SyncState::Stopped {
+            return Err(anyhow::anyhow!("Sync client is stopped"));
+        }
+        let (reply, result) = oneshot::channel();
+        self.commands
+            .send(command(reply))
+            .await
+            .map_err(|_| anyhow::anyhow!("Sync client is not running"))?;
+        result.await.map_err(|_| anyhow::anyhow!("Sync client stopped before the request finished"))?
+    }
+}
 
 pub struct SyncManager {
     clipboard: Arc<ClipboardHandler>,
-    client: Client,
-    server_url: String,
-    token: Option<String>,
-    encrypt_password: Option<String>,
-    device_name: String,
+    /// 按配置顺序排列，第一个可接收的远端用于手动拉取
+    remotes: Vec<Remote>,
+    concealed: ConcealedConfig,
+    filter: SyncFilter,
+    state: Arc<watch::Sender<SyncState>>,
+    push_mode: Arc<watch::Sender<SyncMode>>,
+    direction: SyncDirection,
+    commands_tx: mpsc::Sender<Command>,
+    commands: tokio::sync::Mutex<mpsc::Receiver<Command>>,
+    /// 有远端设置了 `server_id` 时，用于在局域网中查找服务端的本机身份
+    scanner: Option<Device>,
+    /// 跟随服务端时校验发现公告
+    discovery: DiscoveryConfig,
+    /// 调用方共用的发现服务，未设置时跟随服务端需要自行创建
+    shared_discovery: Option<Arc<DiscoveryService>>,
+}
+
+/// 跟随服务端的后台任务
+struct Follower {
+    task: tokio::task::JoinHandle<()>,
+    /// 自行创建的发现服务，停止时关闭；共用的服务由调用方关闭
+    owned: Option<Arc<DiscoveryService>>,
+}
+
+impl Follower {
+    async fn stop(self) {
+        self.task.abort();
+        if let Some(discovery) = self.owned {
+            discovery.shutdown().await;
+        }
+    }
 }
 
 impl SyncManager {
     pub fn new(config: &Config, clipboard: Arc<ClipboardHandler>) -> Self {
-        let server_url = format!("http://{}:{}/SyncClipboard.json", config.client.remote_host, config.client.remote_port);
+        let remotes: Vec<Remote> = config
+            .remotes()
+            .iter()
+            .filter_map(|remote| match Remote::new(remote, &config.general.device_name, &config.general.device_id) {
+                Ok(r) => Some(r),
+                Err(e) => {
+                    // Never fall back to an unverified connection
+                    tracing::error!("Skipping remote '{}': {}", remote.name, e);
+                    None
+                }
+            })
+            .collect();
+        // Overall direction across all remotes, reported in the status
+        let direction = match (
+            remotes.iter().any(|r| r.direction.can_send()),
+            remotes.iter().any(|r| r.direction.can_receive()),
+        ) {
+            (true, false) => SyncDirection::Send,
+            (false, true) => SyncDirection::Receive,
+            _ => SyncDirection::Both,
+        };
+        // Only listens, never announces
+        let scanner = remotes.iter().any(|r| r.server_id.is_some()).then(|| local_device(config, 0));
+        let (commands_tx, commands) = mpsc::channel(8);
         Self {
             clipboard,
-            client: Client::new(),
-            server_url,
-            token: config.auth.token.clone(),
-            encrypt_password: config.auth.encrypt_password.clone(),
-            device_name: config.general.device_name.clone(),
+            remotes,
+            concealed: config.client.concealed.clone(),
+            filter: SyncFilter::new(&config.filters),
+            state: Arc::new(watch::channel(SyncState::Running).0),
+            push_mode: Arc::new(watch::channel(config.client.push_mode).0),
+            direction,
+            commands_tx,
+            commands: tokio::sync::Mutex::new(commands),
+            scanner,
+            discovery: config.discovery.clone(),
+            shared_discovery: None,
+        }
+    }
+
+    /// 跟随服务端时使用已启动的发现服务，而不是另建一个
+    pub fn with_discovery(mut self, discovery: Arc<DiscoveryService>) -> Self {
+        self.shared_discovery = Some(discovery);
+        self
+    }
+
+    /// 获取控制句柄，可在其他任务或线程中暂停、恢复、停止同步或手动发送
+    pub fn handle(&self) -> SyncHandle {
+        SyncHandle {
+            state: self.state.clone(),
+            push_mode: self.push_mode.clone(),
+            direction: self.direction,
+            commands: self.commands_tx.clone(),
         }
     }
 
     pub async fn run(&self) {
-        let mut last_clipboard_content = String::new();
-        let mut last_clipboard_html = String::new(); // Track HTML separately
-        let mut last_image_hash = String::new(); // Track Image Hash
-        let mut last_id: i64 = -1;
+        let mut synced = Synced {
+            last_ids: vec![-1; self.remotes.len()],
+            retry_at: vec![None; self.remotes.len()],
+            ..Default::default()
+        };
+        let mut commands = self.commands.lock().await;
+        let mut state_rx = self.state.subscribe();
+        let mut was_paused = false;
+        let follower = self.start_following().await;
 
         // 0. Initial Sync (Prevent re-upload loop on restart)
+        // The first remote that answers defines what counts as already synced
         tracing::info!("Performing initial sync check...");
-        match self.download(0, -1).await {
-            Ok((Some(data), id)) => {
-                last_id = id;
-                match data {
-                     ClipboardData::Text { content, html, .. } => {
-                         last_clipboard_content = content.clone();
-                         last_clipboard_html = html.unwrap_or_default();
-                         tracing::info!("Initial sync: Loaded text from server (ID: {})", id);
-                     },
-                     ClipboardData::Image { hash, .. } => {
-                         if let Some(h) = hash {
-                             last_image_hash = h;
-                             tracing::info!("Initial sync: Loaded image hash from server (ID: {})", id);
-                         }
-                     },
-                     _ => {}
+        let mut seeded = false;
+        for (index, remote) in self.remotes.iter().enumerate() {
+            match remote.download(0, -1).await {
+                Ok((Some(data), id)) => {
+                    synced.last_ids[index] = id;
+                    if seeded {
+                        continue;
+                    }
+                    seeded = true;
+                    match data {
+                        ClipboardData::Text { content, html, .. } => {
+                            synced.text = content;
+                            synced.html = html.unwrap_or_default();
+                            tracing::info!("Initial sync: Loaded text from '{}' (ID: {})", remote.name, id);
+                        },
+                        ClipboardData::Image { hash: Some(h), .. } => {
+                            synced.image_hash = h;
+                            tracing::info!("Initial sync: Loaded image hash from '{}' (ID: {})", remote.name, id);
+                        },
+                        _ => {}
+                    }
+                },
+                Ok((None, id)) => {
+                    synced.last_ids[index] = id;
+                    tracing::info!("Initial sync: '{}' empty or no change (ID: {})", remote.name, id);
+                },
+                Err(e) => {
+                    tracing::warn!("Initial sync with '{}' failed (offline?): {}", remote.name, e);
                 }
-            },
-            Ok((None, id)) => {
-                last_id = id;
-                tracing::info!("Initial sync: Server empty or no change (ID: {})", id);
-            },
-            Err(e) => {
-                tracing::warn!("Initial sync failed (offline?): {}", e);
             }
         }
 
         loop {
-            // 1. Check Local Clipboard
-            // We check text AND html. 
-            let current_text = self.clipboard.get_text().unwrap_or_default();
-            let current_html = self.clipboard.get_html().unwrap_or_default();
-            
-            // Only upload if something meaningful changed and is not empty (at least one of them)
-            // But usually empty text means empty clipboard.
-            let text_changed = current_text != last_clipboard_content && !current_text.is_empty();
-            let html_changed = current_html != last_clipboard_html && !current_html.is_empty();
-
-            if text_changed || html_changed {
-                 tracing::info!("Local clipboard changed (Text: {}, HTML: {}). Uploading...", text_changed, html_changed);
-                 
-                 // Pass both text and html to upload
-                 let html_opt = if current_html.is_empty() { None } else { Some(current_html.clone()) };
-                 
-                 if let Err(e) = self.upload_text(current_text.clone(), html_opt).await {
-                    tracing::error!("Failed to upload text/html: {}", e);
-                 } else {
-                    last_clipboard_content = current_text;
-                    last_clipboard_html = current_html;
-                 }
+            let state = *state_rx.borrow_and_update();
+            if state == SyncState::Stopped {
+                break;
             }
 
-            // ... (keep image check)
-             match self.clipboard.get_image() {
-                Ok(image) => {
-                    let _w = image.width();
-                    let _h = image.height();
-                    if let Ok(png_bytes) = self.encode_png(&image) {
-                         let mut hasher = Sha256::new();
-                         hasher.update(&png_bytes);
-                         let hash = hex::encode(hasher.finalize());
-                         
-                         // Fix Loop: Only upload if hash is different from last one (downloaded or uploaded)
-                         if hash != last_image_hash {
-                             let filename = format!("{}.png", hash);
-    
-                             if let Err(e) = self.upload_image(filename, png_bytes, hash.clone()).await {
-                                 tracing::error!("Failed to upload image: {}", e);
-                             } else {
-                                 tracing::info!("Uploaded image");
-                                 last_image_hash = hash;
-                             }
-                         }
-                    }
+            // Explicit actions are honoured even while paused
+            while let Ok(command) = commands.try_recv() {
+                self.handle_command(command, &mut synced).await;
+            }
+
+            if state == SyncState::Paused {
+                was_paused = true;
+                tokio::select! {
+                    _ = state_rx.changed() => {}
+                    Some(command) = commands.recv() => self.handle_command(command, &mut synced).await,
                 }
-                Err(_) => {}
+                continue;
             }
 
-            // 2. Check Server (Long Polling)
-            // Use wait=30s
-            match self.download(30, last_id).await {
-                 Ok((Some(data), new_id)) => {
-                    last_id = new_id;
-                    match data {
-                        ClipboardData::Text { content, html, .. } => {
-                            // Check if effectively different
-                            let new_html = html.clone().unwrap_or_default();
-                            if content != last_clipboard_content || new_html != last_clipboard_html {
-                                tracing::info!("Server update (id={}). Updating local...", new_id);
-                                
-                                // If we have HTML, use set_html which sets both. Else set_text.
-                                let result = if let Some(h) = html {
-                                    self.clipboard.set_html(h, Some(content.clone()))
-                                } else {
-                                    self.clipboard.set_text(content.clone())
-                                };
-    
-                                if let Err(e) = result {
-                                    tracing::error!("Failed to set clipboard: {}", e);
-                                } else {
-                                    last_clipboard_content = content;
-                                    last_clipboard_html = new_html;
-                                }
-                            }
-                        },
-                        ClipboardData::Image { hash, .. } => {
-                             if let Some(h) = hash {
-                                 // We don't necessarily download image content automatically to valid clipboard unless user wants it?
-                                 // Currently logic didn't IMPLEMENT downloading image to clipboard in download() function above (it only handled Text).
-                                 // Wait, the previous code block for download() ONLY handled ClipboardData::Text. 
-                                 // If it was Image, it did nothing but update ID.
-                                 // If we want to sync images, we must download the image content here!
-                                 
-                                 // For now, at least update the hash so we don't re-upload it if we ever implement download.
-                                 // OR if the user manually copied it.
-                                 last_image_hash = h;
-                             }
-                        },
-                        _ => {}
-                    }
-                 }
-                 Ok((None, id)) => {
-                     // Update ID even if no data (e.g. timeout with known latest state?)
-                     // Actually logic keeps last_id if None
-                     if id > last_id { last_id = id; }
-                 }
-                 Err(e) => {
-                     tracing::warn!("Failed to fetch from server: {}", e);
-                     sleep(Duration::from_secs(5)).await; // Error backoff
-                 },
+            // 1. Check Local Clipboard
+            // Whatever was copied while paused or in manual mode is never uploaded implicitly
+            let auto = *self.push_mode.borrow() == SyncMode::Auto;
+            self.check_local(&mut synced, auto && !was_paused).await;
+            was_paused = false;
+
+            // 2. Check Servers (Long Polling), interrupted by commands and pause/stop
+            // Use wait=30s. Whichever remote answers first is applied, the other polls restart next pass.
+            let now = Instant::now();
+            let polls: Vec<_> = self.remotes
+                .iter()
+                .enumerate()
+                .filter(|(index, remote)| {
+                    remote.direction.can_receive() && synced.retry_at[*index].is_none_or(|at| at <= now)
+                })
+                .map(|(index, remote)| {
+                    let last_id = synced.last_ids[index];
+                    Box::pin(async move { (index, remote.download(30, last_id).await) })
+                })
+                .collect();
+            if polls.is_empty() {
+                // Send-only (or every remote backing off): just watch the local clipboard
+                tokio::select! {
+                    _ = sleep(Duration::from_millis(500)) => {}
+                    _ = state_rx.changed() => {}
+                    Some(command) = commands.recv() => self.handle_command(command, &mut synced).await,
+                }
+            } else {
+                tokio::select! {
+                    ((index, result), _, _) = select_all(polls) => self.apply_remote(index, result, &mut synced).await,
+                    _ = state_rx.changed() => {}
+                    Some(command) = commands.recv() => self.handle_command(command, &mut synced).await,
+                }
             }
-            
+
             // No sleep needed for long polling, but let's yield small time to avoid tight loop on errors or local checks
             sleep(Duration::from_millis(100)).await;
         }
+        if let Some(follower) = follower {
+            follower.stop().await;
+        }
+        tracing::info!("Sync Manager stopped");
     }
 
-    pub async fn upload_text(&self, text: String, html: Option<String>) -> Result<()> {
-        let content_to_send = if let Some(password) = &self.encrypt_password {
-            // Encrypt
-            match crypto::encrypt(text.as_bytes(), password) {
-                Ok(encrypted_bytes) => {
-                     let b64 = general_purpose::STANDARD.encode(encrypted_bytes);
-                     format!("E2EE::{}", b64)
-                },
-                Err(e) => {
-                    tracing::error!("Encryption failed: {}", e);
-                    return Err(anyhow::anyhow!("Encryption failed: {}", e));
+    /// 为设置了 `server_id` 的远端跟随其地址，未设置共用的发现服务时自行创建；发现服务不可用时只使用配置地址
+    async fn start_following(&self) -> Option<Follower> {
+        let scanner = self.scanner.clone()?;
+        let targets: Vec<(usize, String, String)> = self.remotes
+            .iter()
+            .enumerate()
+            .filter_map(|(index, remote)| Some((index, remote.server_id.clone()?, remote.scheme().to_string())))
+            .collect();
+        let (discovery, owned) = match &self.shared_discovery {
+            Some(discovery) => (discovery.clone(), None),
+            None => {
+                let auth = match DiscoveryAuth::from_config(&self.discovery) {
+                    Ok(auth) => auth,
+                    Err(e) => {
+                        tracing::warn!("Invalid discovery settings, using configured server addresses: {}", e);
+                        return None;
+                    }
+                };
+                let discovery = match DiscoveryService::with_auth(scanner, auth).await {
+                    Ok(discovery) => Arc::new(discovery),
+                    Err(e) => {
+                        tracing::warn!("Discovery unavailable, using configured server addresses: {}", e);
+                        return None;
+                    }
+                };
+                discovery.clone().start().await;
+                (discovery.clone(), Some(discovery))
+            }
+        };
+        if let Err(e) = discovery.scan().await {
+            tracing::warn!("Failed to send discovery search: {}", e);
+        }
+        Some(Follower {
+            task: tokio::spawn(follow_servers(discovery, targets, self.commands_tx.clone())),
+            owned,
+        })
+    }
+
+    async fn handle_command(&self, command: Command, synced: &mut Synced) {
+        match command {
+            Command::Push(reply) => {
+                let result = self.push_current(synced).await;
+                if let Err(e) = &result {
+                    tracing::warn!("Push failed: {}", e);
                 }
+                let _ = reply.send(result);
             }
+            Command::Pull(reply) => {
+                let result = self.pull_latest(synced).await;
+                if let Err(e) = &result {
+                    tracing::warn!("Pull failed: {}", e);
+                }
+                let _ = reply.send(result);
+            }
+            Command::Relocate { remote: index, url, restarted } => {
+                let remote = &self.remotes[index];
+                if remote.relocate(url.as_deref()) {
+                    tracing::info!("Remote '{}' now at {}", remote.name, remote.base_url());
+                } else if !restarted {
+                    return;
+                }
+                // Reconnect right away instead of waiting out an error backoff
+                synced.retry_at[index] = None;
+            }
+        }
+    }
+
+    /// 检查本地剪贴板变化。`upload` 为 false 时只记录当前内容，不上传。
+    /// 内容按各远端的方向与路由规则分发；从某个远端收到的内容不会转发给其他远端。
+    async fn check_local(&self, synced: &mut Synced, upload: bool) {
+        // We check text AND html. 
+        let current_text = self.clipboard.get_text().unwrap_or_default();
+        let current_html = if self.filter.allow_html() {
+            self.clipboard.get_html().unwrap_or_default()
         } else {
-            text
+            String::new()
         };
 
-        let mut data = ClipboardData::new_text(content_to_send);
-        if let ClipboardData::Text { html: ref mut h, device: ref mut d, .. } = data {
-            *d = Some(self.device_name.clone());
-            
-            // ... (E2EE logic)
-            if let Some(password) = &self.encrypt_password {
-                if let Some(raw_html) = html {
-                     match crypto::encrypt(raw_html.as_bytes(), password) {
-                        Ok(encrypted_bytes) => {
-                             let b64 = general_purpose::STANDARD.encode(encrypted_bytes);
-                             *h = Some(format!("E2EE::{}", b64));
-                        },
-                        Err(e) => {
-                            tracing::error!("HTML Encryption failed: {}", e);
-                            *h = None;
+        // Only upload if something meaningful changed and is not empty (at least one of them)
+        // But usually empty text means empty clipboard.
+        let text_changed = current_text != synced.text && !current_text.is_empty();
+        let html_changed = current_html != synced.html && !current_html.is_empty();
+
+        let concealed = upload && (text_changed || html_changed) && self.clipboard.is_concealed();
+        if !upload && (text_changed || html_changed) {
+             synced.text = current_text;
+             synced.html = current_html;
+        } else if concealed && self.concealed.mode == ConcealedMode::Skip {
+             tracing::info!("Local clipboard holds concealed content (password manager). Not syncing.");
+             synced.text = current_text;
+             synced.html = current_html;
+        } else if text_changed || html_changed {
+             let checked = self.filter.check_text(&current_text)
+                 .and_then(|_| self.filter.check_html(&current_html))
+                 .and_then(|_| self.filter.check_interval());
+             match checked {
+                 // Retried on a later pass once the interval has elapsed
+                 Err(Blocked::TooSoon(_)) => {}
+                 Err(reason) => {
+                     tracing::info!("Local clipboard text blocked by filters: {}", reason);
+                     synced.text = current_text;
+                     synced.html = current_html;
+                 }
+                 Ok(()) => {
+                     tracing::info!("Local clipboard changed (Text: {}, HTML: {}). Uploading...", text_changed, html_changed);
+
+                     // Pass both text and html to upload
+                     let html_opt = if current_html.is_empty() { None } else { Some(current_html.clone()) };
+
+                     match self.send_text(&current_text, html_opt, concealed).await {
+                        Err(e) => tracing::error!("Failed to upload text/html: {}", e),
+                        Ok(sent) => {
+                            if sent > 0 {
+                                self.filter.record_upload();
+                            }
+                            synced.text = current_text;
+                            synced.html = current_html;
                         }
-                    }
-                }
+                     }
+                 }
+             }
+        }
+
+        // ... (keep image check)
+        if let Some((png_bytes, hash)) = self.current_image() {
+             // Fix Loop: Only upload if hash is different from last one (downloaded or uploaded)
+             if hash == synced.image_hash {
+                 return;
+             }
+             let concealed = upload && self.clipboard.is_concealed();
+             if !upload {
+                 synced.image_hash = hash;
+             } else if concealed && self.concealed.mode == ConcealedMode::Skip {
+                 tracing::info!("Local clipboard holds a concealed image (password manager). Not syncing.");
+                 synced.image_hash = hash;
+             } else {
+                 match self.filter.check_image().and_then(|_| self.filter.check_interval()) {
+                     Err(Blocked::TooSoon(_)) => {}
+                     Err(reason) => {
+                         tracing::info!("Local clipboard image blocked by filters: {}", reason);
+                         synced.image_hash = hash;
+                     }
+                     Ok(()) => {
+                         match self.send_image(png_bytes, &hash, concealed).await {
+                             Err(e) => tracing::error!("Failed to upload image: {}", e),
+                             Ok(sent) => {
+                                 if sent > 0 {
+                                     tracing::info!("Uploaded image");
+                                     self.filter.record_upload();
+                                 }
+                                 synced.image_hash = hash;
+                             }
+                         }
+                     }
+                 }
+             }
+        }
+    }
+
+    /// 当前剪贴板中的图片，编码为 PNG 并计算哈希
+    fn current_image(&self) -> Option<(Vec<u8>, String)> {
+        let image = self.clipboard.get_image().ok()?;
+        let png_bytes = self.encode_png(&image).ok()?;
+        let mut hasher = Sha256::new();
+        hasher.update(&png_bytes);
+        let hash = hex::encode(hasher.finalize());
+        Some((png_bytes, hash))
+    }
+
+    /// 立即发送当前剪贴板（文本优先，其次图片），不论内容是否变化。
+    /// 仍遵守过滤规则、路由规则与隐藏内容设置，但不受最小上传间隔限制。
+    async fn push_current(&self, synced: &mut Synced) -> Result<()> {
+        if !self.direction.can_send() {
+            return Err(anyhow::anyhow!("Sync direction is receive-only"));
+        }
+        let concealed = self.clipboard.is_concealed();
+        if concealed && self.concealed.mode == ConcealedMode::Skip {
+            return Err(anyhow::anyhow!("Clipboard holds concealed content (password manager)"));
+        }
+
+        let text = self.clipboard.get_text().unwrap_or_default();
+        if !text.is_empty() {
+            self.filter.check_text(&text)?;
+            let html = if self.filter.allow_html() {
+                self.clipboard.get_html().unwrap_or_default()
             } else {
-                *h = html;
+                String::new()
+            };
+            self.filter.check_html(&html)?;
+            let html_opt = if html.is_empty() { None } else { Some(html.clone()) };
+            if self.send_text(&text, html_opt, concealed).await? == 0 {
+                return Err(anyhow::anyhow!("No remote accepts this text (routing rules)"));
             }
+            self.filter.record_upload();
+            tracing::info!("Pushed clipboard text");
+            synced.text = text;
+            synced.html = html;
+            return Ok(());
         }
-        let mut req = self.client.put(&self.server_url);
-        if let Some(token) = &self.token {
-            req = req.header("Authorization", format!("Bearer {}", token));
+
+        if let Some((png_bytes, hash)) = self.current_image() {
+            self.filter.check_image()?;
+            if self.send_image(png_bytes, &hash, concealed).await? == 0 {
+                return Err(anyhow::anyhow!("No remote accepts images (routing rules)"));
+            }
+            self.filter.record_upload();
+            tracing::info!("Pushed clipboard image");
+            synced.image_hash = hash;
+            return Ok(());
+        }
+
+        Err(anyhow::anyhow!("Clipboard is empty"))
+    }
+
+    /// 从第一个可接收的远端取回最新内容并写入本地剪贴板
+    async fn pull_latest(&self, synced: &mut Synced) -> Result<()> {
+        let Some((index, remote)) = self.remotes.iter().enumerate().find(|(_, r)| r.direction.can_receive()) else {
+            return Err(anyhow::anyhow!("Sync direction is send-only"));
+        };
+        let (data, id) = remote.download(0, synced.last_ids[index]).await?;
+        if data.is_none() {
+            return Err(anyhow::anyhow!("Server clipboard is empty"));
         }
-        req.json(&data).send().await?;
+        // Forget what was synced so the content is written even if the local copy diverged
+        synced.text.clear();
+        synced.html.clear();
+        self.apply_remote(index, Ok((data, id)), synced).await;
+        tracing::info!("Pulled clipboard from '{}' (id={})", remote.name, id);
         Ok(())
     }
 
-    async fn upload_image(&self, filename: String, bytes: Vec<u8>, hash: String) -> Result<()> {
-        // 1. Upload file
-        let file_url = self.server_url.replace("SyncClipboard.json", &format!("file/{}", filename));
-        let mut req_file = self.client.put(&file_url);
-        if let Some(token) = &self.token {
-            req_file = req_file.header("Authorization", format!("Bearer {}", token));
+    async fn apply_remote(&self, index: usize, result: Result<(Option<ClipboardData>, i64)>, synced: &mut Synced) {
+        let name = &self.remotes[index].name;
+        match result {
+             Ok((Some(data), new_id)) => {
+                synced.last_ids[index] = new_id;
+                synced.retry_at[index] = None;
+                match data {
+                    ClipboardData::Text { content, html, .. } => {
+                        // Check if effectively different
+                        let new_html = html.clone().unwrap_or_default();
+                        if content != synced.text || new_html != synced.html {
+                            tracing::info!("Server update from '{}' (id={}). Updating local...", name, new_id);
+                            
+                            // If we have HTML, use set_html which sets both. Else set_text.
+                            let result = if let Some(h) = html {
+                                self.clipboard.set_html(h, Some(content.clone()))
+                            } else {
+                                self.clipboard.set_text(content.clone())
+                            };
+
+                            if let Err(e) = result {
+                                tracing::error!("Failed to set clipboard: {}", e);
+                            } else {
+                                synced.text = content;
+                                synced.html = new_html;
+                            }
+                        }
+                    },
+                    ClipboardData::Image { hash, .. } => {
+                         if let Some(h) = hash {
+                             // We don't necessarily download image content automatically to valid clipboard unless user wants it?
+                             // Currently logic didn't IMPLEMENT downloading image to clipboard in download() function above (it only handled Text).
+                             // Wait, the previous code block for download() ONLY handled ClipboardData::Text. 
+                             // If it was Image, it did nothing but update ID.
+                             // If we want to sync images, we must download the image content here!
+                             
+                             // For now, at least update the hash so we don't re-upload it if we ever implement download.
+                             // OR if the user manually copied it.
+                             synced.image_hash = h;
+                         }
+                    },
+                    _ => {}
+                }
+             }
+             Ok((None, id)) => {
+                 // Update ID even if no data (e.g. timeout with known latest state?)
+                 // Actually logic keeps last_id if None
+                 if id > synced.last_ids[index] { synced.last_ids[index] = id; }
+                 synced.retry_at[index] = None;
+             }
+             Err(e) => {
+                 tracing::warn!("Failed to fetch from '{}': {}", name, e);
+                 // Error backoff, other remotes keep polling meanwhile
+                 synced.retry_at[index] = Some(Instant::now() + Duration::from_secs(5));
+             },
         }
-        req_file.body(bytes).send().await?;
+    }
 
-        // 2. Update metadata
-        let data = ClipboardData::Image { 
-            hash: Some(hash),
-            filename: filename,
-            device: Some(self.device_name.clone()),
-        };
-        let mut req_meta = self.client.put(&self.server_url);
-        if let Some(token) = &self.token {
-            req_meta = req_meta.header("Authorization", format!("Bearer {}", token));
+    /// 按路由规则把文本发送到所有接收它的远端；与监听到的复制一样受过滤规则约束，被拦截时返回错误
+    pub async fn upload_text(&self, text: String, html: Option<String>) -> Result<()> {
+        let html = html.filter(|_| self.filter.allow_html());
+        let checked = self.filter.check_text(&text)
+            .and_then(|_| self.filter.check_html(html.as_deref().unwrap_or_default()))
+            .and_then(|_| self.filter.check_interval());
+        if let Err(reason) = checked {
+            tracing::info!("Text upload blocked by filters: {}", reason);
+            return Err(anyhow::anyhow!("Blocked by filters: {}", reason));
+        }
+        if self.send_text(&text, html, false).await? > 0 {
+            self.filter.record_upload();
         }
-        req_meta.json(&data).send().await?;
         Ok(())
     }
 
+    /// 返回成功发送的远端数量（没有远端接收时为 0）；所有目标都失败时返回错误
+    async fn send_text(&self, text: &str, html: Option<String>, concealed: bool) -> Result<usize> {
+        let concealed = concealed.then_some(&self.concealed);
+        let targets: Vec<&Remote> = self.remotes.iter().filter(|r| r.wants(Outgoing::Text(text))).collect();
+        let results = join_all(
+            targets.iter().map(|remote| remote.upload_text(text.to_string(), html.clone(), concealed)),
+        ).await;
+        tally(&targets, results)
+    }
+
+    async fn send_image(&self, png_bytes: Vec<u8>, hash: &str, concealed: bool) -> Result<usize> {
+        let concealed = concealed.then_some(&self.concealed);
+        let filename = format!("{}.png", hash);
+        let targets: Vec<&Remote> = self.remotes.iter().filter(|r| r.wants(Outgoing::Image)).collect();
+        let results = join_all(targets.iter().map(|remote| {
+            remote.upload_image(filename.clone(), png_bytes.clone(), hash.to_string(), concealed)
+        })).await;
+        tally(&targets, results)
+    }
+
     pub async fn upload_file_stream(&self, path: PathBuf, hash: String) -> Result<()> {
+        if let Err(reason) = self.filter.check_file() {
+            tracing::info!("File {:?} blocked by filters: {}", path, reason);
+            return Err(anyhow::anyhow!("Blocked by filters: {}", reason));
+        }
         if !path.exists() {
             return Err(anyhow::anyhow!("File not found: {:?}", path));
         }
-        
-        // 1. Prepare stream
-        let file = File::open(&path).await?;
-        let stream = ReaderStream::new(file);
-        let body = reqwest::Body::wrap_stream(stream);
-
-        // 2. Upload file
-        let _filename = path.file_name().and_then(|n| n.to_str()).unwrap_or("unknown_file").to_string();
-        // Use hash as filename on server for deduplication/storage (match upload_image logic?)
-        // Actually upload_image uses hash.png. Here we use hash as filename maybe?
-        // Or keep original extension.
-        // Let's use hash + extension.
-        let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("").to_string();
-        let remote_filename = if extension.is_empty() { hash.clone() } else { format!("{}.{}", hash, extension) };
-
-        let file_url = self.server_url.replace("SyncClipboard.json", &format!("file/{}", remote_filename));
-        let mut req_file = self.client.put(&file_url);
-        if let Some(token) = &self.token {
-            req_file = req_file.header("Authorization", format!("Bearer {}", token));
-        }
-        
-        // Note: Content-Length is usually required for some servers, but chunked transfer encoding (stream) works without it
-        // unless server requires it. Axum handles streaming body fine.
-        req_file.body(body).send().await?;
-
-        // 3. Update metadata
-        let data = ClipboardData::File { 
-            hash: Some(hash),
-            filename: remote_filename,
-            device: Some(self.device_name.clone()),
-        };
-        let mut req_meta = self.client.put(&self.server_url);
-        if let Some(token) = &self.token {
-            req_meta = req_meta.header("Authorization", format!("Bearer {}", token));
+
+        let targets: Vec<&Remote> = self.remotes.iter().filter(|r| r.wants(Outgoing::File)).collect();
+        let results = join_all(targets.iter().map(|remote| remote.upload_file_stream(&path, hash.clone()))).await;
+        if tally(&targets, results)? > 0 {
+            self.filter.record_upload();
         }
-        req_meta.json(&data).send().await?;
         Ok(())
     }
 
@@ -300,60 +716,31 @@ impl SyncManager {
         Ok(bytes)
     }
 
+    /// 从第一个可接收的远端获取最新内容
     pub async fn download(&self, wait: u64, last_id: i64) -> Result<(Option<ClipboardData>, i64)> {
-        let url = format!("{}?wait={}&last_id={}", self.server_url, wait, last_id);
-        let mut req = self.client.get(&url);
-        if let Some(token) = &self.token {
-            req = req.header("Authorization", format!("Bearer {}", token));
-        }
-        let resp = req.send().await?;
-        if resp.status().is_success() {
-            let id = resp.headers()
-                .get("X-Clipboard-Id")
-                .and_then(|h| h.to_str().ok())
-                .and_then(|s| s.parse::<i64>().ok())
-                .unwrap_or(0);
-                
-            let mut data: ClipboardData = resp.json().await?;
-            
-            // Decrypt if needed
-            if let ClipboardData::Text { content, html, .. } = &mut data {
-                 // 1. Decrypt Text
-                 if content.starts_with("E2EE::") {
-                     if let Some(password) = &self.encrypt_password {
-                         let b64 = &content[6..];
-                         if let Ok(bytes) = general_purpose::STANDARD.decode(b64) {
-                             if let Ok(decrypted_bytes) = crypto::decrypt(&bytes, password) {
-                                  if let Ok(decrypted_text) = String::from_utf8(decrypted_bytes) {
-                                      *content = decrypted_text;
-                                  }
-                             }
-                         }
-                     }
-                 }
-                 
-                 // 2. Decrypt HTML
-                 if let Some(h) = html {
-                     if h.starts_with("E2EE::") {
-                         if let Some(password) = &self.encrypt_password {
-                              let b64 = &h[6..];
-                              if let Ok(bytes) = general_purpose::STANDARD.decode(b64) {
-                                   if let Ok(decrypted_bytes) = crypto::decrypt(&bytes, password) {
-                                       if let Ok(decrypted_html) = String::from_utf8(decrypted_bytes) {
-                                           *html = Some(decrypted_html);
-                                       }
-                                   }
-                              }
-                         }
-                     }
-                 }
+        let remote = self.remotes
+            .iter()
+            .find(|r| r.direction.can_receive())
+            .ok_or_else(|| anyhow::anyhow!("Sync direction is send-only"))?;
+        remote.download(wait, last_id).await
+    }
+}
+
+/// 汇总并行上传的结果：返回成功数量，全部失败时返回最后一个错误
+fn tally(targets: &[&Remote], results: Vec<Result<()>>) -> Result<usize> {
+    let mut sent = 0;
+    let mut last_error = None;
+    for (remote, result) in targets.iter().zip(results) {
+        match result {
+            Ok(()) => sent += 1,
+            Err(e) => {
+                tracing::error!("Upload to '{}' failed: {}", remote.name, e);
+                last_error = Some(e);
             }
-            
-            Ok((Some(data), id))
-        } else if resp.status() == reqwest::StatusCode::NOT_MODIFIED {
-             Ok((None, last_id))
-        } else {
-             Ok((None, last_id))
         }
     }
+    match last_error {
+        Some(e) if sent == 0 => Err(e),
+        _ => Ok(sent),
+    }
 }
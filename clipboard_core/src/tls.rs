@@ -0,0 +1,203 @@
+//! 客户端 TLS：自定义 CA 与证书指纹固定
+//!
+//! 设置了指纹或启用首次信任（TOFU）时，使用自定义的 rustls 证书校验器：
+//! 只比较服务端证书的 SHA-256 指纹，不再依赖 CA 与主机名，适合自签名证书。
+
+use crate::config::ClientTlsConfig;
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::{verify_tls12_signature, verify_tls13_signature, CryptoProvider};
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{DigitallySignedStruct, SignatureScheme};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug, thiserror::Error)]
+pub enum TlsError {
+    #[error("failed to read CA certificate '{path}': {source}")]
+    ReadCa { path: String, source: std::io::Error },
+
+    #[error("invalid CA certificate '{path}': {source}")]
+    InvalidCa { path: String, source: reqwest::Error },
+
+    #[error("invalid certificate fingerprint '{0}', expected 32 hex-encoded bytes")]
+    InvalidFingerprint(String),
+
+    #[error("failed to set up TLS: {0}")]
+    Rustls(#[from] rustls::Error),
+
+    #[error("failed to build HTTP client: {0}")]
+    Client(#[from] reqwest::Error),
+}
+
+/// 计算证书（DER）的 SHA-256 指纹，格式与 `openssl x509 -fingerprint -sha256` 相同
+pub fn fingerprint(der: &[u8]) -> String {
+    Sha256::digest(der)
+        .iter()
+        .map(|b| format!("{:02X}", b))
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+/// 计算 PEM 中第一张证书的指纹，没有可解析的证书时返回 None
+pub fn fingerprint_pem(pem: &[u8]) -> Option<String> {
+    CertificateDer::from_pem_slice(pem).ok().map(|der| fingerprint(&der))
+}
+
+/// 解析十六进制指纹，忽略大小写、冒号与空白
+pub fn parse_fingerprint(value: &str) -> Result<[u8; 32], TlsError> {
+    let digits: String = value.chars().filter(|c| *c != ':' && !c.is_whitespace()).collect();
+    hex::decode(&digits)
+        .ok()
+        .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+        .ok_or_else(|| TlsError::InvalidFingerprint(value.to_string()))
+}
+
+/// 首次信任记录：`host:port` 到证书指纹的映射，保存为 JSON
+pub struct KnownServers {
+    path: PathBuf,
+}
+
+// Several remotes may share one file; serialize read-modify-write cycles
+static KNOWN_SERVERS_LOCK: Mutex<()> = Mutex::new(());
+
+impl KnownServers {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self { path: path.as_ref().to_path_buf() }
+    }
+
+    fn load(&self) -> BTreeMap<String, String> {
+        std::fs::read_to_string(&self.path)
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn get(&self, server: &str) -> Option<String> {
+        let _guard = KNOWN_SERVERS_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        self.load().remove(server)
+    }
+
+    /// 首次见到 `server` 时记录其指纹；已有记录时返回记录的指纹
+    fn trust(&self, server: &str, fingerprint: &str) -> std::io::Result<Option<String>> {
+        let _guard = KNOWN_SERVERS_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let mut known = self.load();
+        if let Some(existing) = known.get(server) {
+            return Ok(Some(existing.clone()));
+        }
+        known.insert(server.to_string(), fingerprint.to_string());
+        std::fs::write(&self.path, serde_json::to_string_pretty(&known)?)?;
+        Ok(None)
+    }
+}
+
+#[derive(Debug)]
+enum Pin {
+    Fixed([u8; 32]),
+    /// 按 `host:port` 在首次信任记录中查找或记录
+    FirstUse { server: String, store: PathBuf },
+}
+
+#[derive(Debug)]
+struct PinnedVerifier {
+    pin: Pin,
+    provider: Arc<CryptoProvider>,
+}
+
+impl ServerCertVerifier for PinnedVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        let presented = fingerprint(end_entity);
+        let expected = match &self.pin {
+            Pin::Fixed(pin) => *pin,
+            Pin::FirstUse { server, store } => {
+                let recorded = KnownServers::new(store)
+                    .trust(server, &presented)
+                    .map_err(|e| rustls::Error::General(format!("failed to record certificate: {}", e)))?;
+                match recorded {
+                    Some(recorded) => parse_fingerprint(&recorded)
+                        .map_err(|e| rustls::Error::General(e.to_string()))?,
+                    None => {
+                        tracing::info!("Trusting certificate {} for {} on first use", presented, server);
+                        return Ok(ServerCertVerified::assertion());
+                    }
+                }
+            }
+        };
+        if Sha256::digest(end_entity.as_ref()).as_slice() == expected {
+            Ok(ServerCertVerified::assertion())
+        } else {
+            tracing::warn!("Server certificate {} does not match the pinned fingerprint", presented);
+            Err(rustls::Error::General(format!("certificate fingerprint {} does not match the pinned one", presented)))
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls12_signature(message, cert, dss, &self.provider.signature_verification_algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(message, cert, dss, &self.provider.signature_verification_algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.provider.signature_verification_algorithms.supported_schemes()
+    }
+}
+
+/// 按配置设置客户端的证书校验。`server` 为 `host:port`，用作首次信任记录的键
+pub fn configure(
+    builder: reqwest::ClientBuilder,
+    config: &ClientTlsConfig,
+    server: &str,
+) -> Result<reqwest::ClientBuilder, TlsError> {
+    let pin = match &config.fingerprint {
+        Some(value) => Some(Pin::Fixed(parse_fingerprint(value)?)),
+        None if config.trust_on_first_use => Some(Pin::FirstUse {
+            server: server.to_string(),
+            store: PathBuf::from(&config.known_servers),
+        }),
+        None => None,
+    };
+
+    if let Some(pin) = pin {
+        if config.ca_cert.is_some() {
+            tracing::warn!("Certificate pinning is enabled for {}, ignoring ca_cert", server);
+        }
+        let provider = Arc::new(rustls::crypto::aws_lc_rs::default_provider());
+        let tls = rustls::ClientConfig::builder_with_provider(provider.clone())
+            .with_safe_default_protocol_versions()?
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(PinnedVerifier { pin, provider }))
+            .with_no_client_auth();
+        return Ok(builder.tls_backend_preconfigured(tls));
+    }
+
+    match &config.ca_cert {
+        Some(path) => {
+            let pem = std::fs::read(path).map_err(|source| TlsError::ReadCa { path: path.clone(), source })?;
+            let certs = reqwest::Certificate::from_pem_bundle(&pem)
+                .map_err(|source| TlsError::InvalidCa { path: path.clone(), source })?;
+            Ok(builder.tls_certs_merge(certs))
+        }
+        None => Ok(builder),
+    }
+}
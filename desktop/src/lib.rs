@@ -1,6 +1,9 @@
-use clipboard_core::config::Config;
-use rusqlite::Connection;
+use clipboard_core::clipboard_handler::ClipboardHandler;
+use clipboard_core::config::{Config, SyncMode};
+use clipboard_core::hotkey::HotkeyAction;
+use clipboard_core::sync::{SyncHandle, SyncState, SyncStatus};
 use serde::Serialize;
+use server::db::{schema, HistoryQuery};
 use std::net::TcpListener;
 use tauri::Manager;
 
@@ -14,6 +17,8 @@ struct HistoryItem {
     device: Option<String>,
     timestamp: String,
     pinned: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    snippet: Option<String>,
 }
 
 #[tauri::command]
@@ -65,68 +70,211 @@ fn save_config(config: Config) -> Result<(), String> {
         }
     }
     
+    config.hotkeys
+        .validate()
+        .map_err(|e| format!("快捷键配置无效：{}", e))?;
+
     // Use the new persistence implementation
     config.save().map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-fn get_history() -> Result<Vec<HistoryItem>, String> {
+fn get_history(query: Option<HistoryQuery>) -> Result<Vec<HistoryItem>, String> {
     let config = Config::new().map_err(|e| e.to_string())?;
-    let conn = Connection::open(&config.history.db_path).map_err(|e| e.to_string())?;
-    
-    // Sort by pinned DESC (pinned first), then by id DESC (newest first)
-    let mut stmt = conn.prepare("SELECT id, type, content, html, file, device, timestamp, pinned FROM history ORDER BY pinned DESC, id DESC LIMIT 50").map_err(|e| e.to_string())?;
-    
-    let rows = stmt.query_map([], |row| {
-        Ok(HistoryItem {
-            id: row.get(0)?,
-            r#type: row.get(1)?,
-            content: row.get(2)?,
-            html: row.get(3).unwrap_or(None), 
-            file: row.get(4)?,
-            device: row.get(5).unwrap_or(None),
-            timestamp: row.get(6)?,
-            pinned: row.get(7).unwrap_or(false),
-        })
-    }).map_err(|e| e.to_string())?;
+    let conn = schema::open(&config.history.db_path).map_err(|e| e.to_string())?;
 
-    let mut history = Vec::new();
-    for row in rows {
-        history.push(row.map_err(|e| e.to_string())?);
-    }
-    Ok(history)
+    // Without a search term: pinned first, then newest first; with one: by relevance
+    let rows = schema::read_history(&conn, &query.unwrap_or_default()).map_err(|e| e.to_string())?;
+    Ok(rows.into_iter().map(|row| HistoryItem {
+        id: row.id,
+        r#type: row.r#type,
+        content: row.content,
+        html: row.html,
+        file: row.file,
+        device: row.device,
+        timestamp: row.timestamp,
+        pinned: row.pinned,
+        snippet: row.snippet,
+    }).collect())
 }
 
 #[tauri::command]
 fn delete_history_item(id: i64) -> Result<(), String> {
     let config = Config::new().map_err(|e| e.to_string())?;
-    let conn = Connection::open(&config.history.db_path).map_err(|e| e.to_string())?;
-    conn.execute("DELETE FROM history WHERE id = ?1", rusqlite::params![id])
-        .map_err(|e| e.to_string())?;
-    Ok(())
+    let conn = schema::open(&config.history.db_path).map_err(|e| e.to_string())?;
+    schema::delete_history(&conn, id).map_err(|e| e.to_string())
 }
 
 #[tauri::command]
 fn clear_history() -> Result<(), String> {
     let config = Config::new().map_err(|e| e.to_string())?;
-    let conn = Connection::open(&config.history.db_path).map_err(|e| e.to_string())?;
-    // Option: Keep pinned items? For now delete everything as requested "Clear All"
-    // If user wants to just clear unpinned, we can change logic.
-    // Let's protect pinned items by default as that is standard behavior for "Pin"
-    conn.execute("DELETE FROM history WHERE pinned = 0", [])
-        .map_err(|e| e.to_string())?;
-    Ok(())
+    let conn = schema::open(&config.history.db_path).map_err(|e| e.to_string())?;
+    // Pinned items are protected from "Clear All", as is standard behavior for "Pin"
+    schema::clear_unpinned(&conn).map_err(|e| e.to_string())
 }
 
 #[tauri::command]
 fn toggle_pin(id: i64) -> Result<(), String> {
     let config = Config::new().map_err(|e| e.to_string())?;
-    let conn = Connection::open(&config.history.db_path).map_err(|e| e.to_string())?;
-    conn.execute("UPDATE history SET pinned = NOT pinned WHERE id = ?1", rusqlite::params![id])
-        .map_err(|e| e.to_string())?;
+    let conn = schema::open(&config.history.db_path).map_err(|e| e.to_string())?;
+    schema::toggle_pinned(&conn, id).map_err(|e| e.to_string())
+}
+
+/// 历史记录快速选择窗口的标签
+const PICKER_WINDOW: &str = "picker";
+
+/// 打开（或重新显示）历史记录快速选择窗口
+fn show_history_picker(app: &tauri::AppHandle) -> tauri::Result<()> {
+    if let Some(window) = app.get_webview_window(PICKER_WINDOW) {
+        window.show()?;
+        return window.set_focus();
+    }
+    tauri::WebviewWindowBuilder::new(app, PICKER_WINDOW, tauri::WebviewUrl::App("index.html?view=picker".into()))
+        .title("SyncClipboard")
+        .inner_size(420.0, 480.0)
+        .resizable(false)
+        .always_on_top(true)
+        .skip_taskbar(true)
+        .center()
+        .focused(true)
+        .build()?;
     Ok(())
 }
 
+#[tauri::command]
+fn close_history_picker(app: tauri::AppHandle) {
+    if let Some(window) = app.get_webview_window(PICKER_WINDOW) {
+        let _ = window.hide();
+    }
+}
+
+/// 将一条文本历史记录写回本地剪贴板并关闭选择窗口。
+/// 自动同步模式下，写入的内容随后会像普通复制一样上传。
+#[tauri::command]
+fn copy_history_item(app: tauri::AppHandle, id: i64) -> Result<(), String> {
+    let config = Config::new().map_err(|e| e.to_string())?;
+    let conn = schema::open(&config.history.db_path).map_err(|e| e.to_string())?;
+    let row = schema::get_history_item(&conn, id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "记录不存在".to_string())?;
+    let content = match (row.r#type.as_str(), row.content) {
+        ("Text", Some(content)) => content,
+        _ => return Err("只能复制文本记录".to_string()),
+    };
+
+    let clipboard = ClipboardHandler::new().map_err(|e| e.to_string())?;
+    match row.html {
+        Some(html) => clipboard.set_html(html, Some(content)),
+        None => clipboard.set_text(content),
+    }
+    .map_err(|e| e.to_string())?;
+
+    close_history_picker(app);
+    Ok(())
+}
+
+/// 同步客户端的控制句柄，客户端未启用或尚未启动时为空
+#[derive(Default)]
+struct SyncClient(std::sync::Mutex<Option<SyncHandle>>);
+
+/// 托盘中的"暂停/恢复同步"菜单项，状态变化时更新文字
+struct SyncTrayItem(tauri::menu::MenuItem<tauri::Wry>);
+
+fn sync_handle(app: &tauri::AppHandle) -> Result<SyncHandle, String> {
+    app.state::<SyncClient>()
+        .0
+        .lock()
+        .unwrap()
+        .clone()
+        .ok_or_else(|| "同步客户端未启用".to_string())
+}
+
+fn refresh_sync_tray(app: &tauri::AppHandle, status: &SyncStatus) {
+    if let Some(item) = app.try_state::<SyncTrayItem>() {
+        let text = if status.state == SyncState::Paused { "恢复同步" } else { "暂停同步" };
+        let _ = item.0.set_text(text);
+        let _ = item.0.set_enabled(status.state != SyncState::Stopped);
+    }
+}
+
+fn toggle_sync(app: &tauri::AppHandle) -> Result<SyncStatus, String> {
+    let handle = sync_handle(app)?;
+    if handle.status().state == SyncState::Paused {
+        handle.resume();
+    } else {
+        handle.pause();
+    }
+    let status = handle.status();
+    refresh_sync_tray(app, &status);
+    Ok(status)
+}
+
+/// 获取同步客户端状态，客户端未启用时返回 None
+#[tauri::command]
+fn get_sync_status(app: tauri::AppHandle) -> Option<SyncStatus> {
+    sync_handle(&app).ok().map(|handle| handle.status())
+}
+
+#[tauri::command]
+fn pause_sync(app: tauri::AppHandle) -> Result<SyncStatus, String> {
+    let handle = sync_handle(&app)?;
+    handle.pause();
+    let status = handle.status();
+    refresh_sync_tray(&app, &status);
+    Ok(status)
+}
+
+#[tauri::command]
+fn resume_sync(app: tauri::AppHandle) -> Result<SyncStatus, String> {
+    let handle = sync_handle(&app)?;
+    handle.resume();
+    let status = handle.status();
+    refresh_sync_tray(&app, &status);
+    Ok(status)
+}
+
+/// 停止同步客户端，需重启应用才能再次启动
+#[tauri::command]
+fn stop_sync(app: tauri::AppHandle) -> Result<SyncStatus, String> {
+    let handle = sync_handle(&app)?;
+    handle.stop();
+    let status = handle.status();
+    refresh_sync_tray(&app, &status);
+    Ok(status)
+}
+
+/// 切换自动/手动模式（仅本次运行有效，持久化请修改 `client.push_mode`）
+#[tauri::command]
+fn set_sync_mode(app: tauri::AppHandle, mode: SyncMode) -> Result<SyncStatus, String> {
+    let handle = sync_handle(&app)?;
+    handle.set_push_mode(mode);
+    Ok(handle.status())
+}
+
+/// 立即发送当前剪贴板
+#[tauri::command]
+async fn push_clipboard(app: tauri::AppHandle) -> Result<(), String> {
+    sync_handle(&app)?.push_now().await.map_err(|e| e.to_string())
+}
+
+/// 立即从服务端拉取最新内容到本地剪贴板
+#[tauri::command]
+async fn pull_clipboard(app: tauri::AppHandle) -> Result<(), String> {
+    sync_handle(&app)?.pull_now().await.map_err(|e| e.to_string())
+}
+
+async fn run_hotkey(app: &tauri::AppHandle, action: HotkeyAction) {
+    let result = match action {
+        HotkeyAction::Push => push_clipboard(app.clone()).await,
+        HotkeyAction::Pull => pull_clipboard(app.clone()).await,
+        HotkeyAction::TogglePause => toggle_sync(app).map(|_| ()),
+        HotkeyAction::HistoryPicker => show_history_picker(app).map_err(|e| e.to_string()),
+    };
+    if let Err(e) = result {
+        tracing::warn!("Hotkey '{}' failed: {}", action.name(), e);
+    }
+}
+
 #[derive(Serialize)]
 struct AppInfo {
     name: String,
@@ -238,13 +386,12 @@ struct NetworkInfo {
 
 #[tauri::command]
 fn get_network_info() -> Result<NetworkInfo, String> {
-    use local_ip_address::list_afinet_netifas;
-    
     let mut interfaces = Vec::new();
     
-    // 获取所有本地网卡信息
-    if let Ok(ifaces) = list_afinet_netifas() {
-        for (name, ip) in ifaces {
+    // 获取所有本地网卡信息（local_ip_address 每次调用都会遗留 netlink Socket 与线程）
+    if let Ok(ifaces) = if_addrs::get_if_addrs() {
+        for iface in ifaces {
+            let (ip, name) = (iface.ip(), iface.name);
             let ip_str = ip.to_string();
             
             // 过滤掉环回地址
@@ -280,68 +427,101 @@ fn get_network_info() -> Result<NetworkInfo, String> {
 
 #[derive(Serialize)]
 struct LanDevice {
+    /// 设备 ID，仅局域网发现的设备有
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<String>,
     name: String,
     ip: String,
     port: u16,
     #[serde(skip_serializing_if = "Option::is_none")]
     last_active: Option<u64>, // Unix时间戳（秒）
+    /// 同步方向（both / send / receive），仅已连接设备有
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sync_direction: Option<String>,
+}
+
+/// 界面监听的局域网设备事件，载荷为 `DiscoveryEvent`（`{"kind": "device_added", "device": {...}}`）
+const DISCOVERY_EVENT: &str = "discovery-event";
+
+/// 进程内共用的局域网发现服务，服务端、同步客户端与界面扫描都使用它；创建失败时为 None
+#[derive(Default)]
+struct LanDiscovery(tokio::sync::OnceCell<Option<std::sync::Arc<clipboard_core::discovery::DiscoveryService>>>);
+
+/// 获取共用的发现服务，首次使用时创建并启动（认证设置修改后需重启应用）
+async fn lan_discovery(app: &tauri::AppHandle) -> Option<std::sync::Arc<clipboard_core::discovery::DiscoveryService>> {
+    app.state::<LanDiscovery>().0.get_or_init(|| start_lan_discovery(app.clone())).await.clone()
 }
 
+/// 启用服务端时以服务端身份公告，否则只发现其他设备；设备变化通过 `discovery-event` 推送给界面
+async fn start_lan_discovery(app: tauri::AppHandle) -> Option<std::sync::Arc<clipboard_core::discovery::DiscoveryService>> {
+    use clipboard_core::discovery::DiscoveryService;
+    use tauri::Emitter;
+    use tokio::sync::broadcast::error::RecvError;
+
+    let config = match Config::new() {
+        Ok(config) => config,
+        Err(e) => {
+            tracing::error!("LAN discovery disabled, failed to load config: {}", e);
+            return None;
+        }
+    };
+    let port = if config.server.enabled { config.server.port } else { 0 };
+    let discovery = match DiscoveryService::from_config(&config, port).await {
+        Ok(discovery) => std::sync::Arc::new(discovery),
+        Err(e) => {
+            tracing::error!("LAN discovery unavailable: {}", e);
+            return None;
+        }
+    };
+    
+    // 把设备变化转发给界面
+    let mut events = discovery.subscribe();
+    tokio::spawn(async move {
+        loop {
+            match events.recv().await {
+                Ok(event) => {
+                    if let Err(e) = app.emit(DISCOVERY_EVENT, &event) {
+                        tracing::warn!("Failed to emit discovery event: {}", e);
+                    }
+                }
+                Err(RecvError::Lagged(missed)) => tracing::warn!("Missed {} discovery event(s)", missed),
+                Err(RecvError::Closed) => break,
+            }
+        }
+    });
+    discovery.clone().start().await;
+    Some(discovery)
+}
+
+/// 扫描局域网设备：立即返回已发现的设备，搜索与跨网段扫描在后台进行，
+/// 之后发现、变化与过期的设备通过 `discovery-event` 事件推送
 #[tauri::command]
-async fn get_lan_devices() -> Result<Vec<LanDevice>, String> {
-    use clipboard_core::discovery::{DiscoveryService, Device};
-    use clipboard_core::config::Config;
+async fn scan_lan_devices(app: tauri::AppHandle) -> Result<Vec<LanDevice>, String> {
+    use clipboard_core::discovery::HttpScanner;
     
     // 获取配置
     let config = Config::new().map_err(|e| e.to_string())?;
+    let http_scanner = HttpScanner::new(&config.discovery.scan_ranges, &config.discovery.scan_ports)
+        .map_err(|e| e.to_string())?;
+    let discovery = lan_discovery(&app).await.ok_or_else(|| "局域网发现不可用".to_string())?;
     
-    // 生成临时实例 ID
-    let instance_id = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap()
-        .as_secs();
-    
-    // 创建临时设备信息（用于发现）
-    let temp_device = Device {
-        id: config.general.device_id.clone(),
-        name: format!("{}-Scanner", config.general.device_name),
-        ip: "0.0.0.0".to_string(),
-        port: 0,  // 扫描器不需要监听端口
-        instance_id,
-        capabilities: vec![],
-    };
-    
-    // 创建发现服务
-    let discovery = std::sync::Arc::new(
-        DiscoveryService::new(temp_device)
-            .await
-            .map_err(|e| format!("Failed to create discovery service: {}", e))?
-    );
-    
-    // 启动发现任务（在后台）
-    let discovery_clone = discovery.clone();
+    // 主动发送搜索请求，同时跨网段 HTTP 扫描配置的网段，结果以事件推送
+    let background = discovery.clone();
     tokio::spawn(async move {
-        discovery_clone.start().await;
+        if let Err(e) = background.scan().await {
+            tracing::warn!("Failed to send discovery search: {}", e);
+        }
+        background.scan_http(&http_scanner).await;
     });
     
-    // 等待发现任务启动
-    tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
-    
-    // 主动发送扫描公告
-    discovery.scan().await.map_err(|e| e.to_string())?;
-    
-    // 等待 3 秒收集设备响应
-    tokio::time::sleep(tokio::time::Duration::from_secs(3)).await;
-    
-    // 获取发现的设备
-    let devices = discovery.get_devices().await;
-    
     // 转换为 LanDevice 格式
-    Ok(devices.into_iter().map(|d| LanDevice {
+    Ok(discovery.get_devices().await.into_iter().map(|d| LanDevice {
+        id: Some(d.id),
         name: d.name,
         ip: d.ip,
         port: d.port,
         last_active: None, // 通过发现扫描的设备没有时间戳
+        sync_direction: None,
     }).collect())
 }
 
@@ -373,6 +553,7 @@ async fn get_connected_clients() -> Result<Vec<LanDevice>, String> {
         ip: String,
         device_name: Option<String>,
         user_agent: Option<String>,
+        sync_direction: Option<String>,
         last_seen_timestamp: u64,
     }
     
@@ -383,10 +564,12 @@ async fn get_connected_clients() -> Result<Vec<LanDevice>, String> {
     
     // 转换为 LanDevice 格式
     Ok(clients.into_iter().map(|c| LanDevice {
+        id: None,
         name: c.device_name.unwrap_or_else(|| c.user_agent.unwrap_or_else(|| "Unknown Device".to_string())),
         ip: c.ip,
         port: 0, // 客户端没有监听端口
         last_active: Some(c.last_seen_timestamp),
+        sync_direction: c.sync_direction,
     }).collect())
 }
 
@@ -395,6 +578,8 @@ async fn get_connected_clients() -> Result<Vec<LanDevice>, String> {
 pub fn run() {
     tauri::Builder::default()
         // .plugin(tauri_plugin_shell::init()) // Removed unused plugin
+        .manage(SyncClient::default())
+        .manage(LanDiscovery::default())
         .invoke_handler(tauri::generate_handler![
             get_config, 
             save_config,
@@ -402,14 +587,23 @@ pub fn run() {
             find_available_port,
             get_history, 
             get_network_info, 
-            get_lan_devices,
+            scan_lan_devices,
             get_connected_clients,
             delete_history_item,
             clear_history,
             toggle_pin,
             get_app_info,
             get_dependencies,
-            check_update
+            check_update,
+            get_sync_status,
+            pause_sync,
+            resume_sync,
+            stop_sync,
+            set_sync_mode,
+            push_clipboard,
+            pull_clipboard,
+            copy_history_item,
+            close_history_picker
         ])
         .setup(|app| {
             // 1. Initialize Logging
@@ -423,13 +617,19 @@ pub fn run() {
             use tauri::tray::TrayIconBuilder;
             
             let show_item = MenuItem::with_id(app, "show", "显示窗口", true, None::<&str>)?;
+            let push_item = MenuItem::with_id(app, "push", "发送当前剪贴板", true, None::<&str>)?;
+            let toggle_sync_item = MenuItem::with_id(app, "toggle_sync", "暂停同步", true, None::<&str>)?;
             let quit_item = MenuItem::with_id(app, "quit", "退出", true, None::<&str>)?;
             
             let menu = MenuBuilder::new(app)
                 .item(&show_item)
                 .separator()
+                .item(&push_item)
+                .item(&toggle_sync_item)
+                .separator()
                 .item(&quit_item)
                 .build()?;
+            app.manage(SyncTrayItem(toggle_sync_item));
             
             let _tray = TrayIconBuilder::new()
                 .menu(&menu)
@@ -442,6 +642,19 @@ pub fn run() {
                                 let _ = window.set_focus();
                             }
                         }
+                        "push" => {
+                            let app = app.clone();
+                            tauri::async_runtime::spawn(async move {
+                                if let Err(e) = push_clipboard(app).await {
+                                    tracing::warn!("Push from tray failed: {}", e);
+                                }
+                            });
+                        }
+                        "toggle_sync" => {
+                            if let Err(e) = toggle_sync(app) {
+                                tracing::warn!("Toggle sync from tray failed: {}", e);
+                            }
+                        }
                         "quit" => {
                             app.exit(0);
                         }
@@ -461,6 +674,24 @@ pub fn run() {
                 });
             }
 
+            // 4. Global hotkeys (changes take effect after restart)
+            let hotkeys = Config::new().map(|config| config.hotkeys).unwrap_or_default();
+            if hotkeys.enabled {
+                match hotkeys.bindings() {
+                    Ok(bindings) => {
+                        let (tx, mut actions) = tokio::sync::mpsc::unbounded_channel();
+                        clipboard_core::hotkey::spawn_listener(bindings, tx);
+                        let app_handle = app.handle().clone();
+                        tauri::async_runtime::spawn(async move {
+                            while let Some(action) = actions.recv().await {
+                                run_hotkey(&app_handle, action).await;
+                            }
+                        });
+                    }
+                    Err(e) => tracing::error!("Global hotkeys disabled: {}", e),
+                }
+            }
+
              /* tauri::async_runtime::spawn(async move {
                 // Cleanup Task (Disabled for now)
                 let log_retention_days = Config::new().unwrap_or_else(|_| Config::new().unwrap()).history.log_retention_days;
@@ -491,13 +722,15 @@ pub fn run() {
             }); */
 
             // Start server in background
+            let app_handle = app.handle().clone();
             tauri::async_runtime::spawn(async move {
                 let config = Config::new().unwrap_or_else(|_| 
                     Config::new().expect("Failed to load config")
                 ); 
                 
                 if config.server.enabled {
-                    if let Err(e) = server::run(config.clone()).await {
+                    let discovery = lan_discovery(&app_handle).await;
+                    if let Err(e) = server::run_with_discovery(config.clone(), discovery).await {
                         tracing::error!("Background server error: {}", e);
                     }
                 } else {
@@ -506,6 +739,7 @@ pub fn run() {
             });
 
             // Start Sync Manager (Client)
+            let app_handle = app.handle().clone();
             tauri::async_runtime::spawn(async move {
                 // Give server a moment to start
                 tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
@@ -518,7 +752,11 @@ pub fn run() {
                     match clipboard_core::clipboard_handler::ClipboardHandler::new() {
                         Ok(handler) => {
                             let handler = std::sync::Arc::new(handler);
-                            let sync_manager = clipboard_core::sync::SyncManager::new(&config, handler);
+                            let mut sync_manager = clipboard_core::sync::SyncManager::new(&config, handler);
+                            if let Some(discovery) = lan_discovery(&app_handle).await {
+                                sync_manager = sync_manager.with_discovery(discovery);
+                            }
+                            *app_handle.state::<SyncClient>().0.lock().unwrap() = Some(sync_manager.handle());
                             tracing::info!("Starting Sync Manager (Client mode)...");
                             sync_manager.run().await;
                         },
@@ -531,6 +769,14 @@ pub fn run() {
 
             Ok(())
         })
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            // Stop discovery tasks and send the mDNS goodbye before exiting
+            if let tauri::RunEvent::Exit = event {
+                if let Some(Some(discovery)) = app.state::<LanDiscovery>().0.get() {
+                    tauri::async_runtime::block_on(discovery.shutdown());
+                }
+            }
+        });
 }
@@ -0,0 +1,243 @@
+//! 历史记录导出/导入的归档格式
+//!
+//! 归档是一个 tar 文件，包含：
+//! - `manifest.json`：格式标识与版本
+//! - `history.jsonl`：每行一条历史记录，按时间从旧到新
+//! - `blobs/<文件名>`：记录引用的上传文件（图片、文件）
+
+use crate::db::HistoryRow;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+
+pub const FORMAT: &str = "syncclipboard-history";
+pub const FORMAT_VERSION: u32 = 1;
+
+const MANIFEST: &str = "manifest.json";
+const HISTORY: &str = "history.jsonl";
+const BLOB_PREFIX: &str = "blobs/";
+
+#[derive(Debug, thiserror::Error)]
+pub enum ArchiveError {
+    /// 归档内容不合法（格式错误、版本不支持、路径非法等）
+    #[error("Invalid archive: {0}")]
+    Invalid(String),
+
+    /// 读写上传目录失败
+    #[error("Storage error: {0}")]
+    Storage(io::Error),
+}
+
+impl From<serde_json::Error> for ArchiveError {
+    fn from(e: serde_json::Error) -> Self {
+        ArchiveError::Invalid(e.to_string())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Manifest {
+    format: String,
+    version: u32,
+    count: usize,
+}
+
+/// `history.jsonl` 中的一行
+#[derive(Serialize, Deserialize)]
+struct Record {
+    #[serde(rename = "type")]
+    r#type: String,
+    content: Option<String>,
+    html: Option<String>,
+    file: Option<String>,
+    hash: Option<String>,
+    device: Option<String>,
+    #[serde(default)]
+    pinned: bool,
+    /// UTC，格式 `YYYY-MM-DD HH:MM:SS`
+    timestamp: String,
+}
+
+impl From<&HistoryRow> for Record {
+    fn from(row: &HistoryRow) -> Self {
+        Self {
+            r#type: row.r#type.clone(),
+            content: row.content.clone(),
+            html: row.html.clone(),
+            file: row.file.clone(),
+            hash: row.hash.clone(),
+            device: row.device.clone(),
+            pinned: row.pinned,
+            timestamp: row.timestamp.clone(),
+        }
+    }
+}
+
+impl From<Record> for HistoryRow {
+    fn from(record: Record) -> Self {
+        Self {
+            id: 0,
+            r#type: record.r#type,
+            content: record.content,
+            file: record.file,
+            hash: record.hash,
+            html: record.html,
+            device: record.device,
+            pinned: record.pinned,
+            timestamp: record.timestamp,
+            snippet: None,
+        }
+    }
+}
+
+/// 读取到的归档内容
+pub struct ImportedArchive {
+    pub rows: Vec<HistoryRow>,
+    /// 归档中的文件，记录导入成功后再移入上传目录
+    pub blobs: StagedBlobs,
+}
+
+/// 解压到上传目录下临时目录中的文件。未调用 `commit` 就被丢弃时（如导入失败）连同临时目录一起删除
+pub struct StagedBlobs {
+    dir: tempfile::TempDir,
+    upload_dir: PathBuf,
+}
+
+impl StagedBlobs {
+    fn new(upload_dir: &Path) -> io::Result<Self> {
+        std::fs::create_dir_all(upload_dir)?;
+        Ok(Self {
+            dir: tempfile::Builder::new().prefix(".import-").tempdir_in(upload_dir)?,
+            upload_dir: upload_dir.to_path_buf(),
+        })
+    }
+
+    /// 把文件移入上传目录，返回新写入的文件数（已存在的文件不会被覆盖）
+    pub fn commit(self) -> io::Result<usize> {
+        let mut moved = 0;
+        for entry in std::fs::read_dir(self.dir.path())? {
+            let entry = entry?;
+            let target = self.upload_dir.join(entry.file_name());
+            if target.exists() {
+                continue;
+            }
+            std::fs::rename(entry.path(), target)?;
+            moved += 1;
+        }
+        Ok(moved)
+    }
+}
+
+/// 上传目录中的文件名只能是单层名称，防止归档通过 `../` 写到目录之外
+pub(crate) fn is_safe_blob_name(name: &str) -> bool {
+    !name.is_empty()
+        && name != "."
+        && name != ".."
+        && !name.contains(['/', '\\', '\0'])
+}
+
+fn append_bytes<W: Write>(builder: &mut tar::Builder<W>, path: &str, data: &[u8]) -> io::Result<()> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(data.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0),
+    );
+    header.set_cksum();
+    builder.append_data(&mut header, path, data)
+}
+
+/// 写出归档。记录引用但上传目录中不存在的文件会被跳过。
+pub fn write_archive<W: Write>(writer: W, rows: &[HistoryRow], upload_dir: &Path) -> io::Result<()> {
+    let mut builder = tar::Builder::new(writer);
+
+    let manifest = Manifest {
+        format: FORMAT.to_string(),
+        version: FORMAT_VERSION,
+        count: rows.len(),
+    };
+    append_bytes(&mut builder, MANIFEST, &serde_json::to_vec_pretty(&manifest)?)?;
+
+    let mut history = Vec::new();
+    for row in rows {
+        serde_json::to_writer(&mut history, &Record::from(row))?;
+        history.push(b'\n');
+    }
+    append_bytes(&mut builder, HISTORY, &history)?;
+
+    let blobs: BTreeSet<&str> = rows
+        .iter()
+        .filter_map(|row| row.file.as_deref())
+        .filter(|name| is_safe_blob_name(name))
+        .collect();
+    for name in blobs {
+        let path = upload_dir.join(name);
+        if !path.is_file() {
+            continue;
+        }
+        builder.append_path_with_name(&path, format!("{}{}", BLOB_PREFIX, name))?;
+    }
+
+    builder.into_inner()?.flush()
+}
+
+/// 读取归档：解析历史记录，并把文件解压到上传目录下的临时目录
+pub fn read_archive<R: Read>(reader: R, upload_dir: &Path) -> Result<ImportedArchive, ArchiveError> {
+    let invalid = |e: io::Error| ArchiveError::Invalid(e.to_string());
+    let mut archive = tar::Archive::new(reader);
+    let mut manifest: Option<Manifest> = None;
+    let mut rows = None;
+    let blobs = StagedBlobs::new(upload_dir).map_err(ArchiveError::Storage)?;
+
+    for entry in archive.entries().map_err(invalid)? {
+        let mut entry = entry.map_err(invalid)?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let path = entry.path().map_err(invalid)?.to_string_lossy().into_owned();
+
+        if path == MANIFEST {
+            let m: Manifest = serde_json::from_reader(&mut entry)?;
+            if m.format != FORMAT {
+                return Err(ArchiveError::Invalid(format!("unknown format '{}'", m.format)));
+            }
+            if m.version > FORMAT_VERSION {
+                return Err(ArchiveError::Invalid(format!(
+                    "archive version {} is newer than supported version {}",
+                    m.version, FORMAT_VERSION
+                )));
+            }
+            manifest = Some(m);
+        } else if path == HISTORY {
+            let rows = rows.insert(Vec::new());
+            for line in BufReader::new(&mut entry).lines() {
+                let line = line.map_err(invalid)?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let record: Record = serde_json::from_str(&line)?;
+                rows.push(HistoryRow::from(record));
+            }
+        } else if let Some(name) = path.strip_prefix(BLOB_PREFIX) {
+            if !is_safe_blob_name(name) {
+                return Err(ArchiveError::Invalid(format!("illegal file name '{}'", name)));
+            }
+            if upload_dir.join(name).exists() {
+                continue;
+            }
+            let mut file = std::fs::File::create(blobs.dir.path().join(name)).map_err(ArchiveError::Storage)?;
+            io::copy(&mut entry, &mut file).map_err(invalid)?;
+        }
+    }
+
+    if manifest.is_none() {
+        return Err(ArchiveError::Invalid(format!("missing {}", MANIFEST)));
+    }
+    let Some(rows) = rows else {
+        return Err(ArchiveError::Invalid(format!("missing {}", HISTORY)));
+    };
+    Ok(ImportedArchive { rows, blobs })
+}
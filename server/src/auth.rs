@@ -11,8 +11,9 @@ pub async fn auth_middleware(
     req: Request,
     next: Next,
 ) -> Result<Response, StatusCode> {
-    // If no token configured, allow all
-    if state.token.is_none() {
+    // If no token configured, allow all. Discovery info is what LAN announcements already
+    // broadcast, and tells scanning clients that a token is needed
+    if state.token.is_none() || req.uri().path() == "/api/discovery" {
         return Ok(next.run(req).await);
     }
     
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use std::time::{SystemTime, Duration};
 use tokio::sync::RwLock;
@@ -10,30 +10,57 @@ pub struct ConnectedClient {
     pub ip: String,
     pub device_name: Option<String>,
     pub user_agent: Option<String>,
+    /// 同步客户端通过 `X-Sync-Direction` 声明的同步方向（both / send / receive）
+    pub sync_direction: Option<String>,
     #[serde(skip)]
     pub last_seen: SystemTime,
     /// 最后活跃时间（秒级时间戳，用于前端显示）
     pub last_seen_timestamp: u64,
 }
 
+/// 活跃判定超时（5分钟）
+const ACTIVE_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// 请求来源的客户端标识：优先使用同步客户端发送的 `X-Device-Id`，否则为连接的客户端IP。
+/// 由追踪中间件写入请求扩展。
+#[derive(Clone, Debug)]
+pub struct ClientKey(pub String);
+
 /// 全局客户端追踪器
 pub struct ClientTracker {
     clients: Arc<RwLock<HashMap<String, ConnectedClient>>>,
+    /// 轮询剪贴板的同步客户端（按客户端标识）及其最后轮询时间
+    sync_clients: Arc<RwLock<HashMap<String, SystemTime>>>,
 }
 
 impl ClientTracker {
     pub fn new() -> Self {
         Self {
             clients: Arc::new(RwLock::new(HashMap::new())),
+            sync_clients: Arc::new(RwLock::new(HashMap::new())),
         }
     }
 
+    /// 记录同步客户端的一次剪贴板轮询
+    pub async fn record_sync(&self, key: &str) {
+        self.sync_clients.write().await.insert(key.to_string(), SystemTime::now());
+    }
+
+    /// 获取活跃的同步客户端标识（5分钟内轮询过剪贴板）
+    pub async fn active_sync_clients(&self) -> HashSet<String> {
+        let mut clients = self.sync_clients.write().await;
+        let now = SystemTime::now();
+        clients.retain(|_, last_sync| now.duration_since(*last_sync).unwrap_or(ACTIVE_TIMEOUT) < ACTIVE_TIMEOUT);
+        clients.keys().cloned().collect()
+    }
+
     /// 记录客户端访问
     pub async fn record_client(
         &self,
         ip: String,
         device_name: Option<String>,
         user_agent: Option<String>,
+        sync_direction: Option<String>,
     ) {
         let now = SystemTime::now();
         let timestamp = now
@@ -58,10 +85,17 @@ impl ClientTracker {
             user_agent
         };
 
+        // 浏览器等非同步请求不带方向，保留同步客户端此前声明的值
+        let final_sync_direction = match clients.get(&ip) {
+            Some(existing) => sync_direction.or(existing.sync_direction.clone()),
+            None => sync_direction,
+        };
+
         let client = ConnectedClient {
             ip: ip.clone(),
             device_name: final_device_name,
             user_agent: final_user_agent,
+            sync_direction: final_sync_direction,
             last_seen: now,
             last_seen_timestamp: timestamp,
         };
@@ -73,11 +107,10 @@ impl ClientTracker {
     pub async fn get_active_clients(&self) -> Vec<ConnectedClient> {
         let mut clients = self.clients.write().await;
         let now = SystemTime::now();
-        let timeout = Duration::from_secs(300); // 5分钟
 
         // 清理过期客户端
         clients.retain(|_, client| {
-            now.duration_since(client.last_seen).unwrap_or(timeout) < timeout
+            now.duration_since(client.last_seen).unwrap_or(ACTIVE_TIMEOUT) < ACTIVE_TIMEOUT
         });
 
         // 返回活跃客户端列表
@@ -0,0 +1,194 @@
+use async_trait::async_trait;
+use clipboard_core::clipboard::ClipboardData;
+use clipboard_core::config::HistoryConfig;
+use clipboard_core::mesh::Stamp;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::Notify;
+
+pub mod schema;
+
+mod sqlite;
+pub use sqlite::SqliteStore;
+
+#[cfg(feature = "postgres")]
+mod postgres;
+#[cfg(feature = "postgres")]
+pub use postgres::PostgresStore;
+
+/// 历史存储错误
+#[derive(Debug, thiserror::Error)]
+pub enum StoreError {
+    #[error("SQLite error: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+
+    #[cfg(feature = "postgres")]
+    #[error("PostgreSQL error: {0}")]
+    Postgres(#[from] tokio_postgres::Error),
+
+    #[cfg(feature = "postgres")]
+    #[error("PostgreSQL pool error: {0}")]
+    Pool(#[from] deadpool_postgres::PoolError),
+
+    #[cfg(feature = "postgres")]
+    #[error("PostgreSQL pool setup failed: {0}")]
+    PoolBuild(#[from] deadpool_postgres::BuildError),
+
+    #[error("Database task failed: {0}")]
+    Task(#[from] tokio::task::JoinError),
+
+    #[error("Database schema version {0} is newer than supported version {supported}", supported = schema::SCHEMA_VERSION)]
+    SchemaTooNew(u32),
+
+    #[error("Unsupported database url: {0}")]
+    UnsupportedUrl(String),
+}
+
+pub type Result<T> = std::result::Result<T, StoreError>;
+
+/// 历史记录行
+#[derive(Debug, Clone)]
+pub struct HistoryRow {
+    pub id: i64,
+    pub r#type: String,
+    pub content: Option<String>,
+    pub file: Option<String>,
+    pub hash: Option<String>,
+    pub html: Option<String>,
+    pub device: Option<String>,
+    pub pinned: bool,
+    pub timestamp: String,
+    /// 全文搜索时的高亮摘要（匹配词以 `<mark>` 包裹）
+    pub snippet: Option<String>,
+}
+
+/// 导入结果
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct ImportStats {
+    /// 新插入的记录数
+    pub imported: usize,
+    /// 与已有记录重复而跳过的记录数
+    pub skipped: usize,
+}
+
+/// 写入结果
+#[derive(Debug, Clone)]
+pub struct Saved {
+    /// 新记录的 ID
+    pub id: i64,
+    /// 超出 `max_count` 被删除的旧记录（用于清理其上传文件）
+    pub pruned: Vec<HistoryRow>,
+}
+
+/// 默认每页条数
+pub const DEFAULT_HISTORY_LIMIT: u32 = 50;
+
+/// 历史查询条件，对应 `GET /history` 的查询参数
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct HistoryQuery {
+    /// 全文搜索关键词（匹配文本内容及 HTML 中提取的文本），结果按相关度排序
+    pub q: Option<String>,
+    /// 类型过滤：Text / Image / File
+    #[serde(rename = "type")]
+    pub r#type: Option<String>,
+    /// 来源设备过滤
+    pub device: Option<String>,
+    /// 置顶状态过滤
+    pub pinned: Option<bool>,
+    /// 起始时间（含），如 `2026-01-01` 或 `2026-01-01 08:00:00`，UTC
+    pub since: Option<String>,
+    /// 截止时间（含），格式同 `since`
+    pub until: Option<String>,
+    pub limit: Option<u32>,
+    pub offset: Option<u32>,
+}
+
+impl HistoryQuery {
+    /// 非空的搜索关键词
+    pub fn search_text(&self) -> Option<&str> {
+        self.q.as_deref().map(str::trim).filter(|q| !q.is_empty())
+    }
+
+    /// 截止时间；只给出日期时包含当天全天
+    pub fn until_bound(&self) -> Option<String> {
+        self.until.as_ref().map(|until| {
+            if until.len() == 10 {
+                format!("{} 23:59:59", until)
+            } else {
+                until.clone()
+            }
+        })
+    }
+
+    pub fn limit(&self) -> u32 {
+        self.limit.unwrap_or(DEFAULT_HISTORY_LIMIT)
+    }
+
+    pub fn offset(&self) -> u32 {
+        self.offset.unwrap_or(0)
+    }
+}
+
+/// 剪贴板历史存储后端
+///
+/// SQLite 为默认实现；PostgreSQL 实现（`postgres` feature）允许多个服务端副本共享同一份历史。
+#[async_trait]
+pub trait HistoryStore: Send + Sync {
+    /// 保存一条剪贴板记录，按 `max_count` 清理旧记录（置顶项除外），返回新记录的 ID 与被清理的记录。
+    /// 敏感条目只保存不含内容的占位行，内容由调用方保存在内存中。
+    /// `stamp` 为 mesh 条目的 Lamport 时间戳，与记录一起保存，重启后用于恢复排序状态。
+    async fn save(&self, data: &ClipboardData, stamp: Option<&Stamp>) -> Result<Saved>;
+
+    /// 时间戳最大的 mesh 记录。敏感、限时或已删除内容的记录只返回时间戳（这类条目不补发给其他节点）
+    async fn latest_stamped(&self) -> Result<Option<(Stamp, Option<ClipboardData>)>>;
+
+    /// 获取最新一条剪贴板记录（按时间戳，导入的旧记录不会成为当前剪贴板）。
+    /// 最新记录是敏感占位或已过期时返回 `None`，不回退到更早的记录
+    async fn get_latest(&self) -> Result<Option<ClipboardData>>;
+
+    /// 获取最新一条记录的 ID（用于长轮询，包含敏感占位）
+    async fn get_latest_id(&self) -> Result<Option<i64>>;
+
+    /// 按条件分页获取历史记录。
+    /// 无搜索关键词时置顶优先、新记录在前；有关键词时按相关度排序并返回高亮摘要。
+    async fn get_history(&self, query: &HistoryQuery) -> Result<Vec<HistoryRow>>;
+
+    async fn delete_history(&self, id: i64) -> Result<()>;
+
+    async fn set_pinned(&self, id: i64, pinned: bool) -> Result<()>;
+
+    /// 删除指定类型中超过 `days` 天的记录，返回被删除的记录（用于清理其上传文件）
+    async fn delete_expired(&self, r#type: &str, days: u32, keep_pinned: bool) -> Result<Vec<HistoryRow>>;
+
+    /// 清除已过 TTL 的记录内容，返回清除前的记录（用于清理其上传文件）
+    async fn expire_entries(&self) -> Result<Vec<HistoryRow>>;
+
+    /// 导出全部历史记录，按时间从旧到新
+    async fn export_history(&self) -> Result<Vec<HistoryRow>>;
+
+    /// 导入历史记录，保留置顶状态、设备与时间戳（`id` 与 `snippet` 被忽略）。
+    /// 与已有记录内容和时间戳完全相同的行视为重复并跳过。
+    async fn import_history(&self, rows: Vec<HistoryRow>) -> Result<ImportStats>;
+
+    /// 订阅其他写入者（例如其他副本）产生的变更，收到变更时唤醒 `notify`。
+    /// 单进程后端无需实现：本进程写入后由处理器直接唤醒。
+    fn watch(&self, _notify: Arc<Notify>) {}
+}
+
+/// 根据配置打开历史存储
+///
+/// 未配置 `history.database_url` 时使用 `history.db_path` 处的 SQLite 数据库。
+pub async fn open(config: &HistoryConfig) -> Result<Arc<dyn HistoryStore>> {
+    match config.database_url.as_deref() {
+        None => Ok(Arc::new(SqliteStore::new(&config.db_path, config.max_count)?)),
+        Some(url) if url.starts_with("sqlite://") => {
+            let path = url.trim_start_matches("sqlite://");
+            Ok(Arc::new(SqliteStore::new(path, config.max_count)?))
+        }
+        #[cfg(feature = "postgres")]
+        Some(url) if url.starts_with("postgres://") || url.starts_with("postgresql://") => {
+            Ok(Arc::new(PostgresStore::connect(url, config.max_count).await?))
+        }
+        Some(url) => Err(StoreError::UnsupportedUrl(url.to_string())),
+    }
+}
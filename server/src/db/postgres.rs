@@ -0,0 +1,445 @@
+use super::{HistoryQuery, HistoryRow, HistoryStore, ImportStats, Result, Saved};
+use async_trait::async_trait;
+use clipboard_core::clipboard::ClipboardData;
+use clipboard_core::mesh::Stamp;
+use futures_util::{stream, StreamExt};
+use std::sync::Arc;
+use tokio::sync::Notify;
+use tokio_postgres::types::ToSql;
+use deadpool_postgres::{Manager, ManagerConfig, Object, Pool, RecyclingMethod};
+use tokio_postgres::{AsyncMessage, NoTls};
+
+/// LISTEN/NOTIFY 通道名，所有副本共享
+const NOTIFY_CHANNEL: &str = "syncclipboard_history";
+
+/// 对外可见的记录：非敏感占位、且未过期
+const VISIBLE: &str = "sensitive = FALSE AND (expires_at IS NULL OR expires_at > now())";
+
+/// 监听连接断开后的重连间隔
+const RECONNECT_DELAY_SECS: u64 = 5;
+
+/// PostgreSQL 历史存储
+///
+/// 多个服务端副本可指向同一数据库：写入时发送 `NOTIFY`，
+/// 每个副本通过独立的 `LISTEN` 连接唤醒本地的长轮询请求。
+pub struct PostgresStore {
+    /// 每次调用取出一个连接，事务在同一连接上执行；断开的连接由连接池替换
+    pool: Pool,
+    url: String,
+    max_count: u32,
+}
+
+impl PostgresStore {
+    pub async fn connect(url: &str, max_count: u32) -> Result<Self> {
+        let manager = Manager::from_config(
+            url.parse()?,
+            NoTls,
+            ManagerConfig { recycling_method: RecyclingMethod::Fast },
+        );
+        let pool = Pool::builder(manager).build()?;
+
+        // Fails at startup when the database is unreachable
+        pool.get().await?
+            .batch_execute(
+                "CREATE TABLE IF NOT EXISTS history (
+                    id BIGSERIAL PRIMARY KEY,
+                    type TEXT NOT NULL,
+                    content TEXT,
+                    html TEXT,
+                    file TEXT,
+                    hash TEXT,
+                    device TEXT,
+                    pinned BOOLEAN NOT NULL DEFAULT FALSE,
+                    timestamp TIMESTAMPTZ NOT NULL DEFAULT now()
+                );
+                ALTER TABLE history ADD COLUMN IF NOT EXISTS search tsvector GENERATED ALWAYS AS (
+                    to_tsvector('simple', coalesce(content, '') || ' ' || coalesce(regexp_replace(html, '<[^>]*>', ' ', 'g'), ''))
+                ) STORED;
+                CREATE INDEX IF NOT EXISTS history_search_idx ON history USING GIN (search);
+                CREATE INDEX IF NOT EXISTS history_timestamp_idx ON history (timestamp, id);
+                ALTER TABLE history ADD COLUMN IF NOT EXISTS sensitive BOOLEAN NOT NULL DEFAULT FALSE;
+                ALTER TABLE history ADD COLUMN IF NOT EXISTS expires_at TIMESTAMPTZ;
+                ALTER TABLE history ADD COLUMN IF NOT EXISTS mesh_clock BIGINT;
+                ALTER TABLE history ADD COLUMN IF NOT EXISTS mesh_origin TEXT;
+                CREATE INDEX IF NOT EXISTS history_mesh_stamp_idx ON history (mesh_clock, mesh_origin COLLATE \"C\");",
+            )
+            .await?;
+
+        Ok(Self {
+            pool,
+            url: url.to_string(),
+            max_count,
+        })
+    }
+
+    /// 从连接池取出一个连接
+    async fn client(&self) -> Result<Object> {
+        Ok(self.pool.get().await?)
+    }
+
+    /// 建立 LISTEN 连接，收到通知时唤醒等待者，直到连接断开
+    async fn listen(url: &str, notify: &Notify) -> Result<()> {
+        let (client, mut connection) = tokio_postgres::connect(url, NoTls).await?;
+        let mut messages = stream::poll_fn(move |cx| connection.poll_message(cx));
+
+        // 连接的消息必须持续被轮询，LISTEN 命令才能完成
+        let listen_sql = format!("LISTEN {}", NOTIFY_CHANNEL);
+        let listen = client.batch_execute(&listen_sql);
+        tokio::pin!(listen);
+        loop {
+            tokio::select! {
+                res = &mut listen => {
+                    res?;
+                    break;
+                }
+                msg = messages.next() => match msg {
+                    Some(Ok(_)) => {}
+                    Some(Err(e)) => return Err(e.into()),
+                    None => return Ok(()),
+                },
+            }
+        }
+        tracing::info!("Listening for history changes on PostgreSQL channel '{}'", NOTIFY_CHANNEL);
+
+        while let Some(msg) = messages.next().await {
+            if let AsyncMessage::Notification(n) = msg? {
+                tracing::debug!("History change notification (id={})", n.payload());
+                notify.notify_waiters();
+            }
+        }
+        Ok(())
+    }
+}
+
+fn row_to_data(row: &tokio_postgres::Row) -> Option<ClipboardData> {
+    let type_: String = row.get("type");
+    let content: Option<String> = row.get("content");
+    let file: Option<String> = row.get("file");
+    let hash: Option<String> = row.get("hash");
+    let html: Option<String> = row.get("html");
+    let device: Option<String> = row.get("device");
+
+    match type_.as_str() {
+        "Text" => Some(ClipboardData::Text {
+            content: content.unwrap_or_default(),
+            file,
+            html,
+            device,
+            ttl: None,
+            sensitive: false,
+        }),
+        "Image" => Some(ClipboardData::Image {
+            hash,
+            filename: file.unwrap_or_default(),
+            device,
+            ttl: None,
+            sensitive: false,
+        }),
+        "File" => Some(ClipboardData::File {
+            hash,
+            filename: file.unwrap_or_default(),
+            device,
+            ttl: None,
+            sensitive: false,
+        }),
+        _ => None,
+    }
+}
+
+fn history_row(row: &tokio_postgres::Row) -> HistoryRow {
+    HistoryRow {
+        id: row.get("id"),
+        r#type: row.get("type"),
+        content: row.get("content"),
+        file: row.get("file"),
+        hash: row.get("hash"),
+        html: row.get("html"),
+        device: row.get("device"),
+        pinned: row.get("pinned"),
+        timestamp: row.get("timestamp"),
+        snippet: row.get("snippet"),
+    }
+}
+
+#[async_trait]
+impl HistoryStore for PostgresStore {
+    async fn save(&self, data: &ClipboardData, stamp: Option<&Stamp>) -> Result<Saved> {
+        let expires = data.ttl().map(|ttl| ttl as f64);
+        // Insert, trim and notify together: replicas only see the entry once it is committed
+        let mut client = self.client().await?;
+        let tx = client.transaction().await?;
+        let row = match data {
+            _ if data.is_sensitive() => {
+                let type_ = match data {
+                    ClipboardData::Text { .. } => "Text",
+                    ClipboardData::Image { .. } => "Image",
+                    ClipboardData::File { .. } => "File",
+                };
+                let device = match data {
+                    ClipboardData::Text { device, .. }
+                    | ClipboardData::Image { device, .. }
+                    | ClipboardData::File { device, .. } => device,
+                };
+                tx.query_one(
+                    "INSERT INTO history (type, device, sensitive) VALUES ($1, $2, TRUE) RETURNING id",
+                    &[&type_, device],
+                ).await?
+            }
+            ClipboardData::Text { content, file, html, device, .. } => {
+                tx.query_one(
+                    "INSERT INTO history (type, content, file, html, device, expires_at)
+                     VALUES ($1, $2, $3, $4, $5, now() + make_interval(secs => $6)) RETURNING id",
+                    &[&"Text", content, file, html, device, &expires],
+                ).await?
+            }
+            ClipboardData::Image { hash, filename, device, .. } => {
+                tx.query_one(
+                    "INSERT INTO history (type, hash, file, device, expires_at)
+                     VALUES ($1, $2, $3, $4, now() + make_interval(secs => $5)) RETURNING id",
+                    &[&"Image", hash, filename, device, &expires],
+                ).await?
+            }
+            ClipboardData::File { hash, filename, device, .. } => {
+                tx.query_one(
+                    "INSERT INTO history (type, hash, file, device, expires_at)
+                     VALUES ($1, $2, $3, $4, now() + make_interval(secs => $5)) RETURNING id",
+                    &[&"File", hash, filename, device, &expires],
+                ).await?
+            }
+        };
+        let id: i64 = row.get(0);
+        if let Some(stamp) = stamp {
+            tx.execute(
+                "UPDATE history SET mesh_clock = $1, mesh_origin = $2 WHERE id = $3",
+                &[&(stamp.clock as i64), &stamp.origin, &id],
+            ).await?;
+        }
+
+        // Cleanup old history (preserve pinned items)
+        let mut pruned = Vec::new();
+        if self.max_count > 0 {
+            let rows = tx.query(
+                "DELETE FROM history WHERE pinned = FALSE AND id NOT IN (
+                    SELECT id FROM history ORDER BY timestamp DESC, id DESC LIMIT $1
+                 )
+                 RETURNING id, type, content, file, hash, html, device, pinned,
+                           to_char(timestamp AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS') AS timestamp,
+                           NULL::text AS snippet",
+                &[&i64::from(self.max_count)],
+            ).await?;
+            pruned = rows.iter().map(history_row).collect();
+        }
+
+        tx.execute("SELECT pg_notify($1, $2)", &[&NOTIFY_CHANNEL, &id.to_string()]).await?;
+        tx.commit().await?;
+
+        Ok(Saved { id, pruned })
+    }
+
+    async fn latest_stamped(&self) -> Result<Option<(Stamp, Option<ClipboardData>)>> {
+        // Origins compare byte-wise, like `Stamp`'s ordering
+        let sql = "SELECT mesh_clock, mesh_origin, type, content, file, hash, html, device,
+                          sensitive = FALSE AND expires_at IS NULL AS replayable
+                   FROM history WHERE mesh_clock IS NOT NULL
+                   ORDER BY mesh_clock DESC, mesh_origin COLLATE \"C\" DESC LIMIT 1";
+        let row = self.client().await?.query_opt(sql, &[]).await?;
+        Ok(row.map(|row| {
+            let clock: i64 = row.get("mesh_clock");
+            let stamp = Stamp { clock: clock as u64, origin: row.get("mesh_origin") };
+            let data = if row.get("replayable") { row_to_data(&row) } else { None };
+            (stamp, data)
+        }))
+    }
+
+    async fn get_latest(&self) -> Result<Option<ClipboardData>> {
+        let sql = format!(
+            "SELECT type, content, file, hash, html, device, {} AS visible FROM history ORDER BY timestamp DESC, id DESC LIMIT 1",
+            VISIBLE
+        );
+        let row = self.client().await?.query_opt(&sql, &[]).await?;
+        Ok(row.as_ref().filter(|row| row.get("visible")).and_then(row_to_data))
+    }
+
+    async fn get_latest_id(&self) -> Result<Option<i64>> {
+        let row = self.client().await?
+            .query_opt("SELECT id FROM history ORDER BY timestamp DESC, id DESC LIMIT 1", &[])
+            .await?;
+        Ok(row.map(|r| r.get(0)))
+    }
+
+    async fn get_history(&self, query: &HistoryQuery) -> Result<Vec<HistoryRow>> {
+        let search = query.search_text().map(str::to_string);
+        let until = query.until_bound();
+        let limit = i64::from(query.limit());
+        let offset = i64::from(query.offset());
+
+        let mut conditions = vec![VISIBLE.to_string()];
+        let mut params: Vec<&(dyn ToSql + Sync)> = Vec::new();
+        let mut snippet = "NULL::text".to_string();
+        let mut order = "pinned DESC, id DESC".to_string();
+
+        if let Some(search) = &search {
+            params.push(search);
+            let n = params.len();
+            conditions.push(format!("search @@ websearch_to_tsquery('simple', ${})", n));
+            snippet = format!(
+                "ts_headline('simple', coalesce(content, '') || ' ' || coalesce(regexp_replace(html, '<[^>]*>', ' ', 'g'), ''),
+                             websearch_to_tsquery('simple', ${}),
+                             'StartSel=<mark>, StopSel=</mark>, MaxFragments=1, MaxWords=12, MinWords=4')",
+                n
+            );
+            order = format!("ts_rank(search, websearch_to_tsquery('simple', ${})) DESC, id DESC", n);
+        }
+        if let Some(type_) = &query.r#type {
+            params.push(type_);
+            conditions.push(format!("lower(type) = lower(${})", params.len()));
+        }
+        if let Some(device) = &query.device {
+            params.push(device);
+            conditions.push(format!("device = ${}", params.len()));
+        }
+        if let Some(pinned) = &query.pinned {
+            params.push(pinned);
+            conditions.push(format!("pinned = ${}", params.len()));
+        }
+        if let Some(since) = &query.since {
+            params.push(since);
+            conditions.push(format!("timestamp >= CAST(${}::text AS timestamp) AT TIME ZONE 'UTC'", params.len()));
+        }
+        if let Some(until) = &until {
+            params.push(until);
+            conditions.push(format!("timestamp <= CAST(${}::text AS timestamp) AT TIME ZONE 'UTC'", params.len()));
+        }
+
+        let filter = format!("WHERE {}", conditions.join(" AND "));
+        params.push(&limit);
+        params.push(&offset);
+
+        // 时间戳格式与 SQLite 的 CURRENT_TIMESTAMP 保持一致
+        let sql = format!(
+            "SELECT id, type, content, file, hash, html, device, pinned,
+                    to_char(timestamp AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS') AS timestamp,
+                    {} AS snippet
+             FROM history
+             {}
+             ORDER BY {}
+             LIMIT ${} OFFSET ${}",
+            snippet,
+            filter,
+            order,
+            params.len() - 1,
+            params.len(),
+        );
+        let rows = self.client().await?.query(&sql, &params).await?;
+        Ok(rows.iter().map(history_row).collect())
+    }
+
+    async fn delete_history(&self, id: i64) -> Result<()> {
+        self.client().await?.execute("DELETE FROM history WHERE id = $1", &[&id]).await?;
+        Ok(())
+    }
+
+    async fn set_pinned(&self, id: i64, pinned: bool) -> Result<()> {
+        self.client().await?.execute("UPDATE history SET pinned = $1 WHERE id = $2", &[&pinned, &id]).await?;
+        Ok(())
+    }
+
+    async fn delete_expired(&self, r#type: &str, days: u32, keep_pinned: bool) -> Result<Vec<HistoryRow>> {
+        let rows = self.client().await?.query(
+            "DELETE FROM history
+             WHERE type = $1 AND timestamp < now() - make_interval(days => $2) AND (NOT $3 OR pinned = FALSE)
+             RETURNING id, type, content, file, hash, html, device, pinned,
+                       to_char(timestamp AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS') AS timestamp,
+                       NULL::text AS snippet",
+            &[&r#type, &(days as i32), &keep_pinned],
+        ).await?;
+        Ok(rows.iter().map(history_row).collect())
+    }
+
+    async fn expire_entries(&self) -> Result<Vec<HistoryRow>> {
+        // 行保留为占位，只清除内容；RETURNING 返回清除前的值
+        let rows = self.client().await?.query(
+            "UPDATE history h
+             SET content = NULL, html = NULL, file = NULL, hash = NULL
+             FROM (
+                 SELECT id, content, html, file, hash FROM history
+                 WHERE expires_at <= now()
+                   AND (content IS NOT NULL OR html IS NOT NULL OR file IS NOT NULL OR hash IS NOT NULL)
+                 FOR UPDATE
+             ) old
+             WHERE h.id = old.id
+             RETURNING h.id, h.type, old.content, old.file, old.hash, old.html, h.device, h.pinned,
+                       to_char(h.timestamp AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS') AS timestamp,
+                       NULL::text AS snippet",
+            &[],
+        ).await?;
+        Ok(rows.iter().map(history_row).collect())
+    }
+
+    async fn export_history(&self) -> Result<Vec<HistoryRow>> {
+        let sql = format!(
+            "SELECT id, type, content, file, hash, html, device, pinned,
+                    to_char(timestamp AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS') AS timestamp,
+                    NULL::text AS snippet
+             FROM history
+             WHERE {}
+             ORDER BY history.timestamp, id",
+            VISIBLE
+        );
+        let rows = self.client().await?.query(&sql, &[]).await?;
+        Ok(rows.iter().map(history_row).collect())
+    }
+
+    async fn import_history(&self, rows: Vec<HistoryRow>) -> Result<ImportStats> {
+        // All or nothing, like the SQLite store: a failed import leaves no partial history
+        let mut client = self.client().await?;
+        let tx = client.transaction().await?;
+        // Timestamps are exchanged as UTC 'YYYY-MM-DD HH:MM:SS' strings, same as SQLite
+        let exists = tx.prepare(
+            "SELECT 1 FROM history
+             WHERE type = $1 AND content IS NOT DISTINCT FROM $2 AND html IS NOT DISTINCT FROM $3
+               AND file IS NOT DISTINCT FROM $4 AND hash IS NOT DISTINCT FROM $5
+               AND device IS NOT DISTINCT FROM $6
+               AND date_trunc('second', timestamp) = CAST($7::text AS timestamp) AT TIME ZONE 'UTC'",
+        ).await?;
+        let insert = tx.prepare(
+            "INSERT INTO history (type, content, html, file, hash, device, pinned, timestamp)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, CAST($8::text AS timestamp) AT TIME ZONE 'UTC')",
+        ).await?;
+
+        let mut stats = ImportStats::default();
+        for row in &rows {
+            let key: [&(dyn ToSql + Sync); 7] =
+                [&row.r#type, &row.content, &row.html, &row.file, &row.hash, &row.device, &row.timestamp];
+            if tx.query_opt(&exists, &key).await?.is_some() {
+                stats.skipped += 1;
+                continue;
+            }
+            tx.execute(
+                &insert,
+                &[&row.r#type, &row.content, &row.html, &row.file, &row.hash, &row.device, &row.pinned, &row.timestamp],
+            ).await?;
+            stats.imported += 1;
+        }
+
+        if stats.imported > 0 {
+            tx.execute("SELECT pg_notify($1, $2)", &[&NOTIFY_CHANNEL, &"import"]).await?;
+        }
+        tx.commit().await?;
+        Ok(stats)
+    }
+
+    fn watch(&self, notify: Arc<Notify>) {
+        let url = self.url.clone();
+        tokio::spawn(async move {
+            loop {
+                if let Err(e) = Self::listen(&url, &notify).await {
+                    tracing::warn!("PostgreSQL LISTEN connection lost: {}", e);
+                }
+                // 重连期间可能错过通知，唤醒等待者让其重新检查最新 ID
+                notify.notify_waiters();
+                tokio::time::sleep(tokio::time::Duration::from_secs(RECONNECT_DELAY_SECS)).await;
+            }
+        });
+    }
+}
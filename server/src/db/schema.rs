@@ -0,0 +1,377 @@
+//! SQLite 历史数据库的表结构与迁移
+//!
+//! 服务端 (`SqliteStore`) 与桌面端的历史页面都通过本模块打开数据库，
+//! 保证两者看到的表结构一致。版本号记录在 `PRAGMA user_version` 中。
+
+use super::{HistoryQuery, HistoryRow, ImportStats, Result, StoreError};
+use clipboard_core::clipboard::html_to_text;
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension, Transaction};
+
+/// 迁移列表：`MIGRATIONS[i]` 把数据库从版本 `i` 升级到 `i + 1`。
+/// 只能在末尾追加，已发布的迁移不可修改。
+const MIGRATIONS: &[fn(&Transaction) -> Result<()>] = &[
+    migrate_v1_baseline,
+    migrate_v2_fts,
+    migrate_v3_timestamp_index,
+    migrate_v4_ephemeral,
+    migrate_v5_mesh_stamp,
+];
+
+/// 对外可见的记录：非敏感占位、且未过期
+pub const VISIBLE: &str = "sensitive = 0 AND (expires_at IS NULL OR expires_at > CURRENT_TIMESTAMP)";
+
+/// 当前代码对应的表结构版本
+pub const SCHEMA_VERSION: u32 = MIGRATIONS.len() as u32;
+
+/// 打开数据库并升级到最新版本
+pub fn open(path: &str) -> Result<Connection> {
+    let mut conn = Connection::open(path)?;
+    migrate(&mut conn)?;
+    Ok(conn)
+}
+
+/// 读取数据库当前的表结构版本
+pub fn version(conn: &Connection) -> Result<u32> {
+    Ok(conn.pragma_query_value(None, "user_version", |row| row.get(0))?)
+}
+
+/// 依次执行尚未应用的迁移，每个迁移在独立事务中完成
+pub fn migrate(conn: &mut Connection) -> Result<()> {
+    let current = version(conn)?;
+    if current > SCHEMA_VERSION {
+        return Err(StoreError::SchemaTooNew(current));
+    }
+
+    for (from, migration) in MIGRATIONS.iter().enumerate().skip(current as usize) {
+        let to = from as u32 + 1;
+        let tx = conn.transaction()?;
+        migration(&tx)?;
+        tx.pragma_update(None, "user_version", to)?;
+        tx.commit()?;
+        tracing::info!("History database migrated to schema version {}", to);
+    }
+    Ok(())
+}
+
+fn has_column(conn: &Connection, table: &str, column: &str) -> Result<bool> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let mut rows = stmt.query([])?;
+    while let Some(row) = rows.next()? {
+        let name: String = row.get(1)?;
+        if name == column {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// v1: 引入版本号之前的表结构。
+/// 旧数据库可能缺少后来追加的 html / device / pinned 列，此处按需补齐。
+fn migrate_v1_baseline(tx: &Transaction) -> Result<()> {
+    tx.execute(
+        "CREATE TABLE IF NOT EXISTS history (
+            id INTEGER PRIMARY KEY,
+            type TEXT NOT NULL,
+            content TEXT,
+            html TEXT,
+            file TEXT,
+            hash TEXT,
+            device TEXT,
+            pinned BOOLEAN DEFAULT 0,
+            timestamp DATETIME DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+
+    for (column, decl) in [("html", "TEXT"), ("device", "TEXT"), ("pinned", "BOOLEAN DEFAULT 0")] {
+        if !has_column(tx, "history", column)? {
+            tx.execute(&format!("ALTER TABLE history ADD COLUMN {} {}", column, decl), [])?;
+        }
+    }
+    Ok(())
+}
+
+/// v2: 全文搜索。
+/// `html_text` 保存从 HTML 中提取的纯文本，与 `content` 一起建立 FTS5 索引，由触发器保持同步。
+fn migrate_v2_fts(tx: &Transaction) -> Result<()> {
+    if !has_column(tx, "history", "html_text")? {
+        tx.execute("ALTER TABLE history ADD COLUMN html_text TEXT", [])?;
+    }
+
+    // Backfill extracted text for existing rich-text rows
+    let rows: Vec<(i64, String)> = {
+        let mut stmt = tx.prepare("SELECT id, html FROM history WHERE html IS NOT NULL")?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.collect::<rusqlite::Result<_>>()?
+    };
+    for (id, html) in rows {
+        tx.execute("UPDATE history SET html_text = ?1 WHERE id = ?2", params![html_to_text(&html), id])?;
+    }
+
+    tx.execute_batch(
+        "CREATE VIRTUAL TABLE IF NOT EXISTS history_fts USING fts5(
+            content, html_text,
+            content='history', content_rowid='id'
+        );
+
+        CREATE TRIGGER IF NOT EXISTS history_fts_insert AFTER INSERT ON history BEGIN
+            INSERT INTO history_fts(rowid, content, html_text) VALUES (new.id, new.content, new.html_text);
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS history_fts_delete AFTER DELETE ON history BEGIN
+            INSERT INTO history_fts(history_fts, rowid, content, html_text)
+            VALUES ('delete', old.id, old.content, old.html_text);
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS history_fts_update AFTER UPDATE OF content, html_text ON history BEGIN
+            INSERT INTO history_fts(history_fts, rowid, content, html_text)
+            VALUES ('delete', old.id, old.content, old.html_text);
+            INSERT INTO history_fts(rowid, content, html_text) VALUES (new.id, new.content, new.html_text);
+        END;
+
+        INSERT INTO history_fts(history_fts) VALUES ('rebuild');"
+    )?;
+    Ok(())
+}
+
+/// v3: 最新记录按时间戳确定（导入的旧记录 ID 更大），为其建立索引
+fn migrate_v3_timestamp_index(tx: &Transaction) -> Result<()> {
+    tx.execute("CREATE INDEX IF NOT EXISTS history_timestamp ON history (timestamp, id)", [])?;
+    Ok(())
+}
+
+/// v4: 会过期的条目与敏感条目。
+/// 敏感条目只在表中保留不含内容的占位行（内容仅保存在服务端内存中），过期条目的内容会被清除。
+fn migrate_v4_ephemeral(tx: &Transaction) -> Result<()> {
+    for (column, decl) in [("sensitive", "BOOLEAN DEFAULT 0"), ("expires_at", "DATETIME")] {
+        if !has_column(tx, "history", column)? {
+            tx.execute(&format!("ALTER TABLE history ADD COLUMN {} {}", column, decl), [])?;
+        }
+    }
+    Ok(())
+}
+
+/// v5: mesh 条目的 Lamport 时间戳（时钟与来源设备 ID），重启后从时间戳最大的记录恢复排序状态
+fn migrate_v5_mesh_stamp(tx: &Transaction) -> Result<()> {
+    for (column, decl) in [("mesh_clock", "INTEGER"), ("mesh_origin", "TEXT")] {
+        if !has_column(tx, "history", column)? {
+            tx.execute(&format!("ALTER TABLE history ADD COLUMN {} {}", column, decl), [])?;
+        }
+    }
+    tx.execute("CREATE INDEX IF NOT EXISTS history_mesh_stamp ON history (mesh_clock, mesh_origin)", [])?;
+    Ok(())
+}
+
+/// 把用户输入转换为 FTS5 查询：每个词按前缀匹配，词之间为 AND。
+/// 词被整体加引号，避免输入中的 FTS5 语法字符导致查询出错。
+fn fts_query(q: &str) -> Option<String> {
+    let terms: Vec<String> = q
+        .split_whitespace()
+        .map(|term| term.replace('"', ""))
+        .filter(|term| !term.is_empty())
+        .map(|term| format!("\"{}\"*", term))
+        .collect();
+    if terms.is_empty() {
+        None
+    } else {
+        Some(terms.join(" "))
+    }
+}
+
+/// 按条件分页读取历史记录。
+/// 无搜索关键词时置顶优先、新记录在前；有关键词时按 bm25 相关度排序，并生成高亮摘要。
+pub fn read_history(conn: &Connection, query: &HistoryQuery) -> Result<Vec<HistoryRow>> {
+    let fts = query.search_text().and_then(fts_query);
+
+    let mut sql = String::from("SELECT h.id, h.type, h.content, h.file, h.hash, h.html, h.device, h.pinned, h.timestamp");
+    let mut conditions = Vec::new();
+    let mut values: Vec<Value> = Vec::new();
+
+    if let Some(fts) = &fts {
+        sql.push_str(
+            ", snippet(history_fts, -1, '<mark>', '</mark>', '…', 12)
+             FROM history_fts JOIN history h ON h.id = history_fts.rowid"
+        );
+        conditions.push("history_fts MATCH ?");
+        values.push(Value::Text(fts.clone()));
+    } else {
+        sql.push_str(", NULL FROM history h");
+    }
+    conditions.push("h.sensitive = 0 AND (h.expires_at IS NULL OR h.expires_at > CURRENT_TIMESTAMP)");
+
+    if let Some(type_) = &query.r#type {
+        conditions.push("h.type = ? COLLATE NOCASE");
+        values.push(Value::Text(type_.clone()));
+    }
+    if let Some(device) = &query.device {
+        conditions.push("h.device = ?");
+        values.push(Value::Text(device.clone()));
+    }
+    if let Some(pinned) = query.pinned {
+        conditions.push("h.pinned = ?");
+        values.push(Value::Integer(pinned as i64));
+    }
+    if let Some(since) = &query.since {
+        conditions.push("datetime(h.timestamp) >= datetime(?)");
+        values.push(Value::Text(since.clone()));
+    }
+    if let Some(until) = query.until_bound() {
+        conditions.push("datetime(h.timestamp) <= datetime(?)");
+        values.push(Value::Text(until));
+    }
+
+    if !conditions.is_empty() {
+        sql.push_str(" WHERE ");
+        sql.push_str(&conditions.join(" AND "));
+    }
+    if fts.is_some() {
+        sql.push_str(" ORDER BY bm25(history_fts), h.id DESC");
+    } else {
+        sql.push_str(" ORDER BY h.pinned DESC, h.id DESC");
+    }
+    sql.push_str(" LIMIT ? OFFSET ?");
+    values.push(Value::Integer(query.limit() as i64));
+    values.push(Value::Integer(query.offset() as i64));
+
+    let mut stmt = conn.prepare(&sql)?;
+    let rows = stmt.query_map(params_from_iter(values), history_row)?;
+    Ok(rows.collect::<rusqlite::Result<_>>()?)
+}
+
+/// 列顺序：id, type, content, file, hash, html, device, pinned, timestamp, snippet
+fn history_row(row: &rusqlite::Row) -> rusqlite::Result<HistoryRow> {
+    Ok(HistoryRow {
+        id: row.get(0)?,
+        r#type: row.get(1)?,
+        content: row.get(2)?,
+        file: row.get(3)?,
+        hash: row.get(4)?,
+        html: row.get(5)?,
+        device: row.get(6)?,
+        pinned: row.get::<_, Option<bool>>(7)?.unwrap_or(false),
+        timestamp: row.get(8)?,
+        snippet: row.get(9)?,
+    })
+}
+
+/// 删除指定类型中超过 `days` 天的记录，返回被删除的记录
+pub fn delete_expired(conn: &Connection, r#type: &str, days: u32, keep_pinned: bool) -> Result<Vec<HistoryRow>> {
+    let mut stmt = conn.prepare(
+        "DELETE FROM history
+         WHERE type = ?1 AND timestamp < datetime('now', ?2) AND (?3 = 0 OR pinned = 0)
+         RETURNING id, type, content, file, hash, html, device, pinned, timestamp, NULL"
+    )?;
+    let rows = stmt.query_map(params![r#type, format!("-{} days", days), keep_pinned], history_row)?;
+    Ok(rows.collect::<rusqlite::Result<_>>()?)
+}
+
+/// 只保留最新的 `max_count` 条记录（置顶项除外），返回被删除的记录
+pub fn trim_history(conn: &Connection, max_count: u32) -> Result<Vec<HistoryRow>> {
+    let mut stmt = conn.prepare_cached(
+        "DELETE FROM history WHERE pinned = 0 AND id NOT IN (
+            SELECT id FROM history ORDER BY timestamp DESC, id DESC LIMIT ?1
+         )
+         RETURNING id, type, content, file, hash, html, device, pinned, timestamp, NULL"
+    )?;
+    let rows = stmt.query_map(params![max_count], history_row)?;
+    Ok(rows.collect::<rusqlite::Result<_>>()?)
+}
+
+/// 清除已过期记录的内容，返回清除前的记录。
+/// 行本身保留为占位，避免客户端看到的最新 ID 回退、把剪贴板改回更早的内容。
+pub fn expire_entries(conn: &Connection) -> Result<Vec<HistoryRow>> {
+    let expired = {
+        let mut stmt = conn.prepare(
+            "SELECT id, type, content, file, hash, html, device, pinned, timestamp, NULL
+             FROM history
+             WHERE expires_at <= CURRENT_TIMESTAMP
+               AND (content IS NOT NULL OR html IS NOT NULL OR file IS NOT NULL OR hash IS NOT NULL)"
+        )?;
+        let rows = stmt.query_map([], history_row)?;
+        rows.collect::<rusqlite::Result<Vec<_>>>()?
+    };
+    for row in &expired {
+        conn.execute(
+            "UPDATE history SET content = NULL, html = NULL, html_text = NULL, file = NULL, hash = NULL WHERE id = ?1",
+            params![row.id],
+        )?;
+    }
+    Ok(expired)
+}
+
+/// 读取全部历史记录，按时间从旧到新
+pub fn export_history(conn: &Connection) -> Result<Vec<HistoryRow>> {
+    let mut stmt = conn.prepare(
+        &format!(
+            "SELECT id, type, content, file, hash, html, device, pinned, timestamp, NULL
+             FROM history
+             WHERE {}
+             ORDER BY timestamp, id",
+            VISIBLE
+        )
+    )?;
+    let rows = stmt.query_map([], history_row)?;
+    Ok(rows.collect::<rusqlite::Result<_>>()?)
+}
+
+/// 按 ID 读取单条可见的历史记录
+pub fn get_history_item(conn: &Connection, id: i64) -> Result<Option<HistoryRow>> {
+    let mut stmt = conn.prepare(
+        &format!(
+            "SELECT id, type, content, file, hash, html, device, pinned, timestamp, NULL
+             FROM history
+             WHERE id = ?1 AND {}",
+            VISIBLE
+        )
+    )?;
+    Ok(stmt.query_row(params![id], history_row).optional()?)
+}
+
+/// 导入历史记录，跳过内容与时间戳完全相同的已有记录。调用方负责包裹事务。
+pub fn import_history(conn: &Connection, rows: &[HistoryRow]) -> Result<ImportStats> {
+    let mut exists = conn.prepare(
+        "SELECT 1 FROM history
+         WHERE type = ?1 AND content IS ?2 AND html IS ?3 AND file IS ?4 AND hash IS ?5 AND device IS ?6
+           AND timestamp = datetime(?7)"
+    )?;
+    let mut insert = conn.prepare(
+        "INSERT INTO history (type, content, html, html_text, file, hash, device, pinned, timestamp)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, COALESCE(datetime(?9), CURRENT_TIMESTAMP))"
+    )?;
+
+    let mut stats = ImportStats::default();
+    for row in rows {
+        if exists.exists(params![row.r#type, row.content, row.html, row.file, row.hash, row.device, row.timestamp])? {
+            stats.skipped += 1;
+            continue;
+        }
+        let html_text = row.html.as_deref().map(html_to_text);
+        insert.execute(params![
+            row.r#type, row.content, row.html, html_text, row.file, row.hash, row.device, row.pinned, row.timestamp
+        ])?;
+        stats.imported += 1;
+    }
+    Ok(stats)
+}
+
+pub fn delete_history(conn: &Connection, id: i64) -> Result<()> {
+    conn.execute("DELETE FROM history WHERE id = ?1", params![id])?;
+    Ok(())
+}
+
+pub fn set_pinned(conn: &Connection, id: i64, pinned: bool) -> Result<()> {
+    conn.execute("UPDATE history SET pinned = ?1 WHERE id = ?2", params![pinned, id])?;
+    Ok(())
+}
+
+pub fn toggle_pinned(conn: &Connection, id: i64) -> Result<()> {
+    conn.execute("UPDATE history SET pinned = NOT pinned WHERE id = ?1", params![id])?;
+    Ok(())
+}
+
+/// 清空所有未置顶的历史记录
+pub fn clear_unpinned(conn: &Connection) -> Result<()> {
+    conn.execute("DELETE FROM history WHERE pinned = 0", [])?;
+    Ok(())
+}
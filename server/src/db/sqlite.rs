@@ -0,0 +1,309 @@
+use super::{schema, HistoryQuery, HistoryRow, HistoryStore, ImportStats, Result, Saved};
+use async_trait::async_trait;
+use rusqlite::{params, Connection, OpenFlags};
+use clipboard_core::clipboard::{html_to_text, ClipboardData};
+use clipboard_core::mesh::Stamp;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+use tokio::sync::Semaphore;
+
+/// 只读连接池大小
+const READ_POOL_SIZE: usize = 4;
+
+/// 写锁被占用时，读写连接等待的最长时间
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// SQLite 历史存储（默认后端）
+///
+/// 数据库以 WAL 模式打开：一个写连接串行化所有写入，
+/// 读请求从一个小型只读连接池中取连接，读写互不阻塞。
+/// 所有数据库操作都在 `spawn_blocking` 线程中执行，不占用异步运行时的工作线程。
+pub struct SqliteStore {
+    writer: Arc<Mutex<Connection>>,
+    readers: Arc<ReadPool>,
+    max_count: u32,
+}
+
+/// 只读连接池：信号量保证取连接时池中一定有空闲连接
+struct ReadPool {
+    conns: Mutex<Vec<Connection>>,
+    permits: Semaphore,
+}
+
+/// 从池中借出的连接，离开作用域（包括 panic）时归还
+struct PooledConn<'a> {
+    conn: Option<Connection>,
+    pool: &'a ReadPool,
+}
+
+impl Drop for PooledConn<'_> {
+    fn drop(&mut self) {
+        if let Some(conn) = self.conn.take() {
+            lock(&self.pool.conns).push(conn);
+        }
+    }
+}
+
+/// 加锁时忽略中毒状态：连接本身在 panic 后仍然可用（未提交的事务会被回滚）
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+impl SqliteStore {
+    pub fn new(path: &str, max_count: u32) -> Result<Self> {
+        let writer = schema::open(path)?;
+        writer.busy_timeout(BUSY_TIMEOUT)?;
+        let mode: String = writer.pragma_update_and_check(None, "journal_mode", "WAL", |row| row.get(0))?;
+        if !mode.eq_ignore_ascii_case("wal") {
+            tracing::warn!("SQLite WAL mode unavailable for {} (journal_mode={})", path, mode);
+        }
+        writer.pragma_update(None, "synchronous", "NORMAL")?;
+
+        let mut readers = Vec::with_capacity(READ_POOL_SIZE);
+        for _ in 0..READ_POOL_SIZE {
+            let conn = Connection::open_with_flags(
+                path,
+                OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX | OpenFlags::SQLITE_OPEN_URI,
+            )?;
+            conn.busy_timeout(BUSY_TIMEOUT)?;
+            readers.push(conn);
+        }
+
+        Ok(Self {
+            writer: Arc::new(Mutex::new(writer)),
+            readers: Arc::new(ReadPool {
+                conns: Mutex::new(readers),
+                permits: Semaphore::new(READ_POOL_SIZE),
+            }),
+            max_count,
+        })
+    }
+
+    /// 在阻塞线程中使用只读连接执行查询
+    async fn read<T, F>(&self, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Connection) -> Result<T> + Send + 'static,
+    {
+        // 等待空闲连接时只挂起当前任务，不阻塞运行时线程
+        let _permit = self.readers.permits.acquire().await.expect("read pool semaphore closed");
+        let pool = self.readers.clone();
+        tokio::task::spawn_blocking(move || {
+            let conn = PooledConn {
+                conn: lock(&pool.conns).pop(),
+                pool: &pool,
+            };
+            f(conn.conn.as_ref().expect("read pool permit without connection"))
+        }).await?
+    }
+
+    /// 在阻塞线程中使用写连接执行操作
+    async fn write<T, F>(&self, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Connection) -> Result<T> + Send + 'static,
+    {
+        let writer = self.writer.clone();
+        tokio::task::spawn_blocking(move || f(&mut lock(&writer))).await?
+    }
+}
+
+/// 写入一条记录，返回其 ID 与超出 `max_count` 被删除的记录。
+/// 敏感条目只写入不含内容的占位行；带 TTL 的条目记录过期时间。
+fn insert(conn: &Connection, data: &ClipboardData, stamp: Option<&Stamp>, max_count: u32) -> Result<Saved> {
+    let expires = data.ttl().map(|ttl| format!("+{} seconds", ttl));
+    match data {
+        _ if data.is_sensitive() => {
+            let (type_, device) = match data {
+                ClipboardData::Text { device, .. } => ("Text", device),
+                ClipboardData::Image { device, .. } => ("Image", device),
+                ClipboardData::File { device, .. } => ("File", device),
+            };
+            conn.execute(
+                "INSERT INTO history (type, device, sensitive) VALUES (?1, ?2, 1)",
+                params![type_, device],
+            )?;
+        }
+        ClipboardData::Text { content, file, html, device, .. } => {
+            let html_text = html.as_deref().map(html_to_text);
+            conn.execute(
+                "INSERT INTO history (type, content, file, html, html_text, device, expires_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, datetime('now', ?7))",
+                params!["Text", content, file, html, html_text, device, expires],
+            )?;
+        }
+        ClipboardData::Image { hash, filename, device, .. } => {
+            conn.execute(
+                "INSERT INTO history (type, hash, file, device, expires_at) VALUES (?1, ?2, ?3, ?4, datetime('now', ?5))",
+                params!["Image", hash, filename, device, expires],
+            )?;
+        }
+        ClipboardData::File { hash, filename, device, .. } => {
+            conn.execute(
+                "INSERT INTO history (type, hash, file, device, expires_at) VALUES (?1, ?2, ?3, ?4, datetime('now', ?5))",
+                params!["File", hash, filename, device, expires],
+            )?;
+        }
+    }
+    let id = conn.last_insert_rowid();
+    if let Some(stamp) = stamp {
+        conn.execute(
+            "UPDATE history SET mesh_clock = ?1, mesh_origin = ?2 WHERE id = ?3",
+            params![stamp.clock as i64, stamp.origin, id],
+        )?;
+    }
+
+    // Cleanup old history (preserve pinned items)
+    let pruned = if max_count > 0 { schema::trim_history(conn, max_count)? } else { Vec::new() };
+
+    Ok(Saved { id, pruned })
+}
+
+fn latest(conn: &Connection) -> Result<Option<ClipboardData>> {
+    let mut stmt = conn.prepare_cached(&format!(
+        "SELECT type, content, file, hash, html, device, {} AS visible FROM history ORDER BY timestamp DESC, id DESC LIMIT 1",
+        schema::VISIBLE
+    ))?;
+    let mut rows = stmt.query([])?;
+
+    match rows.next()? {
+        Some(row) if row.get("visible")? => row_to_data(row),
+        _ => Ok(None),
+    }
+}
+
+/// 时间戳最大的 mesh 记录，敏感或限时条目只返回时间戳
+fn latest_stamped(conn: &Connection) -> Result<Option<(Stamp, Option<ClipboardData>)>> {
+    let mut stmt = conn.prepare_cached(
+        "SELECT mesh_clock, mesh_origin, type, content, file, hash, html, device,
+                sensitive = 0 AND expires_at IS NULL AS replayable
+         FROM history WHERE mesh_clock IS NOT NULL ORDER BY mesh_clock DESC, mesh_origin DESC LIMIT 1",
+    )?;
+    let mut rows = stmt.query([])?;
+
+    let Some(row) = rows.next()? else {
+        return Ok(None);
+    };
+    let clock: i64 = row.get("mesh_clock")?;
+    let stamp = Stamp {
+        clock: clock as u64,
+        origin: row.get("mesh_origin")?,
+    };
+    let data = if row.get("replayable")? { row_to_data(row)? } else { None };
+    Ok(Some((stamp, data)))
+}
+
+/// 把包含 type / content / file / hash / html / device 列的行转换为剪贴板数据
+fn row_to_data(row: &rusqlite::Row) -> Result<Option<ClipboardData>> {
+    let type_: String = row.get("type")?;
+    let content: Option<String> = row.get("content")?;
+    let file: Option<String> = row.get("file")?;
+    let hash: Option<String> = row.get("hash")?;
+    let html: Option<String> = row.get("html")?;
+    let device: Option<String> = row.get("device")?;
+
+    let data = match type_.as_str() {
+        "Text" => ClipboardData::Text {
+            content: content.unwrap_or_default(),
+            file,
+            html,
+            device,
+            ttl: None,
+            sensitive: false,
+        },
+        "Image" => ClipboardData::Image {
+            hash,
+            filename: file.unwrap_or_default(),
+            device,
+            ttl: None,
+            sensitive: false,
+        },
+        "File" => ClipboardData::File {
+            hash,
+            filename: file.unwrap_or_default(),
+            device,
+            ttl: None,
+            sensitive: false,
+        },
+        _ => return Ok(None),
+    };
+    Ok(Some(data))
+}
+
+fn latest_id(conn: &Connection) -> Result<Option<i64>> {
+    let mut stmt = conn.prepare_cached("SELECT id FROM history ORDER BY timestamp DESC, id DESC LIMIT 1")?;
+    let mut rows = stmt.query([])?;
+
+    if let Some(row) = rows.next()? {
+        Ok(Some(row.get(0)?))
+    } else {
+        Ok(None)
+    }
+}
+
+#[async_trait]
+impl HistoryStore for SqliteStore {
+    async fn save(&self, data: &ClipboardData, stamp: Option<&Stamp>) -> Result<Saved> {
+        let data = data.clone();
+        let stamp = stamp.cloned();
+        let max_count = self.max_count;
+        self.write(move |conn| {
+            let tx = conn.transaction()?;
+            let saved = insert(&tx, &data, stamp.as_ref(), max_count)?;
+            tx.commit()?;
+            Ok(saved)
+        }).await
+    }
+
+    async fn latest_stamped(&self) -> Result<Option<(Stamp, Option<ClipboardData>)>> {
+        self.read(latest_stamped).await
+    }
+
+    async fn get_latest(&self) -> Result<Option<ClipboardData>> {
+        self.read(latest).await
+    }
+
+    async fn get_latest_id(&self) -> Result<Option<i64>> {
+        self.read(latest_id).await
+    }
+
+    async fn get_history(&self, query: &HistoryQuery) -> Result<Vec<HistoryRow>> {
+        let query = query.clone();
+        self.read(move |conn| schema::read_history(conn, &query)).await
+    }
+
+    async fn delete_history(&self, id: i64) -> Result<()> {
+        self.write(move |conn| schema::delete_history(conn, id)).await
+    }
+
+    async fn set_pinned(&self, id: i64, pinned: bool) -> Result<()> {
+        self.write(move |conn| schema::set_pinned(conn, id, pinned)).await
+    }
+
+    async fn delete_expired(&self, r#type: &str, days: u32, keep_pinned: bool) -> Result<Vec<HistoryRow>> {
+        let r#type = r#type.to_string();
+        self.write(move |conn| schema::delete_expired(conn, &r#type, days, keep_pinned)).await
+    }
+
+    async fn expire_entries(&self) -> Result<Vec<HistoryRow>> {
+        self.write(|conn| {
+            let tx = conn.transaction()?;
+            let expired = schema::expire_entries(&tx)?;
+            tx.commit()?;
+            Ok(expired)
+        }).await
+    }
+
+    async fn export_history(&self) -> Result<Vec<HistoryRow>> {
+        self.read(schema::export_history).await
+    }
+
+    async fn import_history(&self, rows: Vec<HistoryRow>) -> Result<ImportStats> {
+        self.write(move |conn| {
+            let tx = conn.transaction()?;
+            let stats = schema::import_history(&tx, &rows)?;
+            tx.commit()?;
+            Ok(stats)
+        }).await
+    }
+}
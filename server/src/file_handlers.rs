@@ -9,7 +9,7 @@ use tokio::io::AsyncWriteExt;
 use std::path::PathBuf;
 use futures_util::StreamExt;
 
-const UPLOAD_DIR: &str = "./uploads";
+pub(crate) const UPLOAD_DIR: &str = "./uploads";
 
 pub async fn upload_file(
     Path(filename): Path<String>,
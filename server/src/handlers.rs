@@ -1,21 +1,38 @@
 use axum::{
-    extract::{State, Query},
+    extract::{Extension, State, Query},
     http::StatusCode,
-    response::Json,
+    response::{IntoResponse, Json},
 };
 use serde::{Deserialize, Serialize};
-use crate::db::Database;
+use crate::client_tracker::ClientKey;
+use crate::db::{HistoryQuery, HistoryRow, HistoryStore, Saved};
+use crate::secrets::SecretStore;
 use std::sync::Arc;
 use clipboard_core::clipboard::ClipboardData;
+use clipboard_core::discovery::{AuthRequirements, DeviceIdentity, DiscoveryInfo};
+use clipboard_core::mesh::Stamp;
 use tokio::sync::Notify;
 
 // Shared state
 #[derive(Clone)]
 pub struct AppState {
-    pub db: Arc<Database>,
+    pub db: Arc<dyn HistoryStore>,
     pub notify: Arc<Notify>,
     pub token: Option<String>,
     pub tracker: Arc<crate::client_tracker::ClientTracker>,
+    pub secrets: Arc<SecretStore>,
+    /// 当前 TLS 证书的 SHA-256 指纹，证书热加载后更新；未启用 HTTPS 时为 None
+    pub tls_fingerprint: tokio::sync::watch::Receiver<Option<String>>,
+    /// 发现公告签名用的 Ed25519 公钥（Base64），未配置 `discovery.key_file` 时为 None
+    pub public_key: Option<String>,
+    /// 本机设备身份，与 UDP 公告一致
+    pub identity: DeviceIdentity,
+    /// 本次运行的实例 ID，与 UDP 公告一致
+    pub instance_id: u64,
+    /// 客户端连接所需的认证
+    pub auth_requirements: AuthRequirements,
+    /// 点对点同步，未启用 `[mesh]` 时为 None
+    pub mesh: Option<Arc<crate::mesh::Mesh>>,
 }
 
 #[derive(Deserialize)]
@@ -35,14 +52,35 @@ pub struct HistoryItem {
     pub device: Option<String>,
     pub timestamp: String,
     pub pinned: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snippet: Option<String>,
+}
+
+impl From<HistoryRow> for HistoryItem {
+    fn from(row: HistoryRow) -> Self {
+        Self {
+            id: row.id,
+            r#type: row.r#type,
+            content: row.content,
+            file: row.file,
+            hash: row.hash,
+            html: row.html,
+            device: row.device,
+            timestamp: row.timestamp,
+            pinned: row.pinned,
+            snippet: row.snippet,
+        }
+    }
 }
 
 pub async fn get_clipboard(
     State(state): State<AppState>,
+    Extension(ClientKey(client)): Extension<ClientKey>,
     Query(query): Query<PollQuery>,
-) -> Result<(axum::http::HeaderMap, Json<ClipboardData>), StatusCode> {
+) -> Result<axum::response::Response, StatusCode> {
+    state.tracker.record_sync(&client).await;
     let wait_time = query.wait.unwrap_or(0);
-    let mut latest_id = state.db.get_latest_id().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?.unwrap_or(0);
+    let mut latest_id = state.db.get_latest_id().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?.unwrap_or(0);
     
     // If long polling is requested
     if wait_time > 0 {
@@ -56,7 +94,7 @@ pub async fn get_clipboard(
             tokio::select! {
                 _ = notified => {
                     // Notified, fetch new ID
-                     latest_id = state.db.get_latest_id().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?.unwrap_or(0);
+                     latest_id = state.db.get_latest_id().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?.unwrap_or(0);
                 }
                 _ = tokio::time::sleep(tokio::time::Duration::from_secs(wait_time)) => {
                     // Timeout
@@ -65,11 +103,31 @@ pub async fn get_clipboard(
         }
     }
 
-    match state.db.get_latest() {
+    let mut headers = axum::http::HeaderMap::new();
+
+    // A sensitive entry is served from memory and destroyed once every sync client has it
+    if let Some(data) = state.secrets.fetch(latest_id, &client) {
+        let burned = state.secrets.burn_delivered(&state.tracker.active_sync_clients().await);
+        if !burned.is_empty() {
+            let (secrets, db) = (state.secrets.clone(), state.db.clone());
+            tokio::spawn(async move {
+                let upload_dir = std::path::Path::new(crate::file_handlers::UPLOAD_DIR);
+                crate::secrets::remove_blobs(&burned, &secrets, db.as_ref(), upload_dir).await;
+            });
+        }
+        headers.insert("X-Clipboard-Id", latest_id.to_string().parse().unwrap());
+        return Ok((headers, Json(data)).into_response());
+    }
+
+    match state.db.get_latest().await {
         Ok(Some(data)) => {
-            let mut headers = axum::http::HeaderMap::new();
             headers.insert("X-Clipboard-Id", latest_id.to_string().parse().unwrap());
-            Ok((headers, Json(data)))
+            Ok((headers, Json(data)).into_response())
+        },
+        // The newest entry was burned or has expired: serving older history would roll clients back
+        Ok(None) if latest_id > 0 => {
+            headers.insert("X-Clipboard-Id", latest_id.to_string().parse().unwrap());
+            Ok((StatusCode::NO_CONTENT, headers).into_response())
         },
         Ok(None) => Err(StatusCode::NOT_FOUND),
         Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
@@ -78,10 +136,59 @@ pub async fn get_clipboard(
 
 pub async fn update_clipboard(
     State(state): State<AppState>,
+    Extension(ClientKey(client)): Extension<ClientKey>,
+    headers: axum::http::HeaderMap,
     Json(payload): Json<ClipboardData>,
 ) -> StatusCode {
-    match state.db.save(&payload) {
-        Ok(_) => {
+    let Some(mesh) = state.mesh.clone() else {
+        return store_clipboard(&state, &payload, client, None).await;
+    };
+    let received = match crate::mesh::received_stamp(&headers) {
+        Ok(received) => received,
+        Err(status) => return status,
+    };
+    let forward = received.is_none();
+
+    // Held until the entry is stored so entries land in timestamp order
+    let mut order = mesh.order().await;
+    let stamp = match received {
+        Some(stamp) => match order.accept(&stamp) {
+            Ok(true) => stamp,
+            Ok(false) => {
+                tracing::debug!("Ignoring mesh entry {}, a newer one is already stored", stamp);
+                return StatusCode::CONFLICT;
+            }
+            Err(status) => {
+                tracing::warn!("Rejecting mesh entry {}, its clock is too far ahead of this device", stamp);
+                return status;
+            }
+        },
+        None => order.tick(),
+    };
+    let status = store_clipboard(&state, &payload, client, Some(&stamp)).await;
+    if status == StatusCode::OK {
+        order.commit(stamp.clone(), &payload);
+        drop(order);
+        if forward {
+            mesh.broadcast(payload, stamp);
+        }
+    }
+    status
+}
+
+async fn store_clipboard(state: &AppState, payload: &ClipboardData, client: String, stamp: Option<&Stamp>) -> StatusCode {
+    match state.db.save(payload, stamp).await {
+        Ok(Saved { id, pruned }) => {
+            if payload.is_sensitive() {
+                state.secrets.insert(id, payload.clone(), client);
+            }
+            if !pruned.is_empty() {
+                let db = state.db.clone();
+                tokio::spawn(async move {
+                    let upload_dir = std::path::Path::new(crate::file_handlers::UPLOAD_DIR);
+                    crate::retention::remove_trimmed(db.as_ref(), &pruned, upload_dir).await;
+                });
+            }
             state.notify.notify_waiters();
             StatusCode::OK
         },
@@ -94,29 +201,14 @@ pub async fn update_clipboard(
 
 pub async fn get_history_list(
     State(state): State<AppState>,
-    Query(params): Query<std::collections::HashMap<String, String>>,
+    Query(query): Query<HistoryQuery>,
 ) -> Result<Json<Vec<HistoryItem>>, StatusCode> {
-    let limit = params.get("limit").and_then(|v| v.parse().ok()).unwrap_or(50);
-    let offset = params.get("offset").and_then(|v| v.parse().ok()).unwrap_or(0);
-
-    let items = state.db.get_history(limit, offset).map_err(|e| {
+    let items = state.db.get_history(&query).await.map_err(|e| {
         tracing::error!("Failed to fetch history: {}", e);
         StatusCode::INTERNAL_SERVER_ERROR
     })?;
 
-    let history: Vec<HistoryItem> = items.into_iter().map(|(id, type_, content, file, hash, html, device, pinned, timestamp)| {
-        HistoryItem {
-            id,
-            r#type: type_,
-            content,
-            file,
-            hash,
-            html,
-            device,
-            pinned,
-            timestamp,
-        }
-    }).collect();
+    let history: Vec<HistoryItem> = items.into_iter().map(HistoryItem::from).collect();
 
     Ok(Json(history))
 }
@@ -130,7 +222,7 @@ pub async fn delete_history(
     State(state): State<AppState>,
     axum::extract::Path(id): axum::extract::Path<i64>,
 ) -> StatusCode {
-    match state.db.delete_history(id) {
+    match state.db.delete_history(id).await {
         Ok(_) => StatusCode::OK,
         Err(e) => {
             tracing::error!("Failed to delete history: {}", e);
@@ -144,7 +236,7 @@ pub async fn pin_history(
     axum::extract::Path(id): axum::extract::Path<i64>,
     Json(payload): Json<PinUpdate>,
 ) -> StatusCode {
-    match state.db.set_pinned(id, payload.pinned) {
+    match state.db.set_pinned(id, payload.pinned).await {
         Ok(_) => StatusCode::OK,
         Err(e) => {
             tracing::error!("Failed to pin history: {}", e);
@@ -153,40 +245,142 @@ pub async fn pin_history(
     }
 }
 
-// ===== Discovery API for cross-subnet device discovery =====
+// ===== History export / import =====
 
-/// Discovery information returned to help clients identify this server
+/// Import result returned by `POST /api/import`
 #[derive(Serialize)]
-pub struct DiscoveryInfo {
-    /// Service identifier
-    pub service: String,
-    /// Server version
-    pub version: String,
-    /// Device name
-    pub device_name: String,
-    /// Device ID (placeholder for now)
-    pub device_id: String,
-    /// Server capabilities
-    pub capabilities: Vec<String>,
+pub struct ImportResult {
+    pub imported: usize,
+    pub skipped: usize,
+    pub blobs: usize,
 }
 
+/// Export the whole history with referenced uploads as a tar archive
+///
+/// GET /api/export
+pub async fn export_history(
+    State(state): State<AppState>,
+) -> Result<axum::response::Response, StatusCode> {
+    let rows = state.db.export_history().await.map_err(|e| {
+        tracing::error!("Failed to export history: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    // The archive is produced on a blocking thread and streamed through a channel.
+    // A write error is sent as the last item so the response is aborted instead of
+    // ending like a complete (but truncated) archive.
+    let (tx, rx) = tokio::sync::mpsc::channel::<std::io::Result<axum::body::Bytes>>(16);
+    tokio::task::spawn_blocking(move || {
+        let upload_dir = std::path::Path::new(crate::file_handlers::UPLOAD_DIR);
+        let writer = std::io::BufWriter::with_capacity(64 * 1024, ChannelWriter(tx.clone()));
+        if let Err(e) = crate::archive::write_archive(writer, &rows, upload_dir) {
+            tracing::error!("Failed to write history archive: {}", e);
+            let _ = tx.blocking_send(Err(e));
+        }
+    });
+
+    let stream = futures_util::stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|chunk| (chunk, rx))
+    });
+    let body = axum::body::Body::from_stream(stream);
+    Ok((
+        [
+            (axum::http::header::CONTENT_TYPE, "application/x-tar"),
+            (axum::http::header::CONTENT_DISPOSITION, "attachment; filename=\"syncclipboard-history.tar\""),
+        ],
+        body,
+    ).into_response())
+}
+
+/// Blocking writer that forwards each chunk to the export response body
+struct ChannelWriter(tokio::sync::mpsc::Sender<std::io::Result<axum::body::Bytes>>);
+
+impl std::io::Write for ChannelWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0
+            .blocking_send(Ok(axum::body::Bytes::copy_from_slice(buf)))
+            .map_err(|_| std::io::Error::new(std::io::ErrorKind::BrokenPipe, "export response closed"))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Import an archive produced by `/api/export`, skipping duplicates
+///
+/// POST /api/import
+pub async fn import_history(
+    State(state): State<AppState>,
+    req: axum::extract::Request,
+) -> Result<Json<ImportResult>, StatusCode> {
+    use futures_util::TryStreamExt;
+
+    let stream = req.into_body().into_data_stream().map_err(std::io::Error::other);
+    let reader = tokio_util::io::SyncIoBridge::new(tokio_util::io::StreamReader::new(stream));
+    let archive = tokio::task::spawn_blocking(move || {
+        let upload_dir = std::path::Path::new(crate::file_handlers::UPLOAD_DIR);
+        crate::archive::read_archive(reader, upload_dir)
+    })
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .map_err(|e| match e {
+        crate::archive::ArchiveError::Invalid(_) => {
+            tracing::warn!("Rejected history archive: {}", e);
+            StatusCode::BAD_REQUEST
+        }
+        crate::archive::ArchiveError::Storage(_) => {
+            tracing::error!("Failed to import history archive: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    })?;
+
+    // Files only reach the upload dir once their rows are stored; dropping the staged files removes them
+    let stats = state.db.import_history(archive.rows).await.map_err(|e| {
+        tracing::error!("Failed to import history: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let blobs = tokio::task::spawn_blocking(move || archive.blobs.commit())
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .map_err(|e| {
+            tracing::error!("Failed to move imported files into place: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    if stats.imported > 0 {
+        state.notify.notify_waiters();
+    }
+    tracing::info!(
+        "Imported history archive: {} new, {} duplicates, {} files",
+        stats.imported, stats.skipped, blobs
+    );
+
+    Ok(Json(ImportResult {
+        imported: stats.imported,
+        skipped: stats.skipped,
+        blobs,
+    }))
+}
+
+// ===== Discovery API for cross-subnet device discovery =====
+
 /// Discovery endpoint - allows clients to identify this as a SyncClipboard server
 /// This endpoint is designed for cross-subnet discovery via HTTP scanning
 /// 
-/// GET /api/discovery
-/// Response: JSON with server information
+/// GET /api/discovery (no token required)
+/// Response: JSON with the same identity as the UDP announcements, plus TLS and auth requirements
 pub async fn get_discovery_info(
-    State(_state): State<AppState>,
+    State(state): State<AppState>,
 ) -> Json<DiscoveryInfo> {
     Json(DiscoveryInfo {
         service: "SyncClipboard".to_string(),
-        version: env!("CARGO_PKG_VERSION").to_string(),
-        device_name: hostname::get()
-            .ok()
-            .and_then(|h| h.into_string().ok())
-            .unwrap_or_else(|| "Unknown Device".to_string()),
-        device_id: "auto-generated".to_string(), // TODO: get from config when available
-        capabilities: vec!["clipboard".to_string(), "file".to_string(), "history".to_string()],
+        identity: state.identity.clone(),
+        device_name: state.identity.alias.clone(),
+        instance_id: state.instance_id,
+        tls_fingerprint: state.tls_fingerprint.borrow().clone(),
+        public_key: state.public_key.clone(),
+        auth: state.auth_requirements,
     })
 }
 
@@ -1,9 +1,10 @@
 use clipboard_core::config::Config;
+use clipboard_core::discovery::{local_device, AuthRequirements, DeviceIdentity, DiscoveryAuth, DiscoveryService};
 use handlers::{AppState, get_clipboard, update_clipboard};
 use file_handlers::{upload_file, get_download_file, head_file};
 use std::sync::Arc;
 use axum::{
-    routing::{get, any, delete},
+    routing::{get, post, any, delete},
     Router,
 };
 
@@ -12,8 +13,10 @@ pub mod file_handlers;
 mod auth;
 use auth::auth_middleware;
 
-mod db;
-use db::Database;
+pub mod db;
+pub mod archive;
+pub mod retention;
+mod secrets;
 
 mod webdav;
 use webdav::WebDavRouter;
@@ -23,14 +26,137 @@ use client_tracker::ClientTracker;
 
 mod tracking_middleware;
 
+pub mod tls;
+
+mod mesh;
+
+/// mDNS TXT 记录中证书指纹的键
+const TXT_TLS_FINGERPRINT: &str = "tls_fingerprint";
+
+/// 服务端的后台任务，服务端退出（包括出错返回或被取消）时一并停止，
+/// 避免重启服务端后旧任务继续操作原来的数据库与上传目录
+#[derive(Default)]
+struct BackgroundTasks(Vec<tokio::task::JoinHandle<()>>);
+
+impl BackgroundTasks {
+    fn push(&mut self, task: tokio::task::JoinHandle<()>) {
+        self.0.push(task);
+    }
+}
+
+impl Drop for BackgroundTasks {
+    fn drop(&mut self) {
+        for task in &self.0 {
+            task.abort();
+        }
+    }
+}
+
 pub async fn run(config: Config) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let db = Database::new(&config.history.db_path, config.history.max_count)?;
+    run_with_discovery(config, None).await
+}
+
+/// 同 `run`，但使用调用方创建的发现服务（如桌面端整个进程共用的服务），其本机设备端口应为服务端口。
+/// 为 None 时自行创建，服务端退出时关闭；调用方的服务由调用方关闭
+pub async fn run_with_discovery(
+    config: Config,
+    discovery: Option<Arc<DiscoveryService>>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let tls_paths = match &config.server.tls {
+        Some(tls_config) => Some(tls::prepare(tls_config, &config.general.device_name)?),
+        None => None,
+    };
+    let fingerprint = match &tls_paths {
+        Some(paths) => Some(tls::read_fingerprint(&paths.cert)?),
+        None => None,
+    };
+    let (fingerprint_tx, fingerprint_rx) = tokio::sync::watch::channel(fingerprint);
+
+    // ========== 混合发现服务初始化 ==========
+    // 公告与 /api/discovery 使用同一设备身份
+    let owns_discovery = discovery.is_none();
+    let (discovery, my_device, public_key, discovery_required) = match discovery {
+        Some(discovery) => {
+            let device = discovery.device().clone();
+            let public_key = discovery.auth().public_key();
+            let required = discovery.auth().is_required();
+            (Some(discovery), device, public_key, required)
+        }
+        None => {
+            // Invalid discovery keys must not silently turn authentication off
+            let auth = DiscoveryAuth::from_config(&config.discovery)?;
+            let device = local_device(&config, config.server.port);
+            let public_key = auth.public_key();
+            let required = auth.is_required();
+            // 创建混合发现服务（UDP Multicast + mDNS + 缓存管理），监听端口绑定后启动
+            let discovery = match DiscoveryService::with_auth(device.clone(), auth).await {
+                Ok(discovery) => Some(Arc::new(discovery)),
+                Err(e) => {
+                    tracing::error!("✗ Failed to initialize hybrid discovery service: {}", e);
+                    tracing::error!("  This means LAN discovery (UDP multicast and mDNS) will NOT work!");
+                    None
+                }
+            };
+            (discovery, device, public_key, required)
+        }
+    };
+    if let Some(key) = &public_key {
+        tracing::info!("Discovery public key: {}", key);
+    }
+    let auth_requirements = AuthRequirements {
+        token: config.auth.token.is_some(),
+        discovery: discovery_required,
+    };
+    // 点对点同步通过发现服务寻找节点，推送会带上令牌与敏感条目，必须能确认节点身份
+    if config.mesh.enabled && !clipboard_core::mesh::peers_authenticated(&config.mesh, discovery_required) {
+        return Err("mesh sync needs authenticated peers: set discovery.secret or discovery.trusted_devices, \
+            or use mesh.scheme = \"https\" with mesh.tls.fingerprint"
+            .into());
+    }
+    let mesh = match (&discovery, config.mesh.enabled) {
+        (Some(discovery), true) => Some(Arc::new(mesh::Mesh::new(&config, discovery.clone()))),
+        (None, true) => {
+            tracing::error!("✗ Mesh sync needs LAN discovery, which is unavailable; entries stay on this device");
+            None
+        }
+        (_, false) => None,
+    };
+    // ========================================
+
+    let db = db::open(&config.history).await?;
+    if let Some(mesh) = &mesh {
+        mesh.restore(db.as_ref()).await?;
+    }
+    let notify = Arc::new(tokio::sync::Notify::new());
+    db.watch(notify.clone());
+    let mut tasks = BackgroundTasks::default();
+    if let Some(task) = retention::spawn(
+        db.clone(),
+        config.history.retention.clone(),
+        std::path::PathBuf::from(file_handlers::UPLOAD_DIR),
+    ) {
+        tasks.push(task);
+    }
     let tracker = Arc::new(ClientTracker::new());
+    let secrets = Arc::new(secrets::SecretStore::new());
+    tasks.push(secrets::spawn_sweeper(
+        db.clone(),
+        secrets.clone(),
+        tracker.clone(),
+        std::path::PathBuf::from(file_handlers::UPLOAD_DIR),
+    ));
     let state = AppState {
-        db: Arc::new(db),
-        notify: Arc::new(tokio::sync::Notify::new()),
+        db,
+        notify,
         token: config.auth.token.clone(),
         tracker,
+        secrets,
+        tls_fingerprint: fingerprint_rx.clone(),
+        public_key,
+        identity: DeviceIdentity::new(&my_device),
+        instance_id: my_device.instance_id,
+        auth_requirements,
+        mesh: mesh.clone(),
     };
 
     let mut router = Router::new()
@@ -39,7 +165,9 @@ pub async fn run(config: Config) -> Result<(), Box<dyn std::error::Error + Send
         .route("/history/{id}", delete(handlers::delete_history).patch(handlers::pin_history))
         .route("/file/{filename}", get(get_download_file).put(upload_file).head(head_file))
         .route("/api/discovery", get(handlers::get_discovery_info))  // New: Discovery endpoint for cross-subnet scanning
-        .route("/api/connected_devices", get(handlers::get_connected_devices));  // New: Get connected clients
+        .route("/api/connected_devices", get(handlers::get_connected_devices))  // New: Get connected clients
+        .route("/api/export", get(handlers::export_history))
+        .route("/api/import", post(handlers::import_history));
 
     if config.server.wevdav_enabled {
         let upload_dir = "./uploads";
@@ -102,77 +230,81 @@ pub async fn run(config: Config) -> Result<(), Box<dyn std::error::Error + Send
         .layer(axum::middleware::from_fn_with_state(state.clone(), auth_middleware))
         .layer(axum::middleware::from_fn_with_state(state.clone(), tracking_middleware::client_tracking_middleware));  // Track all clients BEFORE auth
 
-    let addr_str = format!("{}:{}", config.server.host, config.server.port);
-    let addr: std::net::SocketAddr = addr_str.parse()?; // Ensure host is IP or update config default to 0.0.0.0
+    let listener = bind_listener(&config.server.host, config.server.port)?;
+    let addr = listener.local_addr()?;
 
-    // ========== 混合发现服务初始化 ==========
-    // 生成实例 ID（用于重启检测）
-    let instance_id = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap()
-        .as_secs();
-    
-    // 创建设备信息
-    let my_device = clipboard_core::discovery::Device {
-        id: config.general.device_id.clone(),
-        name: config.general.device_name.clone(),
-        ip: "0.0.0.0".to_string(),
-        port: config.server.port,
-        instance_id,
-        capabilities: vec!["clipboard".to_string(), "file".to_string()],
-    };
-    
-    // 启动混合发现服务（UDP Multicast + 缓存管理）
-    match clipboard_core::discovery::DiscoveryService::new(my_device).await {
-        Ok(discovery) => {
-            let discovery_arc = std::sync::Arc::new(discovery);
-            tokio::spawn(async move {
-                tracing::info!("✓ Hybrid discovery service initialized successfully");
-                discovery_arc.start().await;
-            });
-        }
-        Err(e) => {
-            tracing::error!("✗ Failed to initialize hybrid discovery service: {}", e);
-            tracing::error!("  This means UDP multicast discovery will NOT work!");
-            tracing::warn!("  Continuing with mDNS only for backward compatibility");
+    // 启用 HTTPS 时在 mDNS TXT 记录中附带证书指纹，供客户端固定证书，证书热加载后更新
+    if let Some(discovery) = &discovery {
+        if let Err(e) = discovery.set_property(TXT_TLS_FINGERPRINT, fingerprint_rx.borrow().clone()) {
+            tracing::warn!("{}", e);
         }
+        discovery.clone().start().await;
+        tracing::info!("✓ Hybrid discovery service initialized successfully");
+    }
+    if let Some(mesh) = mesh {
+        tracing::info!("✓ Mesh sync enabled, exchanging entries with discovered peers");
+        tasks.push(mesh.spawn());
     }
-    // ========================================
 
-    // mDNS Service Discovery (保留向后兼容)
-    let mdns = mdns_sd::ServiceDaemon::new().map_err(|e| format!("Failed to create mDNS daemon: {}", e))?;
-    let service_type = "_syncclipboard._tcp.local.";
-    let instance_name = &config.general.device_name; // Use configured device name
-    let ip = "0.0.0.0"; // Allow mdns-sd to detect interfaces
-    let host_name = format!("{}.local.", instance_name);
-    let properties = [("version", "1.0")];
-
-    let service_info = mdns_sd::ServiceInfo::new(
-        service_type,
-        instance_name,
-        host_name.as_str(),
-        ip,
-        config.server.port,
-        &properties[..],
-    ).map_err(|e| format!("Invalid mDNS service info: {}", e))?;
-
-    mdns.register(service_info).map_err(|e| format!("Failed to register mDNS service: {}", e))?;
-    tracing::info!("mDNS registered: '{}' ({}) port {}", instance_name, service_type, config.server.port);
-
-    if let Some(tls_config) = config.server.tls {
+    let served = if let Some(paths) = tls_paths {
         tracing::info!("Starting HTTPS server on {}", addr);
-        let config = axum_server::tls_rustls::RustlsConfig::from_pem_file(
-            tls_config.cert,
-            tls_config.key,
-        ).await?;
+        let rustls_config = axum_server::tls_rustls::RustlsConfig::from_pem_file(&paths.cert, &paths.key).await?;
+        tasks.push(tls::spawn_reloader(rustls_config.clone(), paths, fingerprint_tx));
 
-        axum_server::bind_rustls(addr, config)
+        // Re-announce with the new fingerprint after the certificate is reloaded
+        if let Some(discovery) = discovery.clone() {
+            let mut changes = fingerprint_rx;
+            tasks.push(tokio::spawn(async move {
+                while changes.changed().await.is_ok() {
+                    let fingerprint = changes.borrow_and_update().clone();
+                    if let Err(e) = discovery.set_property(TXT_TLS_FINGERPRINT, fingerprint) {
+                        tracing::warn!("Failed to update mDNS service: {}", e);
+                    }
+                }
+            }));
+        }
+
+        axum_server::from_tcp_rustls(listener, rustls_config)?
             .serve(app.into_make_service_with_connect_info::<std::net::SocketAddr>())
-            .await?;
+            .await
     } else {
         tracing::info!("Starting HTTP server on {}", addr);
-        let listener = tokio::net::TcpListener::bind(&addr).await?;
-        axum::serve(listener, app.into_make_service_with_connect_info::<std::net::SocketAddr>()).await?;
+        let listener = tokio::net::TcpListener::from_std(listener)?;
+        axum::serve(listener, app.into_make_service_with_connect_info::<std::net::SocketAddr>()).await
+    };
+    drop(tasks);
+    if let Some(discovery) = discovery.filter(|_| owns_discovery) {
+        discovery.shutdown().await;
+    }
+    Ok(served?)
+}
+
+/// 绑定 HTTP 监听端口。`::` 同时接受 IPv4 与 IPv6 连接，系统不支持 IPv6 时退回 `0.0.0.0`
+pub fn bind_listener(host: &str, port: u16) -> std::io::Result<std::net::TcpListener> {
+    let ip: std::net::IpAddr = host.parse().map_err(|e| {
+        std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("invalid server.host '{}': {}", host, e))
+    })?;
+    match bind_socket(std::net::SocketAddr::new(ip, port)) {
+        Err(e) if ip == std::net::Ipv6Addr::UNSPECIFIED && e.kind() != std::io::ErrorKind::AddrInUse => {
+            tracing::warn!("Cannot listen on [::]:{} ({}), falling back to IPv4 only", port, e);
+            bind_socket(std::net::SocketAddr::from(([0, 0, 0, 0], port)))
+        }
+        result => result,
+    }
+}
+
+fn bind_socket(addr: std::net::SocketAddr) -> std::io::Result<std::net::TcpListener> {
+    use socket2::{Domain, Socket, Type};
+
+    let socket = Socket::new(Domain::for_address(addr), Type::STREAM, None)?;
+    if addr.is_ipv6() && addr.ip().is_unspecified() {
+        // Dual-stack: IPv4 clients arrive as IPv4-mapped addresses
+        socket.set_only_v6(false)?;
     }
-    Ok(())
+    #[cfg(not(target_os = "windows"))]
+    socket.set_reuse_address(true)?;
+    socket.bind(&addr.into())?;
+    socket.listen(1024)?;
+    socket.set_nonblocking(true)?;
+    Ok(socket.into())
 }
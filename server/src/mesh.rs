@@ -0,0 +1,160 @@
+use axum::http::{HeaderMap, StatusCode};
+use clipboard_core::clipboard::ClipboardData;
+use clipboard_core::config::Config;
+use clipboard_core::discovery::{Device, DiscoveryEvent, DiscoveryService};
+use clipboard_core::mesh::{LamportClock, MeshClient, PushOutcome, Stamp, HEADER_CLOCK, HEADER_ORIGIN};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{Mutex, MutexGuard};
+use tokio::task::JoinHandle;
+use crate::db::{HistoryStore, StoreError};
+
+/// 点对点同步：本机写入的条目推送给发现的节点，收到的条目按 Lamport 时间戳取舍
+pub struct Mesh {
+    client: MeshClient,
+    discovery: Arc<DiscoveryService>,
+    order: Mutex<Order>,
+}
+
+/// 本机条目的排序状态。写入数据库期间保持锁定，条目按时间戳顺序写入
+pub struct Order {
+    origin: String,
+    clock: LamportClock,
+    /// 当前最新条目的时间戳，启动时从数据库恢复；没有 mesh 记录时为空，接受任何节点的条目
+    latest: Option<Stamp>,
+    /// 当前最新条目，新节点加入时推送；敏感与限时条目不保留
+    entry: Option<ClipboardData>,
+}
+
+impl Order {
+    /// 本机客户端写入，分配新的时间戳
+    pub fn tick(&mut self) -> Stamp {
+        Stamp {
+            clock: self.clock.tick(),
+            origin: self.origin.clone(),
+        }
+    }
+
+    /// 收到其他节点的条目，比当前条目新时返回 true；时钟超前本机时间过多时返回 400
+    pub fn accept(&mut self, stamp: &Stamp) -> Result<bool, StatusCode> {
+        if !self.clock.observe(stamp.clock) {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+        Ok(self.latest.as_ref().is_none_or(|latest| stamp > latest))
+    }
+
+    /// 条目已写入，成为当前最新条目
+    pub fn commit(&mut self, stamp: Stamp, data: &ClipboardData) {
+        // Sensitive and expiring entries are not replayed to peers joining later
+        self.entry = (!data.is_sensitive() && data.ttl().is_none()).then(|| data.clone());
+        self.latest = Some(stamp);
+    }
+}
+
+impl Mesh {
+    pub fn new(config: &Config, discovery: Arc<DiscoveryService>) -> Self {
+        Self {
+            client: MeshClient::new(config),
+            discovery,
+            order: Mutex::new(Order {
+                origin: config.general.device_id.clone(),
+                clock: LamportClock::new(),
+                latest: None,
+                entry: None,
+            }),
+        }
+    }
+
+    /// 从数据库中时间戳最大的记录恢复排序状态，重启后不会接受比已存条目旧的条目
+    pub async fn restore(&self, db: &dyn HistoryStore) -> Result<(), StoreError> {
+        let Some((stamp, entry)) = db.latest_stamped().await? else {
+            return Ok(());
+        };
+        tracing::info!("Restored the latest mesh entry {}", stamp);
+        let mut order = self.order.lock().await;
+        order.clock = LamportClock::resume(stamp.clock);
+        order.latest = Some(stamp);
+        order.entry = entry;
+        Ok(())
+    }
+
+    /// 锁定排序状态，写入条目前调用
+    pub async fn order(&self) -> MutexGuard<'_, Order> {
+        self.order.lock().await
+    }
+
+    /// 把本机条目推送给当前发现的所有节点。收到的条目已由来源设备推送，不再转发
+    pub fn broadcast(self: &Arc<Self>, data: ClipboardData, stamp: Stamp) {
+        let mesh = self.clone();
+        tokio::spawn(async move {
+            let peers: Vec<Device> = mesh.discovery
+                .get_devices()
+                .await
+                .into_iter()
+                .filter(|device| mesh.client.is_peer(device))
+                .collect();
+            futures_util::future::join_all(peers.iter().map(|peer| mesh.push(peer, &data, &stamp))).await;
+        });
+    }
+
+    async fn push(&self, peer: &Device, data: &ClipboardData, stamp: &Stamp) {
+        let blob = blob_path(data);
+        match self.client.push(peer, data, stamp, blob.as_deref()).await {
+            Ok(PushOutcome::Accepted) => tracing::debug!("Pushed entry {} to mesh peer '{}'", stamp, peer.name),
+            Ok(PushOutcome::Stale) => tracing::debug!("Mesh peer '{}' already has an entry newer than {}", peer.name, stamp),
+            Err(e) => tracing::warn!("Failed to push entry {} to mesh peer '{}' ({}): {}", stamp, peer.name, peer.ip, e),
+        }
+    }
+
+    /// 节点加入、重启或地址变化时推送本机最新条目，使其赶上其他节点
+    pub fn spawn(self: Arc<Self>) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut events = self.discovery.subscribe();
+            loop {
+                let peer = match events.recv().await {
+                    Ok(DiscoveryEvent::DeviceExpired(_)) => continue,
+                    Ok(event) => event.device().clone(),
+                    // Peers that were missed catch up with the next entry
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => return,
+                };
+                if !self.client.is_peer(&peer) {
+                    continue;
+                }
+                let latest = {
+                    let order = self.order.lock().await;
+                    order.latest.clone().zip(order.entry.clone())
+                };
+                if let Some((stamp, data)) = latest {
+                    tracing::info!("Mesh peer '{}' joined, sending the latest entry {}", peer.name, stamp);
+                    let mesh = self.clone();
+                    tokio::spawn(async move { mesh.push(&peer, &data, &stamp).await });
+                }
+            }
+        })
+    }
+}
+
+/// 请求中其他节点附带的时间戳，本机客户端的请求没有时间戳；只有一半或格式错误时返回 400
+pub fn received_stamp(headers: &HeaderMap) -> Result<Option<Stamp>, StatusCode> {
+    let header = |name: &str| headers.get(name).map(|value| value.to_str().map_err(|_| StatusCode::BAD_REQUEST));
+    match (header(HEADER_CLOCK), header(HEADER_ORIGIN)) {
+        (None, None) => Ok(None),
+        (Some(clock), Some(origin)) => Ok(Some(Stamp {
+            clock: clock?.parse().map_err(|_| StatusCode::BAD_REQUEST)?,
+            origin: origin?.to_string(),
+        })),
+        _ => Err(StatusCode::BAD_REQUEST),
+    }
+}
+
+/// 图片与文件条目在本机的文件
+fn blob_path(data: &ClipboardData) -> Option<PathBuf> {
+    match data {
+        ClipboardData::Image { filename, .. } | ClipboardData::File { filename, .. } => {
+            Some(PathBuf::from(crate::file_handlers::UPLOAD_DIR).join(filename))
+        }
+        ClipboardData::Text { .. } => None,
+    }
+}
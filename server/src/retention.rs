@@ -0,0 +1,141 @@
+//! 历史保留策略的后台执行
+//!
+//! 按类型删除过期记录，再按总大小上限从最旧的记录开始清理，
+//! 最后删除不再被任何记录引用的上传文件。
+
+use crate::archive::is_safe_blob_name;
+use crate::db::{self, HistoryRow, HistoryStore};
+use clipboard_core::config::RetentionConfig;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+/// 两次清理之间的最短间隔，防止配置错误导致空转
+const MIN_INTERVAL_SECS: u64 = 60;
+
+/// 一次清理的结果
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PruneStats {
+    /// 删除的记录数
+    pub rows: usize,
+    /// 删除的上传文件数
+    pub blobs: usize,
+}
+
+/// 启动后台清理任务；未配置任何规则时不启动
+pub fn spawn(db: Arc<dyn HistoryStore>, config: RetentionConfig, upload_dir: PathBuf) -> Option<JoinHandle<()>> {
+    if !config.is_enabled() {
+        return None;
+    }
+    Some(tokio::spawn(async move {
+        let period = Duration::from_secs(config.interval_secs.max(MIN_INTERVAL_SECS));
+        let mut interval = tokio::time::interval(period);
+        loop {
+            interval.tick().await;
+            match prune(db.as_ref(), &config, &upload_dir).await {
+                Ok(stats) if stats.rows > 0 => {
+                    tracing::info!("Retention pruned {} history items and {} files", stats.rows, stats.blobs);
+                }
+                Ok(_) => {}
+                Err(e) => tracing::warn!("Retention task failed: {}", e),
+            }
+        }
+    }))
+}
+
+fn text_size(row: &HistoryRow) -> u64 {
+    (row.content.as_ref().map_or(0, String::len) + row.html.as_ref().map_or(0, String::len)) as u64
+}
+
+/// 执行一次清理
+pub async fn prune(db: &dyn HistoryStore, config: &RetentionConfig, upload_dir: &Path) -> db::Result<PruneStats> {
+    let mut removed = Vec::new();
+    for (r#type, days) in [("Text", config.text_days), ("Image", config.image_days), ("File", config.file_days)] {
+        if days > 0 {
+            removed.extend(db.delete_expired(r#type, days, config.keep_pinned).await?);
+        }
+    }
+
+    // Oldest first
+    let mut remaining = db.export_history().await?;
+
+    if config.max_total_mb > 0 {
+        let cap = config.max_total_mb * 1024 * 1024;
+
+        // Rows sharing an upload (same hash) count its size once
+        let mut refs: HashMap<String, usize> = HashMap::new();
+        for file in remaining.iter().filter_map(|row| row.file.as_ref()) {
+            *refs.entry(file.clone()).or_default() += 1;
+        }
+        let mut blob_sizes = HashMap::new();
+        for name in refs.keys().filter(|name| is_safe_blob_name(name)) {
+            if let Ok(meta) = tokio::fs::metadata(upload_dir.join(name)).await {
+                blob_sizes.insert(name.clone(), meta.len());
+            }
+        }
+
+        let mut total: u64 = remaining.iter().map(text_size).sum::<u64>() + blob_sizes.values().sum::<u64>();
+        let mut kept = Vec::with_capacity(remaining.len());
+        for row in remaining {
+            if total <= cap || (config.keep_pinned && row.pinned) {
+                kept.push(row);
+                continue;
+            }
+            db.delete_history(row.id).await?;
+            total = total.saturating_sub(text_size(&row));
+            if let Some(file) = &row.file {
+                let count = refs.entry(file.clone()).or_default();
+                *count = count.saturating_sub(1);
+                if *count == 0 {
+                    total = total.saturating_sub(blob_sizes.get(file).copied().unwrap_or(0));
+                }
+            }
+            removed.push(row);
+        }
+        remaining = kept;
+    }
+
+    let blobs = remove_orphaned(&removed, &remaining, upload_dir).await;
+    Ok(PruneStats { rows: removed.len(), blobs })
+}
+
+/// 写入新条目时超出 `max_count` 被删除的记录：删除它们不再被引用的上传文件
+pub async fn remove_trimmed(db: &dyn HistoryStore, trimmed: &[HistoryRow], upload_dir: &Path) {
+    match db.export_history().await {
+        Ok(remaining) => {
+            let blobs = remove_orphaned(trimmed, &remaining, upload_dir).await;
+            tracing::debug!("Trimmed {} history items and {} files", trimmed.len(), blobs);
+        }
+        Err(e) => tracing::warn!("Failed to clean up trimmed uploads: {}", e),
+    }
+}
+
+/// 删除 `removed` 引用、但 `remaining` 不再引用的上传文件，返回删除的文件数
+pub(crate) async fn remove_orphaned(removed: &[HistoryRow], remaining: &[HistoryRow], upload_dir: &Path) -> usize {
+    remove_unreferenced(removed.iter().filter_map(|row| row.file.as_deref()), remaining, upload_dir).await
+}
+
+/// 删除 `names` 中 `remaining` 不再引用的上传文件，返回删除的文件数
+pub(crate) async fn remove_unreferenced<'a>(
+    names: impl IntoIterator<Item = &'a str>,
+    remaining: &[HistoryRow],
+    upload_dir: &Path,
+) -> usize {
+    let referenced: HashSet<&str> = remaining.iter().filter_map(|row| row.file.as_deref()).collect();
+    let orphaned: BTreeSet<&str> = names
+        .into_iter()
+        .filter(|name| is_safe_blob_name(name) && !referenced.contains(name))
+        .collect();
+
+    let mut blobs = 0;
+    for name in orphaned {
+        match tokio::fs::remove_file(upload_dir.join(name)).await {
+            Ok(()) => blobs += 1,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => tracing::warn!("Failed to remove pruned upload {}: {}", name, e),
+        }
+    }
+    blobs
+}
@@ -0,0 +1,169 @@
+//! 敏感条目（如密码）的内存存储
+//!
+//! 敏感条目的内容从不写入数据库：数据库中只有不含内容的占位行（保证长轮询的 ID 单调递增），
+//! 内容保存在这里，在存活时间到期或所有同步客户端都取走后销毁。服务端重启后内容随之丢失。
+//! 同一个后台任务也负责清除带 TTL 的普通条目。
+
+use crate::client_tracker::ClientTracker;
+use crate::db::HistoryStore;
+use clipboard_core::clipboard::ClipboardData;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+
+/// 敏感条目未指定 TTL 时的存活时间，避免内容因设备离线而长期驻留内存
+pub const DEFAULT_SENSITIVE_TTL_SECS: u64 = 300;
+
+/// 后台清理间隔
+const SWEEP_INTERVAL_SECS: u64 = 5;
+
+struct Secret {
+    data: ClipboardData,
+    /// 上传者的客户端标识，上传者自己取回不算作送达
+    owner: String,
+    fetched: HashSet<String>,
+    expires_at: Instant,
+}
+
+/// 敏感条目内容，按历史记录 ID 索引
+#[derive(Default)]
+pub struct SecretStore {
+    entries: Mutex<HashMap<i64, Secret>>,
+}
+
+impl SecretStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 加锁时忽略中毒状态：每次修改都是单个 map 操作，panic 后内容仍然一致
+    fn entries(&self) -> MutexGuard<'_, HashMap<i64, Secret>> {
+        self.entries.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// 保存敏感条目内容，`id` 为数据库中占位行的 ID
+    pub fn insert(&self, id: i64, data: ClipboardData, owner: String) {
+        let ttl = data.ttl().unwrap_or(DEFAULT_SENSITIVE_TTL_SECS);
+        let secret = Secret {
+            data,
+            owner,
+            fetched: HashSet::new(),
+            expires_at: Instant::now() + Duration::from_secs(ttl),
+        };
+        self.entries().insert(id, secret);
+    }
+
+    /// 取出敏感条目内容并记录取走的客户端；已过期或已销毁时返回 `None`
+    pub fn fetch(&self, id: i64, client: &str) -> Option<ClipboardData> {
+        let mut entries = self.entries();
+        let secret = entries.get_mut(&id)?;
+        if secret.expires_at <= Instant::now() {
+            entries.remove(&id);
+            return None;
+        }
+        if secret.owner != client {
+            secret.fetched.insert(client.to_string());
+        }
+        Some(secret.data.clone())
+    }
+
+    /// 销毁已送达的条目：至少有一个其他客户端取走，且除上传者外的所有活跃同步客户端都已取走。
+    /// 返回销毁的条目。
+    pub fn burn_delivered(&self, active: &HashSet<String>) -> Vec<ClipboardData> {
+        let mut entries = self.entries();
+        entries
+            .extract_if(|_, secret| {
+                !secret.fetched.is_empty()
+                    && active
+                        .iter()
+                        .all(|client| *client == secret.owner || secret.fetched.contains(client))
+            })
+            .map(|(_, secret)| secret.data)
+            .collect()
+    }
+
+    /// 销毁已过期的条目，返回销毁的条目
+    pub fn remove_expired(&self) -> Vec<ClipboardData> {
+        let now = Instant::now();
+        let mut entries = self.entries();
+        entries
+            .extract_if(|_, secret| secret.expires_at <= now)
+            .map(|(_, secret)| secret.data)
+            .collect()
+    }
+
+    /// 仍保存的条目引用的上传文件
+    fn files(&self) -> HashSet<String> {
+        let entries = self.entries();
+        entries.values().filter_map(|secret| blob_name(&secret.data)).map(str::to_string).collect()
+    }
+}
+
+/// 图片与文件条目在上传目录中的文件名
+fn blob_name(data: &ClipboardData) -> Option<&str> {
+    match data {
+        ClipboardData::Image { filename, .. } | ClipboardData::File { filename, .. } => Some(filename),
+        ClipboardData::Text { .. } => None,
+    }
+}
+
+/// 删除已销毁条目上传的图片与文件，其他敏感条目或历史记录仍引用的文件保留
+pub async fn remove_blobs(destroyed: &[ClipboardData], secrets: &SecretStore, db: &dyn HistoryStore, upload_dir: &Path) {
+    let live = secrets.files();
+    let names: Vec<&str> = destroyed
+        .iter()
+        .filter_map(blob_name)
+        .filter(|name| !live.contains(*name))
+        .collect();
+    if names.is_empty() {
+        return;
+    }
+    match db.export_history().await {
+        Ok(remaining) => {
+            let blobs = crate::retention::remove_unreferenced(names, &remaining, upload_dir).await;
+            tracing::debug!("Removed {} files of destroyed sensitive entries", blobs);
+        }
+        Err(e) => tracing::warn!("Failed to clean up sensitive uploads: {}", e),
+    }
+}
+
+/// 启动后台任务：销毁过期或已送达的敏感条目及其上传文件，清除带 TTL 普通条目的内容及其上传文件
+pub fn spawn_sweeper(
+    db: Arc<dyn HistoryStore>,
+    secrets: Arc<SecretStore>,
+    tracker: Arc<ClientTracker>,
+    upload_dir: PathBuf,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(SWEEP_INTERVAL_SECS));
+        loop {
+            interval.tick().await;
+            let mut burned = secrets.remove_expired();
+            burned.extend(secrets.burn_delivered(&tracker.active_sync_clients().await));
+            if !burned.is_empty() {
+                tracing::debug!("Destroyed {} sensitive clipboard entries", burned.len());
+                remove_blobs(&burned, &secrets, db.as_ref(), &upload_dir).await;
+            }
+
+            let expired = match db.expire_entries().await {
+                Ok(expired) => expired,
+                Err(e) => {
+                    tracing::warn!("Failed to expire clipboard entries: {}", e);
+                    continue;
+                }
+            };
+            if expired.is_empty() {
+                continue;
+            }
+            match db.export_history().await {
+                Ok(remaining) => {
+                    let blobs = crate::retention::remove_orphaned(&expired, &remaining, &upload_dir).await;
+                    tracing::info!("Expired {} clipboard entries and {} files", expired.len(), blobs);
+                }
+                Err(e) => tracing::warn!("Failed to clean up expired uploads: {}", e),
+            }
+        }
+    })
+}
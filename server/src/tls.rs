@@ -0,0 +1,125 @@
+//! 服务端 TLS 证书
+//!
+//! 按需生成自签名证书，并在后台轮询证书文件，变化时热加载：
+//! 新连接使用新证书，已建立的连接不受影响。
+
+use axum_server::tls_rustls::RustlsConfig;
+use clipboard_core::config::TlsConfig;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+/// 省略证书路径时自签名证书的保存目录
+pub const DEFAULT_DIR: &str = "./tls";
+
+/// 检查证书文件是否变化的间隔
+const RELOAD_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Debug, thiserror::Error)]
+pub enum CertError {
+    #[error("server.tls needs both cert and key paths unless self_signed is enabled")]
+    MissingPaths,
+
+    #[error("failed to generate self-signed certificate: {0}")]
+    Generate(#[from] rcgen::Error),
+
+    #[error("failed to access '{path}': {source}")]
+    Io { path: PathBuf, source: std::io::Error },
+
+    #[error("no certificate found in '{0}'")]
+    NoCertificate(PathBuf),
+}
+
+#[derive(Debug, Clone)]
+pub struct CertPaths {
+    pub cert: PathBuf,
+    pub key: PathBuf,
+}
+
+/// 解析证书路径。启用 `self_signed` 且证书与私钥都不存在时生成自签名证书
+pub fn prepare(config: &TlsConfig, device_name: &str) -> Result<CertPaths, CertError> {
+    let path = |value: &str, default: &str| match value.trim() {
+        "" if config.self_signed => Ok(Path::new(DEFAULT_DIR).join(default)),
+        "" => Err(CertError::MissingPaths),
+        value => Ok(PathBuf::from(value)),
+    };
+    let paths = CertPaths {
+        cert: path(&config.cert, "cert.pem")?,
+        key: path(&config.key, "key.pem")?,
+    };
+    if config.self_signed && !paths.cert.exists() && !paths.key.exists() {
+        generate_self_signed(&paths, device_name)?;
+        tracing::info!("Generated self-signed certificate at {}", paths.cert.display());
+    }
+    Ok(paths)
+}
+
+/// 生成自签名证书，主机名为本机名与 localhost，通用名为设备名
+pub fn generate_self_signed(paths: &CertPaths, device_name: &str) -> Result<(), CertError> {
+    let mut names = vec!["localhost".to_string(), "127.0.0.1".to_string(), "::1".to_string()];
+    if let Some(host) = hostname::get().ok().and_then(|h| h.into_string().ok()).filter(|h| h.is_ascii() && !h.contains(' ')) {
+        names.push(host);
+    }
+    let mut params = rcgen::CertificateParams::new(names)?;
+    params.distinguished_name.push(rcgen::DnType::CommonName, device_name);
+    let key = rcgen::KeyPair::generate()?;
+    let cert = params.self_signed(&key)?;
+
+    write_file(&paths.cert, cert.pem().as_bytes(), false)?;
+    write_file(&paths.key, key.serialize_pem().as_bytes(), true)
+}
+
+fn write_file(path: &Path, contents: &[u8], private: bool) -> Result<(), CertError> {
+    let io_err = |source| CertError::Io { path: path.to_path_buf(), source };
+    if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir).map_err(io_err)?;
+    }
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    if private {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    #[cfg(not(unix))]
+    let _ = private;
+    std::io::Write::write_all(&mut options.open(path).map_err(io_err)?, contents).map_err(io_err)
+}
+
+/// 读取证书文件中第一张证书的 SHA-256 指纹
+pub fn read_fingerprint(cert: &Path) -> Result<String, CertError> {
+    let pem = std::fs::read(cert).map_err(|source| CertError::Io { path: cert.to_path_buf(), source })?;
+    clipboard_core::tls::fingerprint_pem(&pem).ok_or_else(|| CertError::NoCertificate(cert.to_path_buf()))
+}
+
+fn modified(paths: &CertPaths) -> Option<(SystemTime, SystemTime)> {
+    let mtime = |path: &Path| std::fs::metadata(path).and_then(|m| m.modified()).ok();
+    Some((mtime(&paths.cert)?, mtime(&paths.key)?))
+}
+
+/// 启动热加载任务：证书或私钥的修改时间变化后重新加载，并更新指纹。
+/// 加载失败（如只写入了其中一个文件）时保留旧证书，待文件再次变化后重试。
+pub fn spawn_reloader(rustls: RustlsConfig, paths: CertPaths, fingerprint: watch::Sender<Option<String>>) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut loaded = modified(&paths);
+        let mut interval = tokio::time::interval(RELOAD_INTERVAL);
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            let current = modified(&paths);
+            if current.is_none() || current == loaded {
+                continue;
+            }
+            loaded = current;
+            match rustls.reload_from_pem_file(&paths.cert, &paths.key).await {
+                Ok(()) => {
+                    let new = read_fingerprint(&paths.cert).ok();
+                    tracing::info!("Reloaded TLS certificate {} ({})", paths.cert.display(), new.as_deref().unwrap_or("unknown"));
+                    fingerprint.send_replace(new);
+                }
+                Err(e) => tracing::warn!("Failed to reload TLS certificate, keeping the current one: {}", e),
+            }
+        }
+    })
+}
@@ -2,7 +2,9 @@ use axum::{
     extract::{State, ConnectInfo},
     http::Method,
 };
+use crate::client_tracker::ClientKey;
 use crate::handlers::AppState;
+use clipboard_core::remote::HEADER_DEVICE_ID;
 use std::net::SocketAddr;
 
 /// 客户端追踪中间件
@@ -10,7 +12,7 @@ use std::net::SocketAddr;
 pub async fn client_tracking_middleware(
     State(state): State<AppState>,
     ConnectInfo(addr): ConnectInfo<SocketAddr>,
-    req: axum::http::Request<axum::body::Body>,
+    mut req: axum::http::Request<axum::body::Body>,
     next: axum::middleware::Next,
 ) -> axum::response::Response {
     // 忽略 OPTIONS 请求 (CORS 预检请求通常不带自定义Header，会覆盖掉有效的设备名)
@@ -20,12 +22,13 @@ pub async fn client_tracking_middleware(
     // 提取客户端IP
     // 提取客户端IP
     // 优先使用 X-Forwarded-For (反向代理)，否则使用真实连接IP
+    // 双栈监听时 IPv4 客户端表现为 ::ffff:a.b.c.d，还原为 IPv4
     let client_ip = req
         .headers()
         .get("x-forwarded-for")
         .and_then(|v| v.to_str().ok())
         .and_then(|s| s.split(',').next().map(|s| s.trim().to_string()))
-        .unwrap_or_else(|| addr.ip().to_string());
+        .unwrap_or_else(|| addr.ip().to_canonical().to_string());
 
     // 提取设备名（从自定义头）
     let device_name = req
@@ -41,10 +44,27 @@ pub async fn client_tracking_middleware(
         .and_then(|v| v.to_str().ok())
         .map(|s| s.to_string());
 
+    // 提取同步方向（同步客户端声明）
+    let sync_direction = req
+        .headers()
+        .get("x-sync-direction")
+        .and_then(|v| v.to_str().ok())
+        .map(|s| s.to_string());
+
+    // 客户端标识：同步客户端发送的设备 ID，否则为真实连接 IP（X-Forwarded-For 可伪造，不参与标识）
+    let key = req
+        .headers()
+        .get(HEADER_DEVICE_ID)
+        .and_then(|v| v.to_str().ok())
+        .filter(|s| !s.is_empty())
+        .map(|s| s.to_string())
+        .unwrap_or_else(|| addr.ip().to_canonical().to_string());
+    req.extensions_mut().insert(ClientKey(key));
+
     // 记录客户端
     state
         .tracker
-        .record_client(client_ip, device_name, user_agent)
+        .record_client(client_ip, device_name, user_agent, sync_direction)
         .await;
 
     // 继续处理请求
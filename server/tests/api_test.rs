@@ -1,39 +1,13 @@
-use clipboard_core::config::{Config, ServerConfig, AuthConfig, ClientConfig, GeneralConfig};
+mod common;
+
 use clipboard_core::clipboard::ClipboardData;
 use std::time::Duration;
 use tokio::time::sleep;
 
 // Helper to start server in background
 async fn start_test_server(port: u16, token: Option<String>) {
-    let config = Config {
-        server: ServerConfig {
-            port,
-            host: "127.0.0.1".to_string(),
-            wevdav_enabled: false,
-            tls: None,
-            enabled: true,
-        },
-        client: ClientConfig {
-            enabled: false,
-            remote_host: "127.0.0.1".to_string(),
-            remote_port: port,
-        },
-        auth: AuthConfig {
-            username: None,
-            password: None,
-            token,
-            encrypt_password: None,
-        },
-        history: clipboard_core::config::HistoryConfig {
-            max_count: 100,
-            log_retention_days: 7,
-            db_path: format!("test_api_{}.db", port),
-        },
-        general: GeneralConfig {
-            device_name: "TestAPI".to_string(),
-            device_id: "test-api".to_string(),
-        }
-    };
+    let mut config = common::test_config(port, format!("test_api_{}.db", port));
+    config.auth.token = token;
     
     // Spawn server in background
     tokio::spawn(async move {
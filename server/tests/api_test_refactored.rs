@@ -112,3 +112,23 @@ async fn test_file_upload_and_deduplication() {
     let downloaded = resp.bytes().await.unwrap();
     assert_eq!(downloaded, content.as_slice());
 }
+
+#[tokio::test]
+async fn test_connected_devices_report_sync_direction() {
+    let server = TestServer::new().await;
+    let client = server.client();
+
+    client.get(format!("{}/SyncClipboard.json", server.base_url))
+        .header("X-Device-Name", "kiosk")
+        .header("X-Sync-Direction", "receive")
+        .send().await.unwrap();
+    // Requests without the header (e.g. the web UI) keep the advertised direction
+    let devices: serde_json::Value = client.get(format!("{}/api/connected_devices", server.base_url))
+        .send().await.unwrap()
+        .json().await.unwrap();
+
+    let devices = devices.as_array().unwrap();
+    assert_eq!(devices.len(), 1);
+    assert_eq!(devices[0]["device_name"], "kiosk");
+    assert_eq!(devices[0]["sync_direction"], "receive");
+}
@@ -0,0 +1,163 @@
+mod common;
+use common::TestServer;
+use clipboard_core::clipboard::ClipboardData;
+use serde_json::Value;
+use std::io::Read;
+use std::time::Duration;
+
+async fn history(server: &TestServer) -> Vec<Value> {
+    server.client().get(format!("{}/history", server.base_url))
+        .send().await.unwrap()
+        .json().await.unwrap()
+}
+
+async fn import(server: &TestServer, archive: Vec<u8>) -> reqwest::Response {
+    server.client().post(format!("{}/api/import", server.base_url))
+        .body(archive)
+        .send().await.unwrap()
+}
+
+fn tar_entries(archive: &[u8]) -> Vec<(String, Vec<u8>)> {
+    let mut archive = tar::Archive::new(archive);
+    archive.entries().unwrap().map(|entry| {
+        let mut entry = entry.unwrap();
+        let path = entry.path().unwrap().to_string_lossy().into_owned();
+        let mut data = Vec::new();
+        entry.read_to_end(&mut data).unwrap();
+        (path, data)
+    }).collect()
+}
+
+fn build_tar(entries: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut builder = tar::Builder::new(Vec::new());
+    for (path, data) in entries {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append_data(&mut header, path, *data).unwrap();
+    }
+    builder.into_inner().unwrap()
+}
+
+#[tokio::test]
+async fn test_export_import_round_trip() {
+    let source = TestServer::new().await;
+    let client = source.client();
+    let url = format!("{}/SyncClipboard.json", source.base_url);
+
+    // Unique name: the upload directory is shared by every server in this process
+    let blob_name = format!("archive-test-{}.png", source.port);
+    let blob = b"fake png bytes".to_vec();
+    client.put(format!("{}/file/{}", source.base_url, blob_name)).body(blob.clone()).send().await.unwrap();
+
+    let items = [
+        ClipboardData::Text {
+            content: "rich item".to_string(),
+            file: None,
+            html: Some("<b>rich item</b>".to_string()),
+            device: Some("laptop".to_string()),
+            ttl: None,
+            sensitive: false,
+        },
+        ClipboardData::Image { hash: Some("abc".to_string()), filename: blob_name.clone(), device: Some("phone".to_string()), ttl: None, sensitive: false },
+        ClipboardData::new_text("plain item".to_string()),
+    ];
+    for item in &items {
+        assert!(client.put(&url).json(item).send().await.unwrap().status().is_success());
+    }
+    let original = history(&source).await;
+    let pinned_id = original.iter().find(|i| i["content"] == "rich item").unwrap()["id"].as_i64().unwrap();
+    client.patch(format!("{}/history/{}", source.base_url, pinned_id))
+        .json(&serde_json::json!({ "pinned": true }))
+        .send().await.unwrap();
+    let original = history(&source).await;
+
+    let resp = client.get(format!("{}/api/export", source.base_url)).send().await.unwrap();
+    assert!(resp.status().is_success());
+    assert_eq!(resp.headers()["content-type"], "application/x-tar");
+    let archive = resp.bytes().await.unwrap().to_vec();
+
+    let entries = tar_entries(&archive);
+    let names: Vec<&str> = entries.iter().map(|(name, _)| name.as_str()).collect();
+    assert_eq!(names, ["manifest.json", "history.jsonl", &format!("blobs/{}", blob_name)]);
+    assert_eq!(entries[1].1.split(|b| *b == b'\n').filter(|l| !l.is_empty()).count(), 3);
+    assert_eq!(entries[2].1, blob);
+
+    // Import into a server that already has a newer clipboard item and lacks the blob
+    std::fs::remove_file(format!("./uploads/{}", blob_name)).unwrap();
+    let target = TestServer::new().await;
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    let target_url = format!("{}/SyncClipboard.json", target.base_url);
+    client.put(&target_url).json(&ClipboardData::new_text("current".to_string())).send().await.unwrap();
+
+    let resp = import(&target, archive.clone()).await;
+    assert!(resp.status().is_success());
+    let result: Value = resp.json().await.unwrap();
+    assert_eq!(result, serde_json::json!({ "imported": 3, "skipped": 0, "blobs": 1 }));
+
+    // Imported items are older, so they do not replace the current clipboard
+    let latest: ClipboardData = client.get(&target_url).send().await.unwrap().json().await.unwrap();
+    match latest {
+        ClipboardData::Text { content, .. } => assert_eq!(content, "current"),
+        _ => panic!("Wrong data type"),
+    }
+
+    let imported = history(&target).await;
+    assert_eq!(imported.len(), 4);
+    for item in &original {
+        let copy = imported.iter()
+            .find(|i| i["type"] == item["type"] && i["content"] == item["content"] && i["file"] == item["file"])
+            .unwrap_or_else(|| panic!("missing {}", item));
+        for field in ["html", "hash", "device", "pinned", "timestamp"] {
+            assert_eq!(copy[field], item[field], "{} of {}", field, item);
+        }
+    }
+    let downloaded = client.get(format!("{}/file/{}", target.base_url, blob_name)).send().await.unwrap();
+    assert_eq!(downloaded.bytes().await.unwrap().to_vec(), blob);
+
+    // Importing the same archive again only finds duplicates
+    let result: Value = import(&target, archive).await.json().await.unwrap();
+    assert_eq!(result, serde_json::json!({ "imported": 0, "skipped": 3, "blobs": 0 }));
+    assert_eq!(history(&target).await.len(), 4);
+
+    let _ = std::fs::remove_file(format!("./uploads/{}", blob_name));
+}
+
+#[tokio::test]
+async fn test_import_rejects_invalid_archives() {
+    let server = TestServer::new().await;
+    let manifest = br#"{"format":"syncclipboard-history","version":1,"count":0}"#;
+
+    let resp = import(&server, b"definitely not a tar archive".repeat(64)).await;
+    assert_eq!(resp.status(), 400);
+
+    let resp = import(&server, build_tar(&[("history.jsonl", b"")])).await;
+    assert_eq!(resp.status(), 400, "missing manifest");
+
+    let future = br#"{"format":"syncclipboard-history","version":99,"count":0}"#;
+    let resp = import(&server, build_tar(&[("manifest.json", future)])).await;
+    assert_eq!(resp.status(), 400, "newer format version");
+
+    let resp = import(&server, build_tar(&[("manifest.json", manifest), ("blobs/nested/escape.txt", b"x")])).await;
+    assert_eq!(resp.status(), 400, "nested blob path");
+    assert!(!std::path::Path::new("./uploads/nested").exists());
+
+    let resp = import(&server, build_tar(&[("manifest.json", manifest), ("history.jsonl", b"{not json}\n")])).await;
+    assert_eq!(resp.status(), 400, "malformed history line");
+
+    // Files of rejected archives never reach the upload directory
+    let blob_name = format!("rejected-{}.png", server.port);
+    let blob_path = format!("blobs/{}", blob_name);
+    let resp = import(&server, build_tar(&[("manifest.json", manifest), (&blob_path, b"png")])).await;
+    assert_eq!(resp.status(), 400, "missing history");
+    let resp = import(&server, build_tar(&[("manifest.json", manifest), (&blob_path, b"png"), ("history.jsonl", b"{not json}\n")])).await;
+    assert_eq!(resp.status(), 400, "blob before a malformed history line");
+    let mut truncated = build_tar(&[("manifest.json", manifest), ("history.jsonl", b""), (&blob_path, &[7u8; 4096])]);
+    truncated.truncate(truncated.len() - 3072);
+    let resp = import(&server, truncated).await;
+    assert_eq!(resp.status(), 400, "truncated archive");
+    assert!(!std::path::Path::new("./uploads").join(&blob_name).exists());
+
+    assert!(history(&server).await.is_empty());
+}
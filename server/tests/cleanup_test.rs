@@ -1,38 +1,12 @@
-use clipboard_core::config::{Config, ServerConfig, AuthConfig, HistoryConfig, ClientConfig, GeneralConfig};
+mod common;
+
 use clipboard_core::clipboard::ClipboardData;
 use std::time::Duration;
 use tokio::time::sleep;
 
 async fn start_test_server(port: u16, max_count: u32) {
-    let config = Config {
-        server: ServerConfig {
-            port,
-            host: "127.0.0.1".to_string(),
-            wevdav_enabled: false,
-            tls: None,
-            enabled: true,
-        },
-        client: ClientConfig {
-            enabled: false,
-            remote_host: "127.0.0.1".to_string(),
-            remote_port: port,
-        },
-        auth: AuthConfig {
-            username: None,
-            password: None,
-            token: None,
-            encrypt_password: None,
-        },
-        history: HistoryConfig {
-            max_count,
-            log_retention_days: 7,
-            db_path: "test_cleanup.db".to_string(),
-        },
-        general: GeneralConfig {
-            device_name: "TestCleanup".to_string(),
-            device_id: "test-cleanup".to_string(),
-        }
-    };
+    let mut config = common::test_config(port, "test_cleanup.db");
+    config.history.max_count = max_count;
     
     // Spawn server
     tokio::spawn(async move {
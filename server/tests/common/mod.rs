@@ -1,12 +1,63 @@
+// Each test crate uses a different subset of these helpers
+#![allow(dead_code)]
+
 use clipboard_core::config::*;
 use tempfile::TempDir;
 use std::net::TcpListener;
 
+/// 测试用配置：监听本机回环地址的服务端、不启用客户端，其余选项为默认值。
+/// 测试按需修改返回的配置
+pub fn test_config(port: u16, db_path: impl Into<String>) -> Config {
+    Config {
+        server: ServerConfig {
+            port,
+            host: "127.0.0.1".to_string(),
+            wevdav_enabled: false,
+            tls: None,
+            enabled: true,
+        },
+        client: ClientConfig {
+            enabled: false,
+            remote_host: "127.0.0.1".to_string(),
+            remote_port: port,
+            concealed: Default::default(),
+            mode: Default::default(),
+            push_mode: Default::default(),
+            remotes: Default::default(),
+            scheme: "http".to_string(),
+            tls: Default::default(),
+            server_id: None,
+        },
+        auth: AuthConfig {
+            username: None,
+            password: None,
+            token: None,
+            encrypt_password: None,
+        },
+        history: HistoryConfig {
+            max_count: 100,
+            log_retention_days: 7,
+            db_path: db_path.into(),
+            database_url: None,
+            retention: Default::default(),
+        },
+        general: GeneralConfig {
+            device_name: "TestDevice".to_string(),
+            device_id: format!("test-{}", port),
+        },
+        filters: Default::default(),
+        hotkeys: Default::default(),
+        discovery: Default::default(),
+        mesh: Default::default(),
+    }
+}
+
 /// 测试服务器辅助结构
 /// 自动管理端口分配、DB隔离和资源清理
 pub struct TestServer {
     pub port: u16,
     pub base_url: String,
+    pub db_path: String,
     _temp_dir: TempDir,
 }
 
@@ -26,6 +77,11 @@ impl TestServer {
         Self::with_config(None, None, true).await
     }
     
+    /// 创建只保留 `max_count` 条历史记录的测试服务器
+    pub async fn with_max_count(max_count: u32) -> Self {
+        Self::with_config(None, Some(max_count), false).await
+    }
+
     /// 创建自定义配置的测试服务器
     async fn with_config(token: Option<String>, max_count: Option<u32>, webdav_enabled: bool) -> Self {
         let port = Self::find_available_port();
@@ -40,38 +96,13 @@ impl TestServer {
             .with_env_filter("server=debug,tower_http=debug")
             .try_init();
 
-        let config = Config {
-            server: ServerConfig {
-                port,
-                host: "127.0.0.1".to_string(),
-                wevdav_enabled: webdav_enabled,
-                tls: None,
-                enabled: true,
-            },
-            client: ClientConfig {
-                enabled: false,
-                remote_host: "127.0.0.1".to_string(),
-                remote_port: port,
-            },
-            auth: AuthConfig {
-                username: None,
-                password: None,
-                token,
-                encrypt_password: None,
-            },
-            history: HistoryConfig {
-                max_count: max_count.unwrap_or(100),
-                log_retention_days: 7,
-                db_path,
-            },
-            general: GeneralConfig {
-                device_name: "TestDevice".to_string(),
-                device_id: format!("test-{}", port),
-            },
-        };
-        
+        let mut config = test_config(port, db_path.clone());
+        config.server.wevdav_enabled = webdav_enabled;
+        config.auth.token = token;
+        if let Some(max_count) = max_count {
+            config.history.max_count = max_count;
+        }
 
-        
         // 启动服务器
         tokio::spawn(async move {
             if let Err(e) = server::run(config).await {
@@ -85,6 +116,7 @@ impl TestServer {
         Self {
             port,
             base_url: format!("http://127.0.0.1:{}", port),
+            db_path,
             _temp_dir: temp_dir,
         }
     }
@@ -0,0 +1,109 @@
+// 负载测试：大量长轮询客户端在写入风暴中被反复唤醒、且另一进程（如桌面端历史页面）
+// 同时写入同一数据库文件时，历史查询仍能及时成功返回
+
+mod common;
+use common::TestServer;
+use clipboard_core::clipboard::ClipboardData;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+const POLLERS: usize = 64;
+const WRITES: usize = 40;
+const HISTORY_READS: usize = 40;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_long_poll_clients_do_not_stall_history() {
+    let server = TestServer::new().await;
+    let client = server.client();
+    let url = format!("{}/SyncClipboard.json", server.base_url);
+    let history_url = format!("{}/history?limit=50", server.base_url);
+
+    for i in 0..50 {
+        let data = ClipboardData::new_text(format!("seed-{}", i));
+        client.put(&url).json(&data).send().await.unwrap();
+    }
+
+    let stop = Arc::new(AtomicBool::new(false));
+
+    // Long-poll clients that immediately re-poll after every wakeup
+    let mut pollers = Vec::new();
+    for _ in 0..POLLERS {
+        let client = client.clone();
+        let url = url.clone();
+        let stop = stop.clone();
+        pollers.push(tokio::spawn(async move {
+            let mut last_id = -1i64;
+            while !stop.load(Ordering::Relaxed) {
+                let Ok(resp) = client.get(format!("{}?wait=5&last_id={}", url, last_id)).send().await else {
+                    continue;
+                };
+                if let Some(id) = resp.headers().get("X-Clipboard-Id").and_then(|v| v.to_str().ok()) {
+                    last_id = id.parse().unwrap_or(last_id);
+                }
+                let _ = resp.bytes().await;
+            }
+        }));
+    }
+
+    // Another process editing the same database file, holding the write lock for a while
+    let external = {
+        let db_path = server.db_path.clone();
+        let stop = stop.clone();
+        tokio::task::spawn_blocking(move || {
+            let conn = rusqlite::Connection::open(&db_path).unwrap();
+            conn.busy_timeout(Duration::from_secs(5)).unwrap();
+            while !stop.load(Ordering::Relaxed) {
+                conn.execute_batch("BEGIN EXCLUSIVE; UPDATE history SET pinned = pinned WHERE id = 1;").unwrap();
+                std::thread::sleep(Duration::from_millis(200));
+                conn.execute_batch("COMMIT;").unwrap();
+                std::thread::sleep(Duration::from_millis(50));
+            }
+        })
+    };
+
+    // Writer: every save wakes all pollers at once
+    let writer = {
+        let client = client.clone();
+        let url = url.clone();
+        tokio::spawn(async move {
+            for i in 0..WRITES {
+                let data = ClipboardData::new_text(format!("storm-{}", i));
+                let resp = client.put(&url).json(&data).send().await.unwrap();
+                assert!(resp.status().is_success(), "write failed: {}", resp.status());
+                tokio::time::sleep(Duration::from_millis(25)).await;
+            }
+        })
+    };
+
+    let mut worst = Duration::ZERO;
+    let mut total = Duration::ZERO;
+    for _ in 0..HISTORY_READS {
+        let started = Instant::now();
+        let resp = client.get(&history_url).send().await.unwrap();
+        assert!(resp.status().is_success(), "history read failed: {}", resp.status());
+        let items: Vec<serde_json::Value> = resp.json().await.unwrap();
+        assert!(!items.is_empty());
+        let elapsed = started.elapsed();
+        worst = worst.max(elapsed);
+        total += elapsed;
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+
+    writer.await.unwrap();
+    stop.store(true, Ordering::Relaxed);
+    external.await.unwrap();
+    // Final write releases pollers still waiting
+    client.put(&url).json(&ClipboardData::new_text("done".to_string())).send().await.unwrap();
+    for poller in pollers {
+        let _ = tokio::time::timeout(Duration::from_secs(10), poller).await;
+    }
+
+    println!(
+        "history reads under {} long-poll clients: avg {:?}, worst {:?}",
+        POLLERS,
+        total / HISTORY_READS as u32,
+        worst
+    );
+    assert!(worst < Duration::from_millis(500), "history read stalled for {:?}", worst);
+}
@@ -0,0 +1,76 @@
+// 单独的测试程序：文件描述符按进程统计，避免与其他测试相互影响
+use clipboard_core::discovery::{Device, DiscoveryService};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+
+fn device(id: &str, port: u16) -> Device {
+    Device {
+        id: id.to_string(),
+        name: id.to_string(),
+        ip: "0.0.0.0".to_string(),
+        port,
+        instance_id: 1,
+        capabilities: vec!["clipboard".to_string()],
+    }
+}
+
+/// 当前进程打开的文件描述符数
+fn open_fds() -> usize {
+    std::fs::read_dir("/proc/self/fd").map(|dir| dir.count()).unwrap_or(0)
+}
+
+/// 启动、扫描并关闭一次发现服务，确认后台任务都已退出
+async fn run_once(id: &str) {
+    let service = Arc::new(DiscoveryService::new(device(id, 5997)).await.unwrap());
+    let mut events = service.subscribe();
+    service.clone().start().await;
+    // Starting again does not spawn a second set of tasks
+    service.clone().start().await;
+    service.scan().await.unwrap();
+    service.shutdown().await;
+    service.shutdown().await;
+    drop(service);
+
+    // Only the background tasks could still hold the cache and its event sender
+    let closed = tokio::time::timeout(Duration::from_secs(2), async {
+        while !matches!(events.recv().await, Err(RecvError::Closed)) {}
+    })
+    .await;
+    assert!(closed.is_ok(), "discovery tasks of '{}' are still running", id);
+}
+
+#[tokio::test]
+async fn test_start_and_shutdown_repeatedly() {
+    // The first run sets up process-wide resources such as the blocking thread pool
+    run_once("lifecycle-0").await;
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    let baseline = open_fds();
+
+    for i in 1..=5 {
+        run_once(&format!("lifecycle-{}", i)).await;
+    }
+
+    // mDNS daemon threads close their sockets after handling the shutdown command
+    let mut fds = open_fds();
+    for _ in 0..20 {
+        if fds <= baseline {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        fds = open_fds();
+    }
+    assert!(fds <= baseline, "leaked {} file descriptor(s)", fds - baseline);
+}
+
+#[tokio::test]
+async fn test_start_after_shutdown_is_ignored() {
+    let service = Arc::new(DiscoveryService::new(device("lifecycle-stopped", 0)).await.unwrap());
+    let mut events = service.subscribe();
+    service.shutdown().await;
+    service.clone().start().await;
+    drop(service);
+
+    let closed = tokio::time::timeout(Duration::from_secs(2), events.recv()).await;
+    assert!(matches!(closed, Ok(Err(RecvError::Closed))));
+}
@@ -0,0 +1,453 @@
+mod common;
+
+use clipboard_core::config::{Config, DiscoveryConfig, TlsConfig, TrustedDevice};
+use clipboard_core::discovery::{
+    network_interfaces, parse_cidr, AnnouncementPacket, AuthRequirements, Device, DeviceIdentity, DiscoveryAuth, DiscoveryError,
+    DiscoveryEvent, DiscoveryInfo, DiscoveryPacket, DiscoveryService, HttpScanner, JoinedInterfaces, NetworkInterface,
+    MULTICAST_ADDR, MULTICAST_PORT, PROTOCOL_VERSION,
+};
+use std::sync::Arc;
+use std::time::Duration;
+
+fn device(id: &str, name: &str, port: u16) -> Device {
+    Device {
+        id: id.to_string(),
+        name: name.to_string(),
+        ip: "0.0.0.0".to_string(),
+        port,
+        instance_id: 42,
+        capabilities: vec!["clipboard".to_string(), "file".to_string()],
+    }
+}
+
+#[tokio::test]
+async fn test_scanner_finds_server_once() {
+    let server = Arc::new(DiscoveryService::new(device("disco-server", "Disco Server", 5990)).await.unwrap());
+    server.set_property("tls_fingerprint", Some("AB:CD".to_string())).unwrap();
+    server.clone().start().await;
+
+    let scanner = Arc::new(DiscoveryService::new(device("disco-scanner", "Disco Scanner", 0)).await.unwrap());
+    scanner.clone().start().await;
+    scanner.scan().await.unwrap();
+
+    let mut found = None;
+    for _ in 0..40 {
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        found = scanner.get_device("disco-server").await;
+        if found.is_some() {
+            break;
+        }
+    }
+    let found = found.expect("server was not discovered");
+    assert_eq!(found.name, "Disco Server");
+    assert_eq!(found.port, 5990);
+    assert_eq!(found.instance_id, 42);
+
+    // Give the other method time to report the same device as well
+    tokio::time::sleep(Duration::from_secs(2)).await;
+    let matches = scanner.get_devices().await.into_iter().filter(|d| d.id == "disco-server").count();
+    assert_eq!(matches, 1);
+}
+
+/// 等待与 `id` 有关的下一个事件，跳过其他测试公告的设备
+async fn next_event(events: &mut tokio::sync::broadcast::Receiver<DiscoveryEvent>, id: &str) -> DiscoveryEvent {
+    loop {
+        let event = tokio::time::timeout(Duration::from_secs(5), events.recv())
+            .await
+            .expect("no discovery event")
+            .unwrap();
+        if event.device().id == id {
+            return event;
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_discovery_events() {
+    let scanner = Arc::new(DiscoveryService::new(device("events-scanner", "Events Scanner", 0)).await.unwrap());
+    let mut events = scanner.subscribe();
+    scanner.clone().start().await;
+
+    let socket = std::net::UdpSocket::bind("0.0.0.0:0").unwrap();
+    let announce = |packet: AnnouncementPacket| async {
+        let json = serde_json::to_vec(&DiscoveryPacket::Announcement(Box::new(packet))).unwrap();
+        socket.send_to(&json, (MULTICAST_ADDR, MULTICAST_PORT)).unwrap();
+        // Each packet is registered by its own task
+        tokio::time::sleep(Duration::from_millis(200)).await;
+    };
+
+    announce(packet("events-device", 6000)).await;
+    match next_event(&mut events, "events-device").await {
+        DiscoveryEvent::DeviceAdded(device) => assert_eq!(device.port, 6000),
+        other => panic!("Unexpected event: {:?}", other),
+    }
+
+    // Repeated announcements only refresh the entry
+    announce(packet("events-device", 6000)).await;
+    announce(AnnouncementPacket { instance_id: 2, ..packet("events-device", 6000) }).await;
+    match next_event(&mut events, "events-device").await {
+        DiscoveryEvent::DeviceRestarted(device) => assert_eq!(device.instance_id, 2),
+        other => panic!("Unexpected event: {:?}", other),
+    }
+
+    announce(AnnouncementPacket { instance_id: 2, ..packet("events-device", 6001) }).await;
+    match next_event(&mut events, "events-device").await {
+        DiscoveryEvent::DeviceUpdated(device) => assert_eq!(device.port, 6001),
+        other => panic!("Unexpected event: {:?}", other),
+    }
+    assert_eq!(scanner.get_device("events-device").await.map(|d| d.port), Some(6001));
+}
+
+#[tokio::test]
+async fn test_refresh_interfaces_keeps_membership() {
+    let scanner = Arc::new(DiscoveryService::new(device("refresh-scanner", "Refresh Scanner", 0)).await.unwrap());
+    let mut events = scanner.subscribe();
+    scanner.clone().start().await;
+
+    // The interfaces joined at startup are unchanged
+    assert!(!scanner.refresh_interfaces().await);
+    assert!(!scanner.refresh_interfaces().await);
+
+    let socket = std::net::UdpSocket::bind("0.0.0.0:0").unwrap();
+    let json = serde_json::to_vec(&DiscoveryPacket::Announcement(Box::new(packet("refresh-device", 6002)))).unwrap();
+    socket.send_to(&json, (MULTICAST_ADDR, MULTICAST_PORT)).unwrap();
+    assert!(matches!(next_event(&mut events, "refresh-device").await, DiscoveryEvent::DeviceAdded(_)));
+    scanner.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_update_interfaces_joins_leaves_and_announces() {
+    // Not started, so only the given interface lists change its membership
+    let server = DiscoveryService::new(device("iface-server", "Iface Server", 6005)).await.unwrap();
+    let current = network_interfaces().unwrap();
+    let current_v4 = |joined: JoinedInterfaces| joined.v4.len() == current.iter().filter(|i| i.ip.is_ipv4()).count();
+    assert!(!server.update_interfaces(&current).await);
+    assert!(current_v4(server.joined_interfaces()));
+
+    // Every interface vanished
+    assert!(server.update_interfaces(&[]).await);
+    assert_eq!(server.joined_interfaces(), JoinedInterfaces::default());
+
+    // They come back: joined again and announced right away
+    let listener = Arc::new(DiscoveryService::new(device("iface-listener", "Iface Listener", 0)).await.unwrap());
+    let mut events = listener.subscribe();
+    listener.clone().start().await;
+    assert!(server.update_interfaces(&current).await);
+    assert!(current_v4(server.joined_interfaces()));
+    assert!(matches!(next_event(&mut events, "iface-server").await, DiscoveryEvent::DeviceAdded(_)));
+
+    // A failed join is not recorded, so the next check tries it again
+    let ghost: std::net::Ipv4Addr = "192.0.2.1".parse().unwrap();
+    let mut with_ghost = current.clone();
+    with_ghost.push(NetworkInterface { name: "ghost0".to_string(), ip: ghost.into(), index: None });
+    assert!(server.update_interfaces(&with_ghost).await);
+    assert!(!server.joined_interfaces().v4.contains(&ghost));
+    assert!(!server.update_interfaces(&with_ghost).await);
+    assert!(current_v4(server.joined_interfaces()));
+    listener.shutdown().await;
+}
+
+fn packet(id: &str, port: u16) -> AnnouncementPacket {
+    AnnouncementPacket {
+        identity: DeviceIdentity {
+            device_id: id.to_string(),
+            alias: id.to_string(),
+            version: "test".to_string(),
+            protocol_version: PROTOCOL_VERSION,
+            capabilities: vec![],
+        },
+        port,
+        instance_id: 1,
+        timestamp: None,
+        nonce: None,
+        public_key: None,
+        hmac: None,
+        signature: None,
+    }
+}
+
+fn signed(auth: &DiscoveryAuth, id: &str, port: u16) -> AnnouncementPacket {
+    let mut packet = packet(id, port);
+    auth.sign(&mut packet);
+    packet
+}
+
+#[test]
+fn test_announcement_authentication() {
+    let dir = tempfile::tempdir().unwrap();
+    let key_file = dir.path().join("discovery_key.pem").to_string_lossy().to_string();
+    let sender_config = DiscoveryConfig {
+        secret: Some("shared-secret".to_string()),
+        key_file: Some(key_file.clone()),
+        ..Default::default()
+    };
+    let sender = DiscoveryAuth::from_config(&sender_config).unwrap();
+    let public_key = sender.public_key().expect("key pair generated");
+    // The key is kept across restarts
+    assert_eq!(DiscoveryAuth::from_config(&sender_config).unwrap().public_key(), Some(public_key.clone()));
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        assert_eq!(std::fs::metadata(&key_file).unwrap().permissions().mode() & 0o777, 0o600);
+    }
+
+    let receiver = DiscoveryAuth::from_config(&DiscoveryConfig {
+        secret: Some("shared-secret".to_string()),
+        trusted_devices: vec![TrustedDevice { id: "sender".to_string(), public_key }],
+        ..Default::default()
+    })
+    .unwrap();
+    assert!(receiver.is_required());
+
+    let announcement = signed(&sender, "sender", 5033);
+    receiver.verify(&announcement).unwrap();
+    assert!(matches!(receiver.verify(&announcement), Err(DiscoveryError::Replayed(_))));
+
+    let mut tampered = signed(&sender, "sender", 5033);
+    tampered.port = 6000;
+    assert!(matches!(receiver.verify(&tampered), Err(DiscoveryError::Rejected(_))));
+    assert!(matches!(receiver.verify(&packet("sender", 5033)), Err(DiscoveryError::Rejected(_))));
+
+    // Knowing the shared secret is not enough to impersonate a trusted device
+    let impostor = DiscoveryAuth::from_config(&DiscoveryConfig {
+        secret: Some("shared-secret".to_string()),
+        key_file: Some(dir.path().join("impostor.pem").to_string_lossy().to_string()),
+        ..Default::default()
+    })
+    .unwrap();
+    assert!(matches!(receiver.verify(&signed(&impostor, "sender", 5033)), Err(DiscoveryError::Rejected(_))));
+    assert!(matches!(receiver.verify(&signed(&impostor, "other", 5033)), Err(DiscoveryError::Rejected(_))));
+
+    // Without a trusted list the shared secret alone decides
+    let secret_only = DiscoveryAuth::from_config(&DiscoveryConfig {
+        secret: Some("shared-secret".to_string()),
+        ..Default::default()
+    })
+    .unwrap();
+    secret_only.verify(&signed(&impostor, "other", 5033)).unwrap();
+    let wrong_secret = DiscoveryAuth::from_config(&DiscoveryConfig {
+        secret: Some("guess".to_string()),
+        ..Default::default()
+    })
+    .unwrap();
+    assert!(matches!(secret_only.verify(&signed(&wrong_secret, "other", 5033)), Err(DiscoveryError::Rejected(_))));
+
+    // Authentication is off by default
+    let open = DiscoveryAuth::default();
+    assert!(!open.is_required());
+    open.verify(&packet("anyone", 5033)).unwrap();
+
+    let invalid = DiscoveryConfig {
+        trusted_devices: vec![TrustedDevice { id: "bad".to_string(), public_key: "not-a-key".to_string() }],
+        ..Default::default()
+    };
+    assert!(DiscoveryAuth::from_config(&invalid).is_err());
+}
+
+#[tokio::test]
+async fn test_spoofed_announcement_rejected() {
+    let config = DiscoveryConfig {
+        secret: Some("lan-secret".to_string()),
+        ..Default::default()
+    };
+    let server = Arc::new(
+        DiscoveryService::with_auth(device("auth-server", "Auth Server", 5991), DiscoveryAuth::from_config(&config).unwrap())
+            .await
+            .unwrap(),
+    );
+    server.clone().start().await;
+    let scanner = Arc::new(
+        DiscoveryService::with_auth(device("auth-scanner", "Auth Scanner", 0), DiscoveryAuth::from_config(&config).unwrap())
+            .await
+            .unwrap(),
+    );
+    scanner.clone().start().await;
+    let open_scanner = Arc::new(DiscoveryService::new(device("open-scanner", "Open Scanner", 0)).await.unwrap());
+    open_scanner.clone().start().await;
+    scanner.scan().await.unwrap();
+
+    let mut found = None;
+    for _ in 0..40 {
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        found = scanner.get_device("auth-server").await;
+        if found.is_some() {
+            break;
+        }
+    }
+    assert_eq!(found.expect("signed announcement was not accepted").port, 5991);
+
+    // Unsigned announcements: one trying to take over the server's entry, one inventing a device
+    let takeover = DiscoveryPacket::Announcement(Box::new(AnnouncementPacket {
+        instance_id: 2,
+        ..packet("auth-server", 1)
+    }));
+    let invented = DiscoveryPacket::Announcement(Box::new(packet("auth-spoofed", 1)));
+    let socket = std::net::UdpSocket::bind("0.0.0.0:0").unwrap();
+    for _ in 0..3 {
+        for spoof in [&takeover, &invented] {
+            socket.send_to(&serde_json::to_vec(spoof).unwrap(), (MULTICAST_ADDR, MULTICAST_PORT)).unwrap();
+        }
+        tokio::time::sleep(Duration::from_millis(250)).await;
+    }
+
+    // Delivered: the scanner without authentication believes them
+    assert!(open_scanner.get_device("auth-spoofed").await.is_some());
+    assert!(scanner.get_device("auth-spoofed").await.is_none());
+    let kept = scanner.get_device("auth-server").await.unwrap();
+    assert_eq!(kept.port, 5991);
+    assert_ne!(kept.instance_id, 2);
+}
+
+#[test]
+fn test_parse_scan_ranges() {
+    let hosts = |range: &str| parse_cidr(range).unwrap().iter().map(|ip| ip.to_string()).collect::<Vec<_>>();
+    assert_eq!(hosts("192.168.2.0/30"), ["192.168.2.1", "192.168.2.2"]);
+    assert_eq!(hosts("192.168.2.77/30"), ["192.168.2.77", "192.168.2.78"]);
+    assert_eq!(hosts("10.0.0.5"), ["10.0.0.5"]);
+    assert_eq!(hosts("10.0.0.4/31"), ["10.0.0.4", "10.0.0.5"]);
+    assert_eq!(hosts("fd00::10/126"), ["fd00::10", "fd00::11", "fd00::12", "fd00::13"]);
+    assert_eq!(parse_cidr("192.168.0.0/16").unwrap().len(), 65534);
+
+    for invalid in ["10.0.0.0/33", "10.0.0.0/8", "fd00::/64", "example.com", "10.0.0.0/x"] {
+        assert!(parse_cidr(invalid).is_err(), "{} should be rejected", invalid);
+    }
+
+    let scanner = HttpScanner::new(&["10.0.0.0/30".to_string(), "10.0.1.1".to_string()], &[]).unwrap();
+    assert_eq!(scanner.target_count(), 3);
+    let scanner = HttpScanner::new(&["10.0.0.0/30".to_string()], &[5033, 5034]).unwrap();
+    assert_eq!(scanner.target_count(), 4);
+    assert!(HttpScanner::new(&["10.0.0.0/16".to_string(), "10.1.0.0/16".to_string()], &[]).is_err());
+}
+
+fn server_config(port: u16, dir: &std::path::Path, tls: Option<TlsConfig>) -> Config {
+    let db_path = dir.join(format!("scan_{}.db", port)).to_string_lossy().to_string();
+    let mut config = common::test_config(port, db_path);
+    config.server.tls = tls;
+    config.general.device_name = format!("Scan{}", port);
+    config.general.device_id = format!("scan-{}", port);
+    config
+}
+
+#[tokio::test]
+async fn test_http_scan_finds_servers() {
+    let dir = tempfile::tempdir().unwrap();
+    let tls = TlsConfig {
+        cert: dir.path().join("cert.pem").to_string_lossy().to_string(),
+        key: dir.path().join("key.pem").to_string_lossy().to_string(),
+        self_signed: true,
+    };
+    tokio::spawn(server::run(server_config(5992, dir.path(), None)));
+    tokio::spawn(server::run(server_config(5993, dir.path(), Some(tls))));
+    tokio::time::sleep(Duration::from_millis(1000)).await;
+
+    // 5994 has nothing listening
+    let scanner = HttpScanner::new(&["127.0.0.1/32".to_string()], &[5992, 5993, 5994])
+        .unwrap()
+        .with_limits(2, Duration::from_millis(500));
+    let mut found = scanner.scan().await;
+    found.sort_by_key(|d| d.port);
+    assert_eq!(found.iter().map(|d| d.port).collect::<Vec<_>>(), [5992, 5993]);
+    assert_eq!(found.iter().map(|d| d.id.as_str()).collect::<Vec<_>>(), ["scan-5992", "scan-5993"]);
+    assert_eq!(found[0].name, "Scan5992");
+    assert!(found.iter().all(|d| d.ip == "127.0.0.1" && d.capabilities.contains(&"clipboard".to_string())));
+
+    let discovery = DiscoveryService::new(device("scan-client", "Scan Client", 0)).await.unwrap();
+    assert_eq!(discovery.scan_http(&scanner).await, 2);
+    assert_eq!(discovery.get_device("scan-5993").await.map(|d| d.port), Some(5993));
+
+    // Unauthenticated scan results are not trusted when announcements must be signed
+    let auth = DiscoveryAuth::from_config(&DiscoveryConfig {
+        secret: Some("lan-secret".to_string()),
+        ..Default::default()
+    })
+    .unwrap();
+    let strict = DiscoveryService::with_auth(device("scan-strict", "Scan Strict", 0), auth).await.unwrap();
+    assert_eq!(strict.scan_http(&scanner).await, 0);
+    assert!(strict.get_devices().await.is_empty());
+}
+
+#[tokio::test]
+async fn test_multicast_and_http_scan_report_one_instance() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = server_config(5998, dir.path(), None);
+    let shared = Arc::new(DiscoveryService::from_config(&config, 5998).await.unwrap());
+    shared.clone().start().await;
+    tokio::spawn(server::run_with_discovery(config, Some(shared.clone())));
+    tokio::time::sleep(Duration::from_millis(1000)).await;
+
+    let client = Arc::new(DiscoveryService::new(device("both-client", "Both Client", 0)).await.unwrap());
+    let mut events = client.subscribe();
+    client.clone().start().await;
+    client.scan().await.unwrap();
+    assert!(matches!(next_event(&mut events, "scan-5998").await, DiscoveryEvent::DeviceAdded(_)));
+
+    // The scan reports the instance that was announced, so it is not a restart
+    let scanner = HttpScanner::new(&["127.0.0.1/32".to_string()], &[5998]).unwrap();
+    assert_eq!(client.scan_http(&scanner).await, 1);
+    let found = client.get_device("scan-5998").await.unwrap();
+    assert_eq!(found.instance_id, shared.device().instance_id);
+    while let Ok(event) = events.try_recv() {
+        assert!(!matches!(event, DiscoveryEvent::DeviceRestarted(_)), "unexpected {:?}", event);
+    }
+
+    // Sources without an instance ID keep the known one
+    let socket = std::net::UdpSocket::bind("0.0.0.0:0").unwrap();
+    let send = |packet: AnnouncementPacket| {
+        let json = serde_json::to_vec(&DiscoveryPacket::Announcement(Box::new(packet))).unwrap();
+        socket.send_to(&json, (MULTICAST_ADDR, MULTICAST_PORT)).unwrap();
+    };
+    send(packet("both-device", 6003));
+    assert!(matches!(next_event(&mut events, "both-device").await, DiscoveryEvent::DeviceAdded(_)));
+    send(AnnouncementPacket { instance_id: 0, ..packet("both-device", 6004) });
+    match next_event(&mut events, "both-device").await {
+        DiscoveryEvent::DeviceUpdated(device) => assert_eq!((device.port, device.instance_id), (6004, 1)),
+        other => panic!("Unexpected event: {:?}", other),
+    }
+    shared.shutdown().await;
+    client.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_discovery_info_reports_identity() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut config = server_config(5995, dir.path(), None);
+    config.auth.token = Some("secret-token".to_string());
+    config.discovery.secret = Some("lan-secret".to_string());
+    tokio::spawn(server::run(config));
+    tokio::time::sleep(Duration::from_millis(1000)).await;
+
+    // Reachable without the token so scanners can learn that one is needed
+    let response = reqwest::get("http://127.0.0.1:5995/api/discovery").await.unwrap();
+    assert_eq!(response.status(), 200);
+    let info: DiscoveryInfo = response.json().await.unwrap();
+    assert_eq!(info.service, "SyncClipboard");
+    assert_eq!(info.identity.device_id, "scan-5995");
+    assert_eq!(info.identity.alias, "Scan5995");
+    assert_eq!(info.device_name, "Scan5995");
+    assert_ne!(info.instance_id, 0);
+    assert_eq!(info.identity.protocol_version, PROTOCOL_VERSION);
+    assert!(info.identity.capabilities.contains(&"history".to_string()));
+    assert_eq!(info.auth, AuthRequirements { token: true, discovery: true });
+    assert!(info.tls_fingerprint.is_none());
+
+    // Other endpoints still require the token
+    let response = reqwest::get("http://127.0.0.1:5995/history").await.unwrap();
+    assert_eq!(response.status(), 401);
+}
+
+#[tokio::test]
+async fn test_server_uses_shared_discovery() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = server_config(5996, dir.path(), None);
+    let shared = Arc::new(DiscoveryService::from_config(&config, 5996).await.unwrap());
+    shared.clone().start().await;
+    tokio::spawn(server::run_with_discovery(config, Some(shared.clone())));
+    tokio::time::sleep(Duration::from_millis(1000)).await;
+
+    // The server reports the identity the shared service announces
+    let info: DiscoveryInfo = reqwest::get("http://127.0.0.1:5996/api/discovery").await.unwrap().json().await.unwrap();
+    assert_eq!(info.identity, DeviceIdentity::new(shared.device()));
+    assert_eq!(info.instance_id, shared.device().instance_id);
+    assert_eq!(shared.device().id, "scan-5996");
+    shared.shutdown().await;
+}
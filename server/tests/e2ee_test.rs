@@ -1,4 +1,5 @@
-use clipboard_core::config::{Config, ServerConfig, AuthConfig, HistoryConfig, ClientConfig, GeneralConfig};
+mod common;
+
 use clipboard_core::clipboard::ClipboardData;
 use clipboard_core::crypto;
 use base64::{Engine as _, engine::general_purpose};
@@ -6,35 +7,8 @@ use std::time::Duration;
 use tokio::time::sleep;
 
 async fn start_test_server(port: u16) {
-    let config = Config {
-        server: ServerConfig {
-            port,
-            host: "127.0.0.1".to_string(),
-            wevdav_enabled: false,
-            tls: None,
-            enabled: true,
-        },
-        client: ClientConfig {
-            enabled: false,
-            remote_host: "127.0.0.1".to_string(),
-            remote_port: port,
-        },
-        auth: AuthConfig {
-            username: None,
-            password: None,
-            token: None,
-            encrypt_password: None, // Server doesn't know password
-        },
-        history: HistoryConfig {
-            max_count: 100,
-            log_retention_days: 7,
-            db_path: "test_e2ee.db".to_string(),
-        },
-        general: GeneralConfig {
-            device_name: "TestE2EE".to_string(),
-            device_id: "test-e2ee".to_string(),
-        }
-    };
+    // The server doesn't know the encryption password
+    let config = common::test_config(port, "test_e2ee.db");
     
     tokio::spawn(async move {
         if let Err(e) = server::run(config).await {
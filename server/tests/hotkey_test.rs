@@ -0,0 +1,52 @@
+use clipboard_core::config::HotkeyConfig;
+use clipboard_core::hotkey::{Hotkey, HotkeyAction, HotkeyError, Key};
+
+#[test]
+fn test_parse_hotkey_combos() {
+    let hotkey: Hotkey = "Ctrl+Alt+V".parse().unwrap();
+    assert!(hotkey.modifiers.ctrl && hotkey.modifiers.alt);
+    assert!(!hotkey.modifiers.shift && !hotkey.modifiers.meta);
+    assert_eq!(hotkey.key, Key::KeyV);
+
+    // Case and whitespace are ignored, modifier order does not matter
+    assert_eq!("shift + cmd + f5".parse::<Hotkey>().unwrap(), "Meta+Shift+F5".parse().unwrap());
+    assert_eq!("Ctrl+Alt+Up".parse::<Hotkey>().unwrap().key, Key::UpArrow);
+    assert_eq!("Ctrl+1".parse::<Hotkey>().unwrap().key, Key::Num1);
+
+    assert_eq!(
+        "Ctrl+Hyper".parse::<Hotkey>(),
+        Err(HotkeyError::UnknownKey { combo: "Ctrl+Hyper".to_string(), key: "Hyper".to_string() })
+    );
+    assert_eq!("V".parse::<Hotkey>(), Err(HotkeyError::NoModifier("V".to_string())));
+    assert_eq!("Ctrl+Alt".parse::<Hotkey>(), Err(HotkeyError::KeyCount("Ctrl+Alt".to_string())));
+    assert_eq!("Ctrl+A+B".parse::<Hotkey>(), Err(HotkeyError::KeyCount("Ctrl+A+B".to_string())));
+    assert!("Ctrl+F13".parse::<Hotkey>().is_err());
+}
+
+#[test]
+fn test_validate_hotkey_config() {
+    let defaults = HotkeyConfig::default();
+    assert_eq!(defaults.validate(), Ok(()));
+    let actions: Vec<HotkeyAction> = defaults.bindings().unwrap().into_iter().map(|(_, action)| action).collect();
+    assert_eq!(
+        actions,
+        [HotkeyAction::Push, HotkeyAction::Pull, HotkeyAction::TogglePause, HotkeyAction::HistoryPicker]
+    );
+
+    // Empty bindings are skipped
+    let config = HotkeyConfig { pull: String::new(), toggle_pause: " ".to_string(), ..Default::default() };
+    assert_eq!(config.bindings().unwrap().len(), 2);
+
+    let config = HotkeyConfig { history_picker: "alt+ctrl+up".to_string(), ..Default::default() };
+    assert_eq!(
+        config.validate(),
+        Err(HotkeyError::Duplicate {
+            combo: "alt+ctrl+up".to_string(),
+            first: "push",
+            second: "history_picker",
+        })
+    );
+
+    let config = HotkeyConfig { push: "Ctrl+".to_string(), ..Default::default() };
+    assert!(config.validate().is_err());
+}
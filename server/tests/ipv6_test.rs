@@ -0,0 +1,119 @@
+mod common;
+
+use clipboard_core::config::Config;
+use clipboard_core::discovery::{AnnouncementPacket, Device, DiscoveryService, MULTICAST_PORT};
+use std::net::{SocketAddr, TcpStream};
+use std::sync::Arc;
+use std::time::Duration;
+use tempfile::TempDir;
+
+fn device(id: &str, ip: &str, port: u16) -> Device {
+    Device {
+        id: id.to_string(),
+        name: id.to_string(),
+        ip: ip.to_string(),
+        port,
+        instance_id: 1,
+        capabilities: vec![],
+    }
+}
+
+fn config(port: u16, db_path: String) -> Config {
+    let mut config = common::test_config(port, db_path);
+    config.server.host = "::".to_string();
+    config.client.remote_host = "::1".to_string();
+    config.general.device_name = "TestIPv6".to_string();
+    config.general.device_id = "test-ipv6".to_string();
+    config
+}
+
+#[test]
+fn test_scoped_device_addresses() {
+    let v4 = device("a", "192.168.1.10", 5033);
+    assert_eq!(v4.url("http"), "http://192.168.1.10:5033");
+
+    let v6 = device("b", "fd00::2", 5033);
+    assert_eq!(v6.url("https"), "https://[fd00::2]:5033");
+    assert_eq!(v6.socket_addr(), Some("[fd00::2]:5033".parse().unwrap()));
+
+    // Link-local addresses keep the interface index
+    let link_local = device("c", "fe80::1%3", 5033);
+    assert_eq!(link_local.url("http"), "http://[fe80::1%253]:5033");
+    match link_local.socket_addr() {
+        Some(SocketAddr::V6(addr)) => {
+            assert_eq!(addr.ip().to_string(), "fe80::1");
+            assert_eq!(addr.scope_id(), 3);
+        }
+        other => panic!("unexpected address {:?}", other),
+    }
+
+    assert_eq!(device("d", "not-an-ip", 5033).socket_addr(), None);
+}
+
+#[test]
+fn test_remote_url_brackets_ipv6_host() {
+    let config = config(5033, String::new());
+    assert_eq!(config.remotes()[0].url, "http://[::1]:5033");
+}
+
+#[test]
+fn test_dual_stack_listener() {
+    let listener = server::bind_listener("::", 0).unwrap();
+    let port = listener.local_addr().unwrap().port();
+    TcpStream::connect(("127.0.0.1", port)).expect("IPv4 connection to dual-stack listener");
+    TcpStream::connect(("::1", port)).expect("IPv6 connection to dual-stack listener");
+
+    let listener = server::bind_listener("127.0.0.1", 0).unwrap();
+    let port = listener.local_addr().unwrap().port();
+    assert!(TcpStream::connect(("::1", port)).is_err());
+
+    assert!(server::bind_listener("localhost", 0).is_err());
+}
+
+#[tokio::test]
+async fn test_discovery_over_ipv6_loopback() {
+    let port = 5098;
+    let scanner = Arc::new(DiscoveryService::new(device("ipv6-scanner", "0.0.0.0", 0)).await.unwrap());
+    scanner.clone().start().await;
+
+    // Announce a server to the scanner over ::1
+    let packet = serde_json::json!({
+        "type": "announcement",
+        "device_id": "test-ipv6",
+        "alias": "TestIPv6",
+        "port": port,
+        "instance_id": 7,
+        "version": env!("CARGO_PKG_VERSION"),
+        "capabilities": ["clipboard"],
+    });
+    serde_json::from_value::<AnnouncementPacket>(packet.clone()).unwrap();
+    let socket = std::net::UdpSocket::bind("[::1]:0").unwrap();
+    let mut found = None;
+    for _ in 0..20 {
+        socket.send_to(packet.to_string().as_bytes(), ("::1", MULTICAST_PORT)).unwrap();
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        found = scanner.get_device("test-ipv6").await;
+        if found.is_some() {
+            break;
+        }
+    }
+    let found = found.expect("announcement over ::1 was not received");
+    assert_eq!(found.ip, "::1");
+    assert_eq!(found.instance_id, 7);
+    let url = found.url("http");
+    assert_eq!(url, format!("http://[::1]:{}", port));
+
+    // The discovered address reaches a dual-stack server, as does IPv4
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir.path().join("ipv6.db").to_string_lossy().to_string();
+    tokio::spawn(server::run(config(port, db_path)));
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    let client = reqwest::Client::new();
+    for base in [url, format!("http://127.0.0.1:{}", port)] {
+        let response = client.get(format!("{}/api/discovery", base)).send().await.unwrap();
+        assert!(response.status().is_success(), "{} returned {}", base, response.status());
+        let info: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(info["service"], "SyncClipboard");
+    }
+}
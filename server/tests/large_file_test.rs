@@ -1,4 +1,5 @@
-use clipboard_core::config::{Config, ServerConfig, AuthConfig, HistoryConfig, ClientConfig, GeneralConfig};
+mod common;
+
 // use clipboard_core::clipboard::ClipboardData;
 use std::time::Duration;
 use tokio::time::sleep;
@@ -9,35 +10,7 @@ use tokio_util::io::ReaderStream;
 use sha2::{Sha256, Digest};
 
 async fn start_test_server(port: u16) {
-    let config = Config {
-        server: ServerConfig {
-            port,
-            host: "127.0.0.1".to_string(),
-            wevdav_enabled: false,
-            tls: None,
-            enabled: true,
-        },
-        client: ClientConfig {
-            enabled: false,
-            remote_host: "127.0.0.1".to_string(),
-            remote_port: port,
-        },
-        auth: AuthConfig {
-            username: None,
-            password: None,
-            token: None,
-            encrypt_password: None,
-        },
-        history: HistoryConfig {
-            max_count: 100,
-            log_retention_days: 7,
-            db_path: "test_large.db".to_string(),
-        },
-        general: GeneralConfig {
-            device_name: "TestLarge".to_string(),
-            device_id: "test-large".to_string(),
-        }
-    };
+    let config = common::test_config(port, "test_large.db");
     
     tokio::spawn(async move {
         if let Err(e) = server::run(config).await {
@@ -0,0 +1,209 @@
+mod common;
+
+use clipboard_core::clipboard::ClipboardData;
+use clipboard_core::config::{Config, MeshConfig};
+use clipboard_core::discovery::{DiscoveryService, MAX_CLOCK_SKEW};
+use clipboard_core::mesh::{peers_authenticated, LamportClock, Stamp, HEADER_CLOCK, HEADER_ORIGIN, MESH_CAPABILITY};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+fn mesh_config(port: u16, dir: &std::path::Path) -> Config {
+    let db_path = dir.join(format!("mesh_{}.db", port)).to_string_lossy().to_string();
+    let mut config = common::test_config(port, db_path);
+    // Peers connect to the address they were discovered at
+    config.server.host = "0.0.0.0".to_string();
+    config.auth.token = Some("mesh-token".to_string());
+    config.discovery.secret = Some("mesh-secret".to_string());
+    config.general.device_name = format!("Mesh{}", port);
+    config.general.device_id = format!("mesh-{}", port);
+    config.mesh = MeshConfig {
+        enabled: true,
+        // Only the nodes of this test, whatever else is on the LAN
+        devices: (6010..=6015).map(|port| format!("mesh-{}", port)).collect(),
+        ..Default::default()
+    };
+    config
+}
+
+/// 启动一个 mesh 节点，返回它使用的发现服务与服务端任务
+async fn start_node(port: u16, dir: &std::path::Path) -> (Arc<DiscoveryService>, JoinHandle<()>) {
+    let config = mesh_config(port, dir);
+    let discovery = Arc::new(DiscoveryService::from_config(&config, port).await.unwrap());
+    let task = tokio::spawn({
+        let discovery = discovery.clone();
+        async move {
+            let _ = server::run_with_discovery(config, Some(discovery)).await;
+        }
+    });
+    tokio::time::sleep(Duration::from_millis(1000)).await;
+    (discovery, task)
+}
+
+async fn wait_for_peer(discovery: &DiscoveryService, id: &str) {
+    for _ in 0..40 {
+        if discovery.get_device(id).await.is_some() {
+            return;
+        }
+        discovery.scan().await.unwrap();
+        tokio::time::sleep(Duration::from_millis(250)).await;
+    }
+    panic!("'{}' did not discover '{}'", discovery.device().id, id);
+}
+
+async fn put(port: u16, text: &str, stamp: Option<(u64, &str)>) -> u16 {
+    let mut request = reqwest::Client::new()
+        .put(format!("http://127.0.0.1:{}/SyncClipboard.json", port))
+        .bearer_auth("mesh-token")
+        .json(&ClipboardData::new_text(text.to_string()));
+    if let Some((clock, origin)) = stamp {
+        request = request.header(HEADER_CLOCK, clock.to_string()).header(HEADER_ORIGIN, origin);
+    }
+    request.send().await.unwrap().status().as_u16()
+}
+
+async fn get(port: u16) -> Option<String> {
+    let response = reqwest::Client::new()
+        .get(format!("http://127.0.0.1:{}/SyncClipboard.json", port))
+        .bearer_auth("mesh-token")
+        .send()
+        .await
+        .unwrap();
+    match response.json::<ClipboardData>().await {
+        Ok(ClipboardData::Text { content, .. }) => Some(content),
+        _ => None,
+    }
+}
+
+/// 等待节点的最新条目变为 `text`
+async fn wait_for_text(port: u16, text: &str) {
+    let mut current = None;
+    for _ in 0..40 {
+        current = get(port).await;
+        if current.as_deref() == Some(text) {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(250)).await;
+    }
+    panic!("node {} has {:?} instead of '{}'", port, current, text);
+}
+
+#[test]
+fn test_lamport_order() {
+    let older = Stamp { clock: 5, origin: "b".to_string() };
+    let tie = Stamp { clock: 5, origin: "a".to_string() };
+    let newer = Stamp { clock: 6, origin: "a".to_string() };
+    assert!(newer > older && older > tie);
+
+    let mut clock = LamportClock::new();
+    let first = clock.tick();
+    assert!(clock.tick() > first);
+    // Whatever was seen from other devices orders before the next local entry
+    let ahead = first + 60_000;
+    assert!(clock.observe(ahead));
+    assert_eq!(clock.tick(), ahead + 1);
+
+    // Clocks too far ahead of this device are rejected instead of exhausting the clock
+    assert!(!clock.observe(u64::MAX));
+    assert!(!clock.observe(first + 2 * MAX_CLOCK_SKEW.as_millis() as u64));
+    assert_eq!(clock.value(), ahead + 1);
+    assert_eq!(clock.tick(), ahead + 2);
+}
+
+#[tokio::test]
+async fn test_mesh_requires_authenticated_peers() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut config = mesh_config(6013, dir.path());
+    config.discovery.secret = None;
+    let error = server::run_with_discovery(config.clone(), None).await.unwrap_err();
+    assert!(error.to_string().contains("discovery.secret"), "{}", error);
+
+    // A pinned certificate identifies the peers as well
+    assert!(!peers_authenticated(&config.mesh, false));
+    config.mesh.scheme = "https".to_string();
+    assert!(!peers_authenticated(&config.mesh, false));
+    config.mesh.tls.fingerprint = Some("AB:CD".to_string());
+    assert!(peers_authenticated(&config.mesh, false));
+    assert!(peers_authenticated(&MeshConfig::default(), true));
+}
+
+#[tokio::test]
+async fn test_mesh_peers_converge() {
+    let dir = tempfile::tempdir().unwrap();
+    let (a, _) = start_node(6010, dir.path()).await;
+    let (b, _) = start_node(6011, dir.path()).await;
+    assert!(a.device().capabilities.contains(&MESH_CAPABILITY.to_string()));
+    wait_for_peer(&a, "mesh-6011").await;
+    wait_for_peer(&b, "mesh-6010").await;
+
+    // A local write reaches the other node directly
+    assert_eq!(put(6010, "from a", None).await, 200);
+    wait_for_text(6011, "from a").await;
+
+    // Older and malformed entries are refused
+    assert_eq!(put(6011, "stale", Some((1, "mesh-other"))).await, 409);
+    assert_eq!(get(6011).await.as_deref(), Some("from a"));
+    let response = reqwest::Client::new()
+        .put("http://127.0.0.1:6011/SyncClipboard.json")
+        .bearer_auth("mesh-token")
+        .header(HEADER_CLOCK, "1")
+        .json(&ClipboardData::new_text("half".to_string()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 400);
+
+    // Clocks far ahead of the node's time would exhaust every clock they reach
+    assert_eq!(put(6011, "far future", Some((u64::MAX, "mesh-other"))).await, 400);
+    assert_eq!(get(6011).await.as_deref(), Some("from a"));
+
+    // An entry from a node whose clock runs a little ahead is kept and later local writes still order after it
+    let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis() as u64;
+    let future = now + 60_000;
+    assert_eq!(put(6011, "future", Some((future, "mesh-other"))).await, 200);
+    assert_eq!(get(6011).await.as_deref(), Some("future"));
+    assert_eq!(put(6011, "after future", None).await, 200);
+    wait_for_text(6010, "after future").await;
+    // A has now seen B's clock, so its next write wins on B as well
+    assert_eq!(put(6010, "latest from a", None).await, 200);
+    wait_for_text(6011, "latest from a").await;
+
+    // A node joining later catches up with the latest entry
+    let (c, _) = start_node(6012, dir.path()).await;
+    c.scan().await.unwrap();
+    wait_for_text(6012, "latest from a").await;
+    assert_eq!(put(6012, "from c", None).await, 200);
+    wait_for_text(6010, "from c").await;
+    wait_for_text(6011, "from c").await;
+}
+
+#[tokio::test]
+async fn test_mesh_order_survives_restart() {
+    let dir = tempfile::tempdir().unwrap();
+    let (a, task) = start_node(6014, dir.path()).await;
+    let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis() as u64;
+    let future = now + 60_000;
+    assert_eq!(put(6014, "before restart", None).await, 200);
+    assert_eq!(put(6014, "future", Some((future, "mesh-other"))).await, 200);
+
+    // Same port and database, fresh process state
+    task.abort();
+    let _ = task.await;
+    a.shutdown().await;
+    let (a, _) = start_node(6014, dir.path()).await;
+
+    // Entries older than the stored one are still refused
+    assert_eq!(put(6014, "stale", Some((future - 1, "mesh-other"))).await, 409);
+    assert_eq!(get(6014).await.as_deref(), Some("future"));
+
+    // The restored entry is sent to peers joining later
+    let (b, _) = start_node(6015, dir.path()).await;
+    wait_for_peer(&a, "mesh-6015").await;
+    b.scan().await.unwrap();
+    wait_for_text(6015, "future").await;
+
+    // The clock resumes from the stored stamp, so local writes still order after it
+    assert_eq!(put(6014, "after restart", None).await, 200);
+    assert_eq!(put(6014, "older", Some((future + 1, "mesh-0"))).await, 409);
+    wait_for_text(6015, "after restart").await;
+}
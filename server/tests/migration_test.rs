@@ -0,0 +1,154 @@
+use rusqlite::Connection;
+use server::db::{schema, HistoryQuery};
+use tempfile::TempDir;
+
+/// 历史上发布过的表结构（均未设置 user_version）
+const LEGACY_SCHEMAS: &[(&str, &str)] = &[
+    (
+        "original",
+        "CREATE TABLE history (
+            id INTEGER PRIMARY KEY,
+            type TEXT NOT NULL,
+            content TEXT,
+            file TEXT,
+            hash TEXT,
+            timestamp DATETIME DEFAULT CURRENT_TIMESTAMP
+        )",
+    ),
+    (
+        "with_html",
+        "CREATE TABLE history (
+            id INTEGER PRIMARY KEY,
+            type TEXT NOT NULL,
+            content TEXT,
+            html TEXT,
+            file TEXT,
+            hash TEXT,
+            timestamp DATETIME DEFAULT CURRENT_TIMESTAMP
+        )",
+    ),
+    (
+        "with_device",
+        "CREATE TABLE history (
+            id INTEGER PRIMARY KEY,
+            type TEXT NOT NULL,
+            content TEXT,
+            html TEXT,
+            file TEXT,
+            hash TEXT,
+            device TEXT,
+            timestamp DATETIME DEFAULT CURRENT_TIMESTAMP
+        )",
+    ),
+    (
+        "unversioned_current",
+        "CREATE TABLE history (
+            id INTEGER PRIMARY KEY,
+            type TEXT NOT NULL,
+            content TEXT,
+            html TEXT,
+            file TEXT,
+            hash TEXT,
+            device TEXT,
+            pinned BOOLEAN DEFAULT 0,
+            timestamp DATETIME DEFAULT CURRENT_TIMESTAMP
+        )",
+    ),
+];
+
+fn db_path(dir: &TempDir, name: &str) -> String {
+    dir.path().join(format!("{}.db", name)).to_string_lossy().to_string()
+}
+
+#[test]
+fn test_fresh_database_is_current() {
+    let dir = TempDir::new().unwrap();
+    let conn = schema::open(&db_path(&dir, "fresh")).unwrap();
+
+    assert_eq!(schema::version(&conn).unwrap(), schema::SCHEMA_VERSION);
+    assert!(schema::read_history(&conn, &HistoryQuery::default()).unwrap().is_empty());
+}
+
+#[test]
+fn test_upgrade_legacy_schemas() {
+    let dir = TempDir::new().unwrap();
+
+    for (name, ddl) in LEGACY_SCHEMAS {
+        let path = db_path(&dir, name);
+        {
+            let conn = Connection::open(&path).unwrap();
+            conn.execute(ddl, []).unwrap();
+            conn.execute(
+                "INSERT INTO history (type, content, file, hash) VALUES ('Text', 'legacy', NULL, NULL)",
+                [],
+            ).unwrap();
+        }
+
+        let conn = schema::open(&path).unwrap_or_else(|e| panic!("{}: migration failed: {}", name, e));
+        assert_eq!(schema::version(&conn).unwrap(), schema::SCHEMA_VERSION, "{}", name);
+
+        let rows = schema::read_history(&conn, &HistoryQuery::default()).unwrap();
+        assert_eq!(rows.len(), 1, "{}", name);
+        assert_eq!(rows[0].content.as_deref(), Some("legacy"), "{}", name);
+        assert!(!rows[0].pinned, "{}", name);
+
+        // Upgraded databases accept writes to every current column
+        conn.execute(
+            "INSERT INTO history (type, content, html, device, pinned) VALUES ('Text', 'new', '<b>new</b>', 'dev', 1)",
+            [],
+        ).unwrap();
+        schema::toggle_pinned(&conn, rows[0].id).unwrap();
+        let rows = schema::read_history(&conn, &HistoryQuery::default()).unwrap();
+        assert!(rows.iter().all(|r| r.pinned), "{}", name);
+    }
+}
+
+#[test]
+fn test_upgrade_indexes_existing_rows_for_search() {
+    let dir = TempDir::new().unwrap();
+    let path = db_path(&dir, "search_backfill");
+    {
+        let conn = Connection::open(&path).unwrap();
+        conn.execute(LEGACY_SCHEMAS[3].1, []).unwrap();
+        conn.execute(
+            "INSERT INTO history (type, content, html) VALUES ('Text', 'plain words', '<i>italic&nbsp;phrase</i>')",
+            [],
+        ).unwrap();
+    }
+
+    let conn = schema::open(&path).unwrap();
+    for q in ["plain", "italic phrase"] {
+        let query = HistoryQuery { q: Some(q.to_string()), ..Default::default() };
+        let rows = schema::read_history(&conn, &query).unwrap();
+        assert_eq!(rows.len(), 1, "{}", q);
+        assert!(rows[0].snippet.as_deref().unwrap().contains("<mark>"), "{}", q);
+    }
+
+    // Index stays in sync with later deletes
+    schema::clear_unpinned(&conn).unwrap();
+    let query = HistoryQuery { q: Some("plain".to_string()), ..Default::default() };
+    assert!(schema::read_history(&conn, &query).unwrap().is_empty());
+}
+
+#[test]
+fn test_migrate_is_idempotent() {
+    let dir = TempDir::new().unwrap();
+    let path = db_path(&dir, "idempotent");
+
+    drop(schema::open(&path).unwrap());
+    let mut conn = schema::open(&path).unwrap();
+    schema::migrate(&mut conn).unwrap();
+    assert_eq!(schema::version(&conn).unwrap(), schema::SCHEMA_VERSION);
+}
+
+#[test]
+fn test_refuses_newer_schema() {
+    let dir = TempDir::new().unwrap();
+    let path = db_path(&dir, "future");
+    {
+        let conn = Connection::open(&path).unwrap();
+        conn.pragma_update(None, "user_version", schema::SCHEMA_VERSION + 1).unwrap();
+    }
+
+    assert!(schema::open(&path).is_err());
+}
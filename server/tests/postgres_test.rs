@@ -0,0 +1,205 @@
+#![cfg(feature = "postgres")]
+
+mod common;
+
+use clipboard_core::clipboard::ClipboardData;
+use clipboard_core::mesh::Stamp;
+use server::db::{HistoryStore, PostgresStore};
+use std::net::TcpListener;
+use std::time::{Duration, Instant};
+use tokio::time::sleep;
+
+/// 需要设置 SYNCCLIPBOARD_TEST_POSTGRES_URL 指向一个可写的测试数据库，否则跳过
+fn postgres_url() -> Option<String> {
+    std::env::var("SYNCCLIPBOARD_TEST_POSTGRES_URL").ok()
+}
+
+fn find_available_port() -> u16 {
+    TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port()
+}
+
+async fn start_replica(database_url: String) -> u16 {
+    let port = find_available_port();
+    let mut config = common::test_config(port, String::new());
+    config.history.database_url = Some(database_url);
+
+    tokio::spawn(async move {
+        if let Err(e) = server::run(config).await {
+            eprintln!("Server error: {}", e);
+        }
+    });
+    sleep(Duration::from_millis(500)).await;
+    port
+}
+
+#[tokio::test]
+async fn test_long_poll_wakes_across_replicas() {
+    let Some(url) = postgres_url() else {
+        eprintln!("SYNCCLIPBOARD_TEST_POSTGRES_URL not set, skipping");
+        return;
+    };
+
+    let port_a = start_replica(url.clone()).await;
+    let port_b = start_replica(url).await;
+    let client = reqwest::Client::new();
+    let url_a = format!("http://127.0.0.1:{}/SyncClipboard.json", port_a);
+    let url_b = format!("http://127.0.0.1:{}/SyncClipboard.json", port_b);
+
+    // Seed so both replicas agree on a latest ID
+    let resp = client.put(&url_a).json(&ClipboardData::new_text("seed".to_string())).send().await.unwrap();
+    assert!(resp.status().is_success());
+    let resp = client.get(&url_a).send().await.unwrap();
+    let last_id: i64 = resp.headers()["X-Clipboard-Id"].to_str().unwrap().parse().unwrap();
+
+    // Long-poll replica A, write through replica B
+    let poll_client = client.clone();
+    let poll = tokio::spawn(async move {
+        let started = Instant::now();
+        let resp = poll_client
+            .get(format!("{}?wait=20&last_id={}", url_a, last_id))
+            .send()
+            .await
+            .unwrap();
+        let id: i64 = resp.headers()["X-Clipboard-Id"].to_str().unwrap().parse().unwrap();
+        let body: ClipboardData = resp.json().await.unwrap();
+        (started.elapsed(), id, body)
+    });
+
+    sleep(Duration::from_millis(500)).await;
+    let resp = client.put(&url_b).json(&ClipboardData::new_text("from replica b".to_string())).send().await.unwrap();
+    assert!(resp.status().is_success());
+
+    let (elapsed, id, body) = poll.await.unwrap();
+    assert!(elapsed < Duration::from_secs(10), "long poll was not woken by NOTIFY ({:?})", elapsed);
+    assert!(id > last_id);
+    match body {
+        ClipboardData::Text { content, .. } => assert_eq!(content, "from replica b"),
+        _ => panic!("Wrong data type"),
+    }
+}
+
+#[tokio::test]
+async fn test_search_on_postgres() {
+    let Some(url) = postgres_url() else {
+        eprintln!("SYNCCLIPBOARD_TEST_POSTGRES_URL not set, skipping");
+        return;
+    };
+
+    let port = start_replica(url).await;
+    let client = reqwest::Client::new();
+    let base = format!("http://127.0.0.1:{}", port);
+    // The database is shared across runs, so search for a term unique to this run
+    let marker = format!("needle{}", port);
+
+    let html = ClipboardData::Text {
+        content: "rich".to_string(),
+        file: None,
+        html: Some(format!("<p>inside <b>{}</b> markup</p>", marker)),
+        device: Some("pg-search".to_string()),
+        ttl: None,
+        sensitive: false,
+    };
+    for data in [ClipboardData::new_text(format!("plain {} text", marker)), html] {
+        let resp = client.put(format!("{}/SyncClipboard.json", base)).json(&data).send().await.unwrap();
+        assert!(resp.status().is_success());
+    }
+
+    let items: Vec<serde_json::Value> = client.get(format!("{}/history?q={}", base, marker))
+        .send().await.unwrap().json().await.unwrap();
+    assert_eq!(items.len(), 2);
+    assert!(items.iter().all(|i| i["snippet"].as_str().unwrap().contains(&format!("<mark>{}</mark>", marker))));
+
+    let items: Vec<serde_json::Value> = client.get(format!("{}/history?q={}&device=pg-search&type=text&since=2000-01-01", base, marker))
+        .send().await.unwrap().json().await.unwrap();
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0]["content"], "rich");
+}
+
+#[tokio::test]
+async fn test_reimport_own_export_on_postgres() {
+    let Some(url) = postgres_url() else {
+        eprintln!("SYNCCLIPBOARD_TEST_POSTGRES_URL not set, skipping");
+        return;
+    };
+
+    let port = start_replica(url).await;
+    let client = reqwest::Client::new();
+    let base = format!("http://127.0.0.1:{}", port);
+    let marker = format!("exported{}", port);
+    let resp = client.put(format!("{}/SyncClipboard.json", base))
+        .json(&ClipboardData::new_text(marker.clone()))
+        .send().await.unwrap();
+    assert!(resp.status().is_success());
+
+    let archive = client.get(format!("{}/api/export", base)).send().await.unwrap().bytes().await.unwrap();
+    let resp = client.post(format!("{}/api/import", base)).body(archive).send().await.unwrap();
+    assert!(resp.status().is_success());
+    let result: serde_json::Value = resp.json().await.unwrap();
+    assert!(result["skipped"].as_u64().unwrap() >= 1, "{}", result);
+
+    // Other tests share the database, so only check this run's row was not duplicated
+    let items: Vec<serde_json::Value> = client.get(format!("{}/history?q={}", base, marker))
+        .send().await.unwrap().json().await.unwrap();
+    assert_eq!(items.len(), 1);
+}
+
+#[tokio::test]
+async fn test_mesh_stamps_on_postgres() {
+    let Some(url) = postgres_url() else {
+        eprintln!("SYNCCLIPBOARD_TEST_POSTGRES_URL not set, skipping");
+        return;
+    };
+
+    let store = PostgresStore::connect(&url, 0).await.unwrap();
+    // The database is shared across runs, so stamp above anything stored before
+    let clock = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis() as u64 * 1000;
+    let stamp = |origin: &str| Stamp { clock, origin: origin.to_string() };
+    store.save(&ClipboardData::new_text("upper".to_string()), Some(&stamp("B"))).await.unwrap();
+    store.save(&ClipboardData::new_text("lower".to_string()), Some(&stamp("a"))).await.unwrap();
+
+    // Origins compare byte-wise whatever the database collation
+    let (latest, entry) = store.latest_stamped().await.unwrap().unwrap();
+    assert_eq!(latest, stamp("a"));
+    match entry {
+        Some(ClipboardData::Text { content, .. }) => assert_eq!(content, "lower"),
+        other => panic!("Unexpected entry: {:?}", other),
+    }
+
+    // Sensitive entries keep their stamp but are not replayed
+    let secret = ClipboardData::Text {
+        content: "secret".to_string(),
+        file: None,
+        html: None,
+        device: None,
+        ttl: None,
+        sensitive: true,
+    };
+    let newer = Stamp { clock: clock + 1, origin: "a".to_string() };
+    store.save(&secret, Some(&newer)).await.unwrap();
+    assert_eq!(store.latest_stamped().await.unwrap(), Some((newer, None)));
+}
+
+#[tokio::test]
+async fn test_store_replaces_broken_connections() {
+    let Some(url) = postgres_url() else {
+        eprintln!("SYNCCLIPBOARD_TEST_POSTGRES_URL not set, skipping");
+        return;
+    };
+
+    // Tagged so only this store's connections are terminated
+    let separator = if url.contains('?') { '&' } else { '?' };
+    let store = PostgresStore::connect(&format!("{}{}application_name=sc_pool_test", url, separator), 0).await.unwrap();
+    store.save(&ClipboardData::new_text("before".to_string()), None).await.unwrap();
+
+    let (admin, connection) = tokio_postgres::connect(&url, tokio_postgres::NoTls).await.unwrap();
+    tokio::spawn(connection);
+    let killed = admin
+        .query("SELECT pg_terminate_backend(pid) FROM pg_stat_activity WHERE application_name = 'sc_pool_test'", &[])
+        .await
+        .unwrap();
+    assert!(!killed.is_empty());
+    sleep(Duration::from_millis(200)).await;
+
+    store.save(&ClipboardData::new_text("after".to_string()), None).await.unwrap();
+    assert!(store.get_latest_id().await.unwrap().is_some());
+}
@@ -0,0 +1,168 @@
+mod common;
+use common::TestServer;
+use clipboard_core::clipboard::ClipboardData;
+use clipboard_core::config::{ContentKind, RemoteConfig, RouteConfig, SyncDirection, SyncMode};
+use clipboard_core::discovery::Device;
+use clipboard_core::remote::{Outgoing, Remote, Route};
+
+fn remote(name: &str, url: String) -> RemoteConfig {
+    RemoteConfig {
+        name: name.to_string(),
+        url,
+        token: None,
+        encrypt_password: None,
+        direction: SyncDirection::Both,
+        route: RouteConfig::default(),
+        tls: Default::default(),
+        server_id: None,
+    }
+}
+
+#[test]
+fn test_route_rules() {
+    let everything = Route::new(&RouteConfig::default());
+    assert!(everything.accepts(Outgoing::Text("anything")));
+    assert!(everything.accepts(Outgoing::Image));
+    assert!(everything.accepts(Outgoing::File));
+
+    let work = Route::new(&RouteConfig {
+        types: vec![ContentKind::Text, ContentKind::File],
+        include: vec![r"(?i)\bJIRA-\d+".to_string(), "(unclosed".to_string()],
+        exclude: vec!["password".to_string()],
+    });
+    assert!(work.accepts(Outgoing::Text("see JIRA-123 for details")));
+    assert!(!work.accepts(Outgoing::Text("holiday photos")));
+    assert!(!work.accepts(Outgoing::Text("JIRA-9 password reset")));
+    assert!(!work.accepts(Outgoing::Image));
+    // Text patterns do not apply to files
+    assert!(work.accepts(Outgoing::File));
+}
+
+#[tokio::test]
+async fn test_each_remote_uses_its_own_url_and_encryption() {
+    let home = TestServer::new().await;
+    let work = TestServer::new().await;
+
+    // Trailing slash and a missing scheme are both accepted
+    let home_remote = Remote::new(
+        &RemoteConfig { encrypt_password: Some("home-secret".to_string()), ..remote("home", format!("{}/", home.base_url)) },
+        "laptop",
+        "laptop-id",
+    ).unwrap();
+    let work_remote = Remote::new(&remote("work", format!("127.0.0.1:{}", work.port)), "laptop", "laptop-id").unwrap();
+
+    home_remote.upload_text("family plans".to_string(), None, None).await.unwrap();
+    work_remote.upload_text("quarterly report".to_string(), None, None).await.unwrap();
+
+    // The home server only ever sees ciphertext
+    let raw: ClipboardData = home.client()
+        .get(format!("{}/SyncClipboard.json", home.base_url))
+        .send().await.unwrap()
+        .json().await.unwrap();
+    match raw {
+        ClipboardData::Text { content, .. } => assert!(content.starts_with("E2EE::")),
+        _ => panic!("Wrong data type"),
+    }
+
+    match home_remote.download(0, -1).await.unwrap() {
+        (Some(ClipboardData::Text { content, device, .. }), _) => {
+            assert_eq!(content, "family plans");
+            assert_eq!(device.as_deref(), Some("laptop"));
+        }
+        other => panic!("Unexpected response: {:?}", other),
+    }
+    match work_remote.download(0, -1).await.unwrap() {
+        (Some(ClipboardData::Text { content, .. }), _) => assert_eq!(content, "quarterly report"),
+        other => panic!("Unexpected response: {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_rejected_uploads_are_errors() {
+    let server = TestServer::with_token("secret").await;
+    let remote = Remote::new(&remote("locked", server.base_url.clone()), "laptop", "laptop-id").unwrap();
+
+    assert!(remote.upload_text("no token".to_string(), None, None).await.is_err());
+    assert!(remote.upload_image("a.png".to_string(), vec![1, 2, 3], "a".to_string(), None).await.is_err());
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("notes.txt");
+    std::fs::write(&path, "notes").unwrap();
+    assert!(remote.upload_file_stream(&path, "b".to_string()).await.is_err());
+}
+
+#[test]
+fn test_remote_wants_respects_direction() {
+    let receive_only = Remote::new(
+        &RemoteConfig { direction: SyncDirection::Receive, ..remote("kiosk", "http://127.0.0.1:1".to_string()) },
+        "laptop",
+        "laptop-id",
+    ).unwrap();
+    assert!(!receive_only.wants(Outgoing::Text("hello")));
+
+    let images_only = Remote::new(
+        &RemoteConfig {
+            route: RouteConfig { types: vec![ContentKind::Image], ..Default::default() },
+            ..remote("gallery", "http://127.0.0.1:1".to_string())
+        },
+        "laptop",
+        "laptop-id",
+    ).unwrap();
+    assert!(images_only.wants(Outgoing::Image));
+    assert!(!images_only.wants(Outgoing::Text("hello")));
+}
+
+#[test]
+fn test_client_mode_is_the_default_remote_direction() {
+    let mut config = common::test_config(1, String::new());
+    config.client.mode = SyncDirection::Receive;
+    assert_eq!(config.client.push_mode, SyncMode::Auto);
+    let remotes = config.remotes();
+    assert_eq!(remotes.len(), 1);
+    assert_eq!(remotes[0].direction, SyncDirection::Receive);
+}
+
+#[test]
+fn test_discovered_device_url() {
+    let mut device = Device {
+        id: "desk".to_string(),
+        name: "Desk".to_string(),
+        ip: "192.168.1.10".to_string(),
+        port: 5033,
+        instance_id: 1,
+        capabilities: vec![],
+    };
+    assert_eq!(device.url("http"), "http://192.168.1.10:5033");
+    device.ip = "fe80::1".to_string();
+    assert_eq!(device.url("https"), "https://[fe80::1]:5033");
+}
+
+#[tokio::test]
+async fn test_remote_follows_relocated_server() {
+    let old = TestServer::new().await;
+    let new = TestServer::new().await;
+
+    let remote = Remote::new(
+        &RemoteConfig { server_id: Some("desk".to_string()), ..remote("desk", old.base_url.clone()) },
+        "laptop",
+        "laptop-id",
+    ).unwrap();
+    assert_eq!(remote.scheme(), "http");
+
+    // The server moved to another address
+    assert!(remote.relocate(Some(&new.base_url)));
+    assert!(!remote.relocate(Some(&new.base_url)));
+    remote.upload_text("moved".to_string(), None, None).await.unwrap();
+    match new.client().get(format!("{}/SyncClipboard.json", new.base_url)).send().await.unwrap().json().await.unwrap() {
+        ClipboardData::Text { content, .. } => assert_eq!(content, "moved"),
+        other => panic!("Unexpected data: {:?}", other),
+    }
+
+    // Nothing discovered any more: back to the configured address
+    assert!(remote.relocate(None));
+    assert_eq!(remote.base_url(), old.base_url);
+    remote.upload_text("home again".to_string(), None, None).await.unwrap();
+    match remote.download(0, -1).await.unwrap() {
+        (Some(ClipboardData::Text { content, .. }), _) => assert_eq!(content, "home again"),
+        other => panic!("Unexpected response: {:?}", other),
+    }
+}
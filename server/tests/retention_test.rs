@@ -0,0 +1,176 @@
+mod common;
+
+use clipboard_core::clipboard::ClipboardData;
+use clipboard_core::config::RetentionConfig;
+use rusqlite::{params, Connection};
+use server::db::{HistoryStore, SqliteStore};
+use server::retention::{prune, PruneStats};
+use std::path::Path;
+use tempfile::TempDir;
+
+struct Fixture {
+    dir: TempDir,
+    store: SqliteStore,
+}
+
+impl Fixture {
+    fn new() -> Self {
+        let dir = TempDir::new().unwrap();
+        std::fs::create_dir(dir.path().join("uploads")).unwrap();
+        let store = SqliteStore::new(&Self::db_path(&dir), 0).unwrap();
+        Self { dir, store }
+    }
+
+    fn db_path(dir: &TempDir) -> String {
+        dir.path().join("history.db").to_string_lossy().to_string()
+    }
+
+    fn uploads(&self) -> std::path::PathBuf {
+        self.dir.path().join("uploads")
+    }
+
+    async fn text(&self, content: &str, days_old: u32) {
+        self.save(ClipboardData::new_text(content.to_string()), days_old).await;
+    }
+
+    async fn image(&self, name: &str, size: usize, days_old: u32) {
+        std::fs::write(self.uploads().join(name), vec![0u8; size]).unwrap();
+        let data = ClipboardData::Image { hash: None, filename: name.to_string(), device: None, ttl: None, sensitive: false };
+        self.save(data, days_old).await;
+    }
+
+    /// 保存后把时间戳改到 `days_old` 天前
+    async fn save(&self, data: ClipboardData, days_old: u32) {
+        self.store.save(&data, None).await.unwrap();
+        let id = self.store.get_latest_id().await.unwrap().unwrap();
+        let conn = Connection::open(Self::db_path(&self.dir)).unwrap();
+        conn.execute(
+            "UPDATE history SET timestamp = datetime('now', ?1) WHERE id = ?2",
+            params![format!("-{} days", days_old), id],
+        ).unwrap();
+    }
+
+    async fn pin(&self, content: &str) {
+        let rows = self.store.export_history().await.unwrap();
+        let row = rows.iter().find(|r| r.content.as_deref() == Some(content) || r.file.as_deref() == Some(content)).unwrap();
+        self.store.set_pinned(row.id, true).await.unwrap();
+    }
+
+    async fn remaining(&self) -> Vec<String> {
+        self.store.export_history().await.unwrap()
+            .into_iter()
+            .map(|r| r.content.or(r.file).unwrap_or_default())
+            .collect()
+    }
+
+    async fn prune(&self, config: &RetentionConfig) -> PruneStats {
+        prune(&self.store, config, &self.uploads()).await.unwrap()
+    }
+}
+
+fn exists(dir: &Path, name: &str) -> bool {
+    dir.join(name).exists()
+}
+
+#[tokio::test]
+async fn test_per_type_age_limits() {
+    let f = Fixture::new();
+    f.text("old text", 40).await;
+    f.text("old pinned text", 40).await;
+    f.text("recent text", 5).await;
+    f.image("old.png", 10, 10).await;
+    f.image("recent.png", 10, 1).await;
+    f.pin("old pinned text").await;
+
+    let config = RetentionConfig { text_days: 30, image_days: 7, ..Default::default() };
+    let stats = f.prune(&config).await;
+
+    assert_eq!(stats, PruneStats { rows: 2, blobs: 1 });
+    assert_eq!(f.remaining().await, ["old pinned text", "recent text", "recent.png"]);
+    assert!(!exists(&f.uploads(), "old.png"));
+    assert!(exists(&f.uploads(), "recent.png"));
+
+    // Pinned items expire too when keep_pinned is off
+    let config = RetentionConfig { keep_pinned: false, ..config };
+    assert_eq!(f.prune(&config).await.rows, 1);
+    assert_eq!(f.remaining().await, ["recent text", "recent.png"]);
+}
+
+#[tokio::test]
+async fn test_shared_upload_survives_until_last_reference() {
+    let f = Fixture::new();
+    f.image("same.png", 10, 10).await;
+    f.image("same.png", 10, 1).await;
+
+    let config = RetentionConfig { image_days: 7, ..Default::default() };
+    assert_eq!(f.prune(&config).await, PruneStats { rows: 1, blobs: 0 });
+    assert!(exists(&f.uploads(), "same.png"));
+}
+
+#[tokio::test]
+async fn test_total_size_cap_removes_oldest_first() {
+    let f = Fixture::new();
+    let mb = 1024 * 1024;
+    f.image("a.png", mb / 2, 4).await;
+    f.image("b.png", mb / 2, 3).await;
+    f.image("c.png", mb / 2, 2).await;
+    f.image("d.png", mb / 2, 1).await;
+    f.pin("a.png").await;
+
+    // 2 MB in total, 1 MB allowed: the oldest unpinned images go first, pinned ones stay
+    let config = RetentionConfig { max_total_mb: 1, ..Default::default() };
+    let stats = f.prune(&config).await;
+
+    assert_eq!(stats, PruneStats { rows: 2, blobs: 2 });
+    assert_eq!(f.remaining().await, ["a.png", "d.png"]);
+    for (name, kept) in [("a.png", true), ("b.png", false), ("c.png", false), ("d.png", true)] {
+        assert_eq!(exists(&f.uploads(), name), kept, "{}", name);
+    }
+
+    // Already under the cap: nothing more to do
+    assert_eq!(f.prune(&config).await, PruneStats::default());
+}
+
+#[tokio::test]
+async fn test_max_count_trim_removes_uploads() {
+    let server = common::TestServer::with_max_count(2).await;
+    let client = server.client();
+    let names: Vec<String> = (0..3).map(|i| format!("trim-{}-{}.png", server.port, i)).collect();
+    for name in &names {
+        let resp = client.put(format!("{}/file/{}", server.base_url, name)).body(vec![1u8, 2, 3]).send().await.unwrap();
+        assert!(resp.status().is_success());
+        let data = ClipboardData::Image { hash: None, filename: name.clone(), device: None, ttl: None, sensitive: false };
+        let resp = client.put(format!("{}/SyncClipboard.json", server.base_url)).json(&data).send().await.unwrap();
+        assert!(resp.status().is_success());
+    }
+
+    // The oldest entry was trimmed together with its upload
+    let uploads = Path::new("./uploads");
+    for _ in 0..20 {
+        if !uploads.join(&names[0]).exists() {
+            break;
+        }
+        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+    }
+    assert!(!uploads.join(&names[0]).exists());
+    assert!(uploads.join(&names[1]).exists() && uploads.join(&names[2]).exists());
+    for name in &names[1..] {
+        let _ = std::fs::remove_file(uploads.join(name));
+    }
+}
+
+#[tokio::test]
+async fn test_background_tasks_stop_with_the_server() {
+    let dir = TempDir::new().unwrap();
+    // The port is taken, so the server fails after its background tasks were started
+    let taken = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = taken.local_addr().unwrap().port();
+    let mut config = common::test_config(port, Fixture::db_path(&dir));
+    config.history.retention = RetentionConfig { text_days: 1, ..Default::default() };
+
+    let metrics = tokio::runtime::Handle::current().metrics();
+    let before = metrics.num_alive_tasks();
+    assert!(server::run(config).await.is_err());
+    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+    assert_eq!(metrics.num_alive_tasks(), before);
+}
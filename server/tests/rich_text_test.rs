@@ -1,39 +1,13 @@
-use clipboard_core::config::{Config, ServerConfig, AuthConfig, HistoryConfig, ClientConfig, GeneralConfig};
+mod common;
+
 use clipboard_core::clipboard::ClipboardData;
 use std::time::Duration;
 use tokio::time::sleep;
 
 // Helper to start server in background
 async fn start_test_server(port: u16) {
-    let config = Config {
-        server: ServerConfig {
-            port,
-            host: "127.0.0.1".to_string(),
-            wevdav_enabled: false,
-            tls: None,
-            enabled: true,
-        },
-        client: ClientConfig {
-            enabled: false,
-            remote_host: "127.0.0.1".to_string(),
-            remote_port: port,
-        },
-        auth: AuthConfig {
-            username: None,
-            password: None,
-            token: None,
-            encrypt_password: None,
-        },
-        history: HistoryConfig {
-            max_count: 10,
-            log_retention_days: 7,
-            db_path: "test_rich.db".to_string(),
-        },
-        general: GeneralConfig {
-            device_name: "TestRich".to_string(),
-            device_id: "test-rich".to_string(),
-        }
-    };
+    let mut config = common::test_config(port, "test_rich.db");
+    config.history.max_count = 10;
     
     // Spawn server in background
     tokio::spawn(async move {
@@ -63,6 +37,8 @@ async fn test_rich_text_sync() {
         html: Some(html_content.clone()),
         file: None,
         device: None,
+        ttl: None,
+        sensitive: false,
     };
     
     // 2. Upload
@@ -0,0 +1,114 @@
+mod common;
+use common::TestServer;
+use clipboard_core::clipboard::ClipboardData;
+use serde_json::Value;
+
+fn text(content: &str, html: Option<&str>, device: &str) -> ClipboardData {
+    ClipboardData::Text {
+        content: content.to_string(),
+        file: None,
+        html: html.map(str::to_string),
+        device: Some(device.to_string()),
+        ttl: None,
+        sensitive: false,
+    }
+}
+
+async fn seed(server: &TestServer) {
+    let client = server.client();
+    let url = format!("{}/SyncClipboard.json", server.base_url);
+    let items = [
+        text("grocery list: milk, eggs, bread", None, "laptop"),
+        text("meeting notes about the deploy pipeline", None, "desktop"),
+        text("deploy deploy deploy: rollback steps", None, "laptop"),
+        text("see attachment", Some("<p>Quarterly <b>budget</b> &amp; forecast</p><script>var hidden = 1;</script>"), "desktop"),
+    ];
+    for item in items {
+        let resp = client.put(&url).json(&item).send().await.unwrap();
+        assert!(resp.status().is_success());
+    }
+}
+
+async fn history(server: &TestServer, query: &str) -> Vec<Value> {
+    let resp = server.client()
+        .get(format!("{}/history?{}", server.base_url, query))
+        .send()
+        .await
+        .unwrap();
+    assert!(resp.status().is_success(), "{} -> {}", query, resp.status());
+    resp.json().await.unwrap()
+}
+
+fn contents(items: &[Value]) -> Vec<&str> {
+    items.iter().map(|i| i["content"].as_str().unwrap_or_default()).collect()
+}
+
+#[tokio::test]
+async fn test_search_ranks_and_highlights() {
+    let server = TestServer::new().await;
+    seed(&server).await;
+
+    let items = history(&server, "q=deploy").await;
+    assert_eq!(items.len(), 2);
+    // More occurrences rank higher
+    assert_eq!(items[0]["content"], "deploy deploy deploy: rollback steps");
+    let snippet = items[1]["snippet"].as_str().unwrap();
+    assert!(snippet.contains("<mark>deploy</mark>"), "snippet: {}", snippet);
+
+    // Terms match as prefixes and are combined with AND
+    let items = history(&server, "q=roll%20dep").await;
+    assert_eq!(contents(&items), ["deploy deploy deploy: rollback steps"]);
+
+    // Plain listing has no snippets
+    let items = history(&server, "limit=10").await;
+    assert_eq!(items.len(), 4);
+    assert!(items.iter().all(|i| i.get("snippet").is_none()));
+}
+
+#[tokio::test]
+async fn test_search_matches_html_text() {
+    let server = TestServer::new().await;
+    seed(&server).await;
+
+    let items = history(&server, "q=budget").await;
+    assert_eq!(contents(&items), ["see attachment"]);
+    assert!(items[0]["snippet"].as_str().unwrap().contains("<mark>budget</mark>"));
+
+    // Markup, entities and script bodies are not indexed
+    assert!(history(&server, "q=amp").await.is_empty());
+    assert!(history(&server, "q=hidden").await.is_empty());
+    // FTS syntax in user input does not break the query
+    assert!(history(&server, "q=%22budget%20OR%20(").await.is_empty());
+}
+
+#[tokio::test]
+async fn test_history_filters() {
+    let server = TestServer::new().await;
+    seed(&server).await;
+    let client = server.client();
+
+    let items = history(&server, "device=laptop").await;
+    assert_eq!(items.len(), 2);
+    assert!(items.iter().all(|i| i["device"] == "laptop"));
+
+    let items = history(&server, "q=deploy&device=desktop").await;
+    assert_eq!(contents(&items), ["meeting notes about the deploy pipeline"]);
+
+    let id = items[0]["id"].as_i64().unwrap();
+    client.patch(format!("{}/history/{}", server.base_url, id))
+        .json(&serde_json::json!({ "pinned": true }))
+        .send()
+        .await
+        .unwrap();
+    let items = history(&server, "pinned=true").await;
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0]["id"], id);
+    assert_eq!(history(&server, "pinned=false").await.len(), 3);
+
+    assert_eq!(history(&server, "type=text").await.len(), 4);
+    assert!(history(&server, "type=Image").await.is_empty());
+
+    assert_eq!(history(&server, "since=2000-01-01&until=2999-12-31").await.len(), 4);
+    assert!(history(&server, "until=2000-01-01").await.is_empty());
+    assert!(history(&server, "since=2999-01-01").await.is_empty());
+}
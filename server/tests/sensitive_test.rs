@@ -0,0 +1,174 @@
+mod common;
+use common::TestServer;
+use clipboard_core::clipboard::ClipboardData;
+use clipboard_core::config::{ConcealedConfig, RemoteConfig};
+use clipboard_core::remote::{Remote, HEADER_DEVICE_ID};
+use serde_json::Value;
+
+/// 以设备 ID 区分同一主机上的多个客户端
+fn device(id: &str) -> reqwest::Client {
+    let mut headers = reqwest::header::HeaderMap::new();
+    headers.insert(HEADER_DEVICE_ID, id.parse().unwrap());
+    reqwest::Client::builder().default_headers(headers).build().unwrap()
+}
+
+async fn put(client: &reqwest::Client, server: &TestServer, data: &ClipboardData) {
+    let resp = client
+        .put(format!("{}/SyncClipboard.json", server.base_url))
+        .json(data)
+        .send()
+        .await
+        .unwrap();
+    assert!(resp.status().is_success());
+}
+
+/// 获取当前剪贴板，返回 (ID, 文本内容)
+async fn fetch(client: &reqwest::Client, server: &TestServer) -> (i64, String) {
+    let resp = client
+        .get(format!("{}/SyncClipboard.json", server.base_url))
+        .send()
+        .await
+        .unwrap();
+    assert!(resp.status().is_success());
+    let id = resp.headers()["X-Clipboard-Id"].to_str().unwrap().parse().unwrap();
+    match resp.json::<ClipboardData>().await.unwrap() {
+        ClipboardData::Text { content, .. } => (id, content),
+        _ => panic!("Wrong data type"),
+    }
+}
+
+/// 获取当前剪贴板的状态码与 ID，用于最新条目已销毁或过期的情况
+async fn fetch_status(client: &reqwest::Client, server: &TestServer) -> (u16, i64) {
+    let resp = client
+        .get(format!("{}/SyncClipboard.json", server.base_url))
+        .send()
+        .await
+        .unwrap();
+    let id = resp.headers()["X-Clipboard-Id"].to_str().unwrap().parse().unwrap();
+    (resp.status().as_u16(), id)
+}
+
+async fn history(server: &TestServer) -> Vec<Value> {
+    server.client()
+        .get(format!("{}/history", server.base_url))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_sensitive_entry_stays_out_of_history_and_disk() {
+    let server = TestServer::new().await;
+    let client = device("laptop");
+    put(&client, &server, &ClipboardData::new_text("shopping list".to_string())).await;
+    put(&client, &server, &ClipboardData::new_text("hunter2-secret".to_string()).into_sensitive()).await;
+
+    let (_, content) = fetch(&device("phone"), &server).await;
+    assert_eq!(content, "hunter2-secret");
+
+    let items = history(&server).await;
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0]["content"], "shopping list");
+
+    for suffix in ["", "-wal"] {
+        if let Ok(bytes) = std::fs::read(format!("{}{}", server.db_path, suffix)) {
+            assert!(!bytes.windows(14).any(|w| w == b"hunter2-secret"), "secret written to {}{}", server.db_path, suffix);
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_sensitive_entry_burns_after_every_device_fetched() {
+    let server = TestServer::new().await;
+    let (laptop, phone, tablet) = (device("laptop"), device("phone"), device("tablet"));
+    put(&laptop, &server, &ClipboardData::new_text("before".to_string())).await;
+
+    // Register the sync clients before the secret is uploaded
+    for client in [&laptop, &phone, &tablet] {
+        fetch(client, &server).await;
+    }
+
+    put(&laptop, &server, &ClipboardData::new_text("one-time password".to_string()).into_sensitive()).await;
+
+    // The uploader reading its own entry does not count as delivery
+    let (secret_id, content) = fetch(&laptop, &server).await;
+    assert_eq!(content, "one-time password");
+    assert_eq!(fetch(&phone, &server).await.1, "one-time password");
+    assert_eq!(fetch(&phone, &server).await.1, "one-time password");
+    assert_eq!(fetch(&tablet, &server).await.1, "one-time password");
+
+    // Destroyed: the ID stays the same and no older content is served, so clients do not roll back
+    assert_eq!(fetch_status(&phone, &server).await, (204, secret_id));
+    assert_eq!(fetch_status(&device("late-device"), &server).await, (204, secret_id));
+    assert_eq!(history(&server).await[0]["content"], "before");
+}
+
+#[tokio::test]
+async fn test_entries_expire_after_ttl() {
+    let server = TestServer::new().await;
+    let client = device("laptop");
+    put(&client, &server, &ClipboardData::new_text("permanent".to_string())).await;
+    put(&client, &server, &ClipboardData::new_text("short lived".to_string()).with_ttl(Some(1))).await;
+    let (expiring_id, content) = fetch(&client, &server).await;
+    assert_eq!(content, "short lived");
+    assert_eq!(history(&server).await.len(), 2);
+
+    tokio::time::sleep(tokio::time::Duration::from_millis(2100)).await;
+    assert_eq!(fetch_status(&client, &server).await, (204, expiring_id));
+    assert_eq!(history(&server).await.len(), 1);
+
+    put(&client, &server, &ClipboardData::new_text("secret".to_string()).into_sensitive().with_ttl(Some(1))).await;
+    let (secret_id, content) = fetch(&client, &server).await;
+    assert_eq!(content, "secret");
+    tokio::time::sleep(tokio::time::Duration::from_millis(1100)).await;
+    assert_eq!(fetch_status(&device("phone"), &server).await, (204, secret_id));
+
+    // A new entry is served as usual
+    put(&client, &server, &ClipboardData::new_text("fresh".to_string())).await;
+    assert_eq!(fetch(&device("phone"), &server).await.1, "fresh");
+}
+
+#[tokio::test]
+async fn test_burned_image_is_removed_from_uploads() {
+    let server = TestServer::new().await;
+    let remote = |id: &str| {
+        let config = RemoteConfig {
+            name: "server".to_string(),
+            url: server.base_url.clone(),
+            token: None,
+            encrypt_password: None,
+            direction: Default::default(),
+            route: Default::default(),
+            tls: Default::default(),
+            server_id: None,
+        };
+        Remote::new(&config, id, id).unwrap()
+    };
+    // Both clients run on the same host and are told apart by the device ID they send
+    let (laptop, phone) = (remote("laptop"), remote("phone"));
+
+    let filename = format!("secret-{}.png", server.port);
+    laptop
+        .upload_image(filename.clone(), vec![1, 2, 3], "secret-hash".to_string(), Some(&ConcealedConfig::default()))
+        .await
+        .unwrap();
+    let blob = std::path::Path::new("./uploads").join(&filename);
+    assert!(blob.exists());
+
+    let secret_id = match phone.download(0, -1).await.unwrap() {
+        (Some(ClipboardData::Image { filename: name, sensitive, .. }), id) if name == filename && sensitive => id,
+        other => panic!("Unexpected response: {:?}", other),
+    };
+    for _ in 0..20 {
+        if !blob.exists() {
+            // The client keeps the burned entry's ID and gets no content
+            assert_eq!(phone.download(0, -1).await.unwrap(), (None, secret_id));
+            return;
+        }
+        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+    }
+    panic!("{} was not removed after every device fetched it", blob.display());
+}
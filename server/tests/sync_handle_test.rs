@@ -0,0 +1,273 @@
+mod common;
+
+use clipboard_core::clipboard::ClipboardData;
+use clipboard_core::clipboard_handler::ClipboardHandler;
+use clipboard_core::config::SyncMode;
+use clipboard_core::sync::{SyncHandle, SyncManager, SyncState};
+use clipboard_rs::{Clipboard, ClipboardContent, ContentFormat, RustImageData};
+use common::TestServer;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// 内存中的剪贴板，只支持文本与 HTML
+#[derive(Clone, Default)]
+struct MemoryClipboard {
+    contents: Arc<Mutex<(String, String)>>,
+}
+
+impl MemoryClipboard {
+    fn text(&self) -> String {
+        self.contents.lock().unwrap().0.clone()
+    }
+
+    fn copy(&self, text: &str) {
+        *self.contents.lock().unwrap() = (text.to_string(), String::new());
+    }
+}
+
+fn unsupported<T>() -> clipboard_rs::Result<T> {
+    Err("unsupported by the memory clipboard".into())
+}
+
+impl Clipboard for MemoryClipboard {
+    fn available_formats(&self) -> clipboard_rs::Result<Vec<String>> {
+        Ok(Vec::new())
+    }
+
+    fn has(&self, format: ContentFormat) -> bool {
+        matches!(format, ContentFormat::Text) && !self.text().is_empty()
+    }
+
+    fn clear(&self) -> clipboard_rs::Result<()> {
+        self.copy("");
+        Ok(())
+    }
+
+    fn get_buffer(&self, _format: &str) -> clipboard_rs::Result<Vec<u8>> {
+        unsupported()
+    }
+
+    fn get_text(&self) -> clipboard_rs::Result<String> {
+        Ok(self.text())
+    }
+
+    fn get_rich_text(&self) -> clipboard_rs::Result<String> {
+        unsupported()
+    }
+
+    fn get_html(&self) -> clipboard_rs::Result<String> {
+        Ok(self.contents.lock().unwrap().1.clone())
+    }
+
+    fn get_image(&self) -> clipboard_rs::Result<RustImageData> {
+        unsupported()
+    }
+
+    fn get_files(&self) -> clipboard_rs::Result<Vec<String>> {
+        unsupported()
+    }
+
+    fn get(&self, _formats: &[ContentFormat]) -> clipboard_rs::Result<Vec<ClipboardContent>> {
+        unsupported()
+    }
+
+    fn set_buffer(&self, _format: &str, _buffer: Vec<u8>) -> clipboard_rs::Result<()> {
+        unsupported()
+    }
+
+    fn set_text(&self, text: String) -> clipboard_rs::Result<()> {
+        self.contents.lock().unwrap().0 = text;
+        Ok(())
+    }
+
+    fn set_rich_text(&self, _text: String) -> clipboard_rs::Result<()> {
+        unsupported()
+    }
+
+    fn set_html(&self, html: String) -> clipboard_rs::Result<()> {
+        self.contents.lock().unwrap().1 = html;
+        Ok(())
+    }
+
+    fn set_image(&self, _image: RustImageData) -> clipboard_rs::Result<()> {
+        unsupported()
+    }
+
+    fn set_files(&self, _files: Vec<String>) -> clipboard_rs::Result<()> {
+        unsupported()
+    }
+
+    fn set(&self, _contents: Vec<ClipboardContent>) -> clipboard_rs::Result<()> {
+        unsupported()
+    }
+}
+
+/// 启动连接到 `server` 的同步客户端，返回控制句柄与运行任务
+fn start_client(
+    server: &TestServer,
+    clipboard: &MemoryClipboard,
+    mode: SyncMode,
+) -> (SyncHandle, tokio::task::JoinHandle<()>) {
+    let mut config = common::test_config(server.port, String::new());
+    config.client.push_mode = mode;
+    let handler = Arc::new(ClipboardHandler::with_backend(Box::new(clipboard.clone())));
+    let manager = SyncManager::new(&config, handler);
+    let handle = manager.handle();
+    let task = tokio::spawn(async move { manager.run().await });
+    (handle, task)
+}
+
+/// 服务端当前的文本，没有条目时为 `None`
+async fn server_text(server: &TestServer) -> Option<String> {
+    let response = server.client()
+        .get(format!("{}/SyncClipboard.json", server.base_url))
+        .send()
+        .await
+        .unwrap();
+    match response.json::<ClipboardData>().await {
+        Ok(ClipboardData::Text { content, .. }) => Some(content),
+        _ => None,
+    }
+}
+
+async fn wait_for_server_text(server: &TestServer, text: &str) {
+    let mut current = None;
+    for _ in 0..40 {
+        current = server_text(server).await;
+        if current.as_deref() == Some(text) {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("server has {:?} instead of '{}'", current, text);
+}
+
+async fn put_server_text(server: &TestServer, text: &str) {
+    let response = server.client()
+        .put(format!("{}/SyncClipboard.json", server.base_url))
+        .json(&ClipboardData::new_text(text.to_string()))
+        .send()
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+}
+
+#[tokio::test]
+async fn test_paused_client_does_not_upload() {
+    let server = TestServer::new().await;
+    let clipboard = MemoryClipboard::default();
+    clipboard.copy("before pause");
+    let (handle, task) = start_client(&server, &clipboard, SyncMode::Auto);
+    wait_for_server_text(&server, "before pause").await;
+
+    handle.pause();
+    assert_eq!(handle.status().state, SyncState::Paused);
+    clipboard.copy("copied while paused");
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    assert_eq!(server_text(&server).await.as_deref(), Some("before pause"));
+
+    // Resuming does not upload what was copied in the meantime
+    handle.resume();
+    assert_eq!(handle.status().state, SyncState::Running);
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    assert_eq!(server_text(&server).await.as_deref(), Some("before pause"));
+
+    // An explicit push still works after resuming
+    handle.push_now().await.unwrap();
+    assert_eq!(server_text(&server).await.as_deref(), Some("copied while paused"));
+
+    handle.stop();
+    tokio::time::timeout(Duration::from_secs(5), task).await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn test_manual_mode_uploads_only_on_push() {
+    let server = TestServer::new().await;
+    let clipboard = MemoryClipboard::default();
+    clipboard.copy("manual entry");
+    let (handle, task) = start_client(&server, &clipboard, SyncMode::Manual);
+
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    assert_eq!(server_text(&server).await, None);
+
+    handle.push_now().await.unwrap();
+    assert_eq!(server_text(&server).await.as_deref(), Some("manual entry"));
+
+    // Downloads stay automatic in manual mode
+    put_server_text(&server, "from server").await;
+    for _ in 0..40 {
+        if clipboard.text() == "from server" {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert_eq!(clipboard.text(), "from server");
+
+    // A local edit is neither uploaded nor kept by an explicit pull
+    clipboard.copy("local edit");
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    assert_eq!(server_text(&server).await.as_deref(), Some("from server"));
+    handle.pull_now().await.unwrap();
+    assert_eq!(clipboard.text(), "from server");
+
+    handle.stop();
+    tokio::time::timeout(Duration::from_secs(5), task).await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn test_stopped_client_rejects_requests() {
+    let server = TestServer::new().await;
+    let clipboard = MemoryClipboard::default();
+    let (handle, task) = start_client(&server, &clipboard, SyncMode::Auto);
+
+    handle.stop();
+    tokio::time::timeout(Duration::from_secs(5), task).await.unwrap().unwrap();
+    assert_eq!(handle.status().state, SyncState::Stopped);
+
+    // A stopped client cannot be paused or resumed again
+    handle.pause();
+    handle.resume();
+    assert_eq!(handle.status().state, SyncState::Stopped);
+    let error = handle.push_now().await.unwrap_err();
+    assert!(error.to_string().contains("stopped"), "{}", error);
+    assert!(handle.pull_now().await.is_err());
+}
+
+#[tokio::test]
+async fn test_handle_outliving_manager_reports_not_running() {
+    let config = common::test_config(1, String::new());
+    let handler = Arc::new(ClipboardHandler::with_backend(Box::new(MemoryClipboard::default())));
+    let manager = SyncManager::new(&config, handler);
+    let handle = manager.handle();
+    drop(manager);
+
+    assert_eq!(handle.status().state, SyncState::Running);
+    let error = handle.push_now().await.unwrap_err();
+    assert!(error.to_string().contains("not running"), "{}", error);
+}
+
+#[tokio::test]
+async fn test_upload_text_applies_filters() {
+    let server = TestServer::new().await;
+    let mut config = common::test_config(server.port, String::new());
+    config.filters.deny_patterns = vec![r"\bsk_[A-Za-z0-9]{20,}\b".to_string()];
+    config.filters.max_text_bytes = 64;
+    config.filters.min_interval_ms = 60_000;
+    let handler = Arc::new(ClipboardHandler::with_backend(Box::new(MemoryClipboard::default())));
+    let manager = SyncManager::new(&config, handler);
+
+    let error = manager.upload_text("key sk_abcdefghijklmnopqrstuvwxyz".to_string(), None).await.unwrap_err();
+    assert!(error.to_string().contains("deny pattern"), "{}", error);
+    let error = manager.upload_text("x".repeat(65), None).await.unwrap_err();
+    assert!(error.to_string().contains("limit is 64"), "{}", error);
+    let error = manager.upload_text("see html".to_string(), Some("<p>sk_abcdefghijklmnopqrstuvwxyz</p>".to_string())).await.unwrap_err();
+    assert!(error.to_string().contains("deny pattern"), "{}", error);
+    assert_eq!(server_text(&server).await, None);
+
+    // Blocked uploads don't count towards the interval, the first allowed one does
+    manager.upload_text("first".to_string(), None).await.unwrap();
+    assert_eq!(server_text(&server).await.as_deref(), Some("first"));
+    let error = manager.upload_text("second".to_string(), None).await.unwrap_err();
+    assert!(error.to_string().contains("less than"), "{}", error);
+    assert_eq!(server_text(&server).await.as_deref(), Some("first"));
+}
@@ -1,41 +1,16 @@
-use clipboard_core::config::{Config, ServerConfig, AuthConfig, TlsConfig, ClientConfig, GeneralConfig};
+mod common;
+
+use clipboard_core::clipboard::ClipboardData;
+use clipboard_core::config::{TlsConfig, ClientTlsConfig, RemoteConfig};
+use clipboard_core::remote::Remote;
+use clipboard_core::tls::KnownServers;
 use std::time::Duration;
 use tokio::time::sleep;
 use std::process::Command;
 
-async fn start_test_server(port: u16, cert_path: String, key_path: String) {
-    let config = Config {
-        server: ServerConfig {
-            port,
-            host: "127.0.0.1".to_string(),
-            wevdav_enabled: false,
-            tls: Some(TlsConfig {
-                cert: cert_path,
-                key: key_path,
-            }),
-            enabled: true,
-        },
-        client: ClientConfig {
-            enabled: false,
-            remote_host: "127.0.0.1".to_string(),
-            remote_port: port,
-        },
-        auth: AuthConfig {
-            username: None,
-            password: None,
-            token: None,
-            encrypt_password: None,
-        },
-        history: clipboard_core::config::HistoryConfig {
-            max_count: 100,
-            log_retention_days: 7,
-            db_path: "test_tls.db".to_string(),
-        },
-        general: GeneralConfig {
-            device_name: "TestTLS".to_string(),
-            device_id: "test-tls".to_string(),
-        }
-    };
+async fn start_test_server(port: u16, tls: TlsConfig) {
+    let mut config = common::test_config(port, format!("test_tls_{}.db", port));
+    config.server.tls = Some(tls);
     
     // Server runs indefinitely, so we spawn it
     tokio::spawn(async move {
@@ -68,7 +43,7 @@ async fn test_tls_connection() {
     }
 
     let port = 5094;
-    start_test_server(port, cert_path.to_string(), key_path.to_string()).await;
+    start_test_server(port, TlsConfig { cert: cert_path.to_string(), key: key_path.to_string(), self_signed: false }).await;
     
     // Client that accepts invalid certs (since it's self-signed)
     let client = reqwest::Client::builder()
@@ -95,3 +70,165 @@ async fn test_tls_connection() {
     let _ = std::fs::remove_file(cert_path);
     let _ = std::fs::remove_file(key_path);
 }
+
+/// Self-signed certificate valid for 127.0.0.1, usable as its own CA
+fn generate_cert(cert_path: &str, key_path: &str) {
+    let _ = std::fs::remove_file(cert_path);
+    let _ = std::fs::remove_file(key_path);
+    let status = Command::new("openssl")
+        .args([
+            "req", "-x509", "-newkey", "rsa:2048", "-keyout", key_path, "-out", cert_path, "-days", "1", "-nodes",
+            "-subj", "/CN=localhost",
+            "-addext", "subjectAltName=IP:127.0.0.1,DNS:localhost",
+            "-addext", "basicConstraints=critical,CA:FALSE",
+        ])
+        .output()
+        .expect("Failed to run openssl. Is it installed?");
+    if !status.status.success() {
+        panic!("OpenSSL failed: {}", String::from_utf8_lossy(&status.stderr));
+    }
+}
+
+/// SHA-256 fingerprint as printed by `openssl x509 -fingerprint`
+fn cert_fingerprint(cert_path: &str) -> String {
+    let output = Command::new("openssl")
+        .args(["x509", "-in", cert_path, "-noout", "-fingerprint", "-sha256"])
+        .output()
+        .expect("Failed to run openssl");
+    let line = String::from_utf8(output.stdout).unwrap();
+    line.trim().split_once('=').unwrap().1.to_string()
+}
+
+fn https_remote(port: u16, tls: ClientTlsConfig) -> Result<Remote, clipboard_core::tls::TlsError> {
+    Remote::new(
+        &RemoteConfig {
+            name: "tls".to_string(),
+            url: format!("https://127.0.0.1:{}", port),
+            token: None,
+            encrypt_password: None,
+            direction: Default::default(),
+            route: Default::default(),
+            tls,
+            server_id: None,
+        },
+        "TLSClient",
+        "tls-client",
+    )
+}
+
+async fn assert_round_trip(remote: &Remote, text: &str) {
+    remote.upload_text(text.to_string(), None, None).await.unwrap();
+    match remote.download(0, -1).await.unwrap() {
+        (Some(ClipboardData::Text { content, device, .. }), _) => {
+            assert_eq!(content, text);
+            assert_eq!(device.as_deref(), Some("TLSClient"));
+        }
+        other => panic!("Unexpected response: {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_https_round_trip_with_custom_ca() {
+    let (cert_path, key_path) = ("test_ca_cert.pem", "test_ca_key.pem");
+    generate_cert(cert_path, key_path);
+    let port = 5095;
+    start_test_server(port, TlsConfig { cert: cert_path.to_string(), key: key_path.to_string(), self_signed: false }).await;
+
+    // The system roots do not know the self-signed certificate
+    let untrusted = https_remote(port, ClientTlsConfig::default()).unwrap();
+    assert!(untrusted.upload_text("rejected".to_string(), None, None).await.is_err());
+
+    let trusted = https_remote(port, ClientTlsConfig { ca_cert: Some(cert_path.to_string()), ..Default::default() }).unwrap();
+    assert_round_trip(&trusted, "over https").await;
+
+    assert!(https_remote(port, ClientTlsConfig { ca_cert: Some("missing.pem".to_string()), ..Default::default() }).is_err());
+
+    let _ = std::fs::remove_file(cert_path);
+    let _ = std::fs::remove_file(key_path);
+}
+
+#[tokio::test]
+async fn test_https_certificate_pinning() {
+    let (cert_path, key_path) = ("test_pin_cert.pem", "test_pin_key.pem");
+    generate_cert(cert_path, key_path);
+    let port = 5096;
+    start_test_server(port, TlsConfig { cert: cert_path.to_string(), key: key_path.to_string(), self_signed: false }).await;
+    let fingerprint = cert_fingerprint(cert_path);
+
+    // A pinned certificate is accepted without any CA, lower case and no colons work too
+    let pinned = https_remote(port, ClientTlsConfig {
+        fingerprint: Some(fingerprint.replace(':', "").to_lowercase()),
+        ..Default::default()
+    }).unwrap();
+    assert_round_trip(&pinned, "pinned").await;
+
+    let wrong = https_remote(port, ClientTlsConfig { fingerprint: Some("AB".repeat(32)), ..Default::default() }).unwrap();
+    assert!(wrong.upload_text("rejected".to_string(), None, None).await.is_err());
+
+    assert!(https_remote(port, ClientTlsConfig { fingerprint: Some("not-hex".to_string()), ..Default::default() }).is_err());
+
+    // Trust on first use records the fingerprint, then only accepts the same certificate
+    let store = "test_known_servers.json";
+    let _ = std::fs::remove_file(store);
+    let tofu = ClientTlsConfig { trust_on_first_use: true, known_servers: store.to_string(), ..Default::default() };
+    let first = https_remote(port, tofu.clone()).unwrap();
+    assert_round_trip(&first, "first use").await;
+    let server = format!("127.0.0.1:{}", port);
+    assert_eq!(KnownServers::new(store).get(&server), Some(fingerprint));
+
+    let again = https_remote(port, tofu.clone()).unwrap();
+    assert_round_trip(&again, "second use").await;
+
+    // Simulate the server presenting a different certificate than the recorded one
+    std::fs::write(store, format!(r#"{{"{}": "{}"}}"#, server, "00".repeat(32))).unwrap();
+    let changed = https_remote(port, tofu).unwrap();
+    assert!(changed.upload_text("rejected".to_string(), None, None).await.is_err());
+
+    let _ = std::fs::remove_file(store);
+    let _ = std::fs::remove_file(cert_path);
+    let _ = std::fs::remove_file(key_path);
+}
+
+async fn discovery_fingerprint(port: u16) -> Option<String> {
+    let client = reqwest::Client::builder().danger_accept_invalid_certs(true).build().unwrap();
+    let info: serde_json::Value = client
+        .get(format!("https://127.0.0.1:{}/api/discovery", port))
+        .send().await.unwrap()
+        .json().await.unwrap();
+    info["tls_fingerprint"].as_str().map(str::to_string)
+}
+
+#[tokio::test]
+async fn test_self_signed_certificate_and_hot_reload() {
+    let dir = tempfile::tempdir().unwrap();
+    let cert_path = dir.path().join("cert.pem").to_str().unwrap().to_string();
+    let key_path = dir.path().join("key.pem").to_str().unwrap().to_string();
+    let port = 5097;
+    start_test_server(port, TlsConfig { cert: cert_path.clone(), key: key_path.clone(), self_signed: true }).await;
+
+    // The generated certificate is advertised for pinning
+    let fingerprint = discovery_fingerprint(port).await.expect("fingerprint in discovery info");
+    assert_eq!(fingerprint, cert_fingerprint(&cert_path));
+    let old = https_remote(port, ClientTlsConfig { fingerprint: Some(fingerprint.clone()), ..Default::default() }).unwrap();
+    assert_round_trip(&old, "self-signed").await;
+
+    // Replace the certificate on disk, the server picks it up without a restart
+    generate_cert(&cert_path, &key_path);
+    let rotated = cert_fingerprint(&cert_path);
+    let mut reloaded = None;
+    for _ in 0..30 {
+        sleep(Duration::from_millis(500)).await;
+        if discovery_fingerprint(port).await.as_deref() == Some(rotated.as_str()) {
+            reloaded = Some(rotated.clone());
+            break;
+        }
+    }
+    assert_eq!(reloaded, Some(rotated.clone()), "certificate was not reloaded");
+
+    // The already established connection keeps working, new connections get the new certificate
+    assert_round_trip(&old, "kept alive").await;
+    let stale = https_remote(port, ClientTlsConfig { fingerprint: Some(fingerprint), ..Default::default() }).unwrap();
+    assert!(stale.upload_text("rejected".to_string(), None, None).await.is_err());
+    let fresh = https_remote(port, ClientTlsConfig { fingerprint: Some(rotated), ..Default::default() }).unwrap();
+    assert_round_trip(&fresh, "rotated").await;
+}
@@ -1,16 +1,32 @@
-import { useState, useEffect } from 'react';
+import { useState, useEffect, Fragment } from 'react';
 import { invoke } from '@tauri-apps/api/core';
-import type { HistoryItem, Config } from '../types';
-import { FileText, Image, File, Copy, Check, ExternalLink, Trash2, Pin, PinOff, XCircle } from 'lucide-react';
+import type { HistoryItem, HistoryQuery, Config } from '../types';
+import { FileText, Image, File, Copy, Check, ExternalLink, Trash2, Pin, PinOff, XCircle, Search } from 'lucide-react';
 import { cn } from '../lib/utils';
 import { useTranslation } from 'react-i18next';
 import { Button } from "./ui/button";
+import { Input } from "./ui/input";
+
+// Render a search snippet, turning the backend's <mark> delimiters into highlights
+// without interpreting any other markup in the clipboard content.
+function Snippet({ text }: { text: string }) {
+    const parts = text.split(/<mark>|<\/mark>/);
+    return (
+        <>
+            {parts.map((part, i) => i % 2 === 1
+                ? <mark key={i} className="bg-primary/20 text-foreground rounded-sm">{part}</mark>
+                : <Fragment key={i}>{part}</Fragment>
+            )}
+        </>
+    );
+}
 
 function History() {
     const { t } = useTranslation();
     const [history, setHistory] = useState<HistoryItem[]>([]);
     const [copiedId, setCopiedId] = useState<number | null>(null);
     const [config, setConfig] = useState<Config | null>(null);
+    const [search, setSearch] = useState('');
 
     // Fetch config to get server host/port for image preview
     useEffect(() => {
@@ -19,8 +35,11 @@ function History() {
 
     const fetchHistory = async () => {
         try {
-            const items = await invoke<HistoryItem[]>('get_history');
-            setHistory(items.sort((a, b) => b.id - a.id));
+            const q = search.trim();
+            const query: HistoryQuery | null = q ? { q } : null;
+            const items = await invoke<HistoryItem[]>('get_history', { query });
+            // Search results are already ordered by relevance
+            setHistory(q ? items : items.sort((a, b) => b.id - a.id));
         } catch (err) {
             console.error("Failed to fetch history:", err);
         }
@@ -30,7 +49,7 @@ function History() {
         fetchHistory();
         const interval = setInterval(fetchHistory, 2000);
         return () => clearInterval(interval);
-    }, []);
+    }, [search]);
 
     const handleCopy = (item: HistoryItem) => {
         if (item.type === 'Text' && item.content) {
@@ -99,14 +118,15 @@ function History() {
     );
 
 
-    if (history.length === 0) {
+    if (history.length === 0 && !search.trim()) {
         return <EmptyState />;
     }
 
     // Construct Image URL
     const getImageUrl = (filename: string) => {
         if (!config) return '';
-        const host = config.server.host === '0.0.0.0' ? '127.0.0.1' : config.server.host;
+        const { host: bind } = config.server;
+        const host = bind === '0.0.0.0' || bind === '::' ? '127.0.0.1' : bind.includes(':') ? `[${bind}]` : bind;
         return `http://${host}:${config.server.port}/file/${filename}`;
     };
 
@@ -125,7 +145,20 @@ function History() {
                 </Button>
             </div>
 
+            <div className="relative px-1">
+                <Search size={14} className="absolute left-4 top-1/2 -translate-y-1/2 text-muted-foreground" />
+                <Input
+                    value={search}
+                    onChange={(e) => setSearch(e.target.value)}
+                    placeholder={t('history.search_placeholder', 'Search history...')}
+                    className="h-9 pl-8"
+                />
+            </div>
+
             <div className="space-y-3 pb-8 overflow-y-auto pr-1">
+                {history.length === 0 && (
+                    <p className="text-sm text-center text-muted-foreground py-8">{t('history.no_results', 'No matching items')}</p>
+                )}
                 {history.map((item) => (
                     <div
                         key={item.id}
@@ -187,7 +220,7 @@ function History() {
 
                         {item.type === 'Text' ? (
                             <p className="text-sm font-mono break-words leading-relaxed selection:bg-primary/20 line-clamp-[10]">
-                                {item.content || <span className="text-muted-foreground italic">{t('history.empty_content', 'Empty Content')}</span>}
+                                {item.snippet ? <Snippet text={item.snippet} /> : item.content || <span className="text-muted-foreground italic">{t('history.empty_content', 'Empty Content')}</span>}
                             </p>
                         ) : item.type === 'Image' && item.file ? (
                             <div className="mt-2 relative group/image max-w-sm">
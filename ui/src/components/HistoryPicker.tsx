@@ -0,0 +1,111 @@
+import { useState, useEffect, useRef, type KeyboardEvent } from 'react';
+import { invoke } from '@tauri-apps/api/core';
+import type { HistoryItem, HistoryQuery } from '../types';
+import { Search } from 'lucide-react';
+import { cn } from '../lib/utils';
+import { useTranslation } from 'react-i18next';
+import { Input } from "./ui/input";
+
+// Compact keyboard-driven history list shown in the "picker" window (opened by the global hotkey).
+function HistoryPicker() {
+    const { t } = useTranslation();
+    const [items, setItems] = useState<HistoryItem[]>([]);
+    const [search, setSearch] = useState('');
+    const [selected, setSelected] = useState(0);
+    const inputRef = useRef<HTMLInputElement>(null);
+
+    const fetchItems = async () => {
+        try {
+            const q = search.trim();
+            const query: HistoryQuery = { type: 'Text', limit: 30, ...(q ? { q } : {}) };
+            setItems(await invoke<HistoryItem[]>('get_history', { query }));
+            setSelected(0);
+        } catch (err) {
+            console.error("Failed to fetch history:", err);
+        }
+    };
+
+    useEffect(() => {
+        fetchItems();
+    }, [search]);
+
+    // The window is hidden rather than closed, so refresh and refocus whenever it is shown again
+    useEffect(() => {
+        const onFocus = () => {
+            fetchItems();
+            inputRef.current?.focus();
+        };
+        window.addEventListener('focus', onFocus);
+        return () => window.removeEventListener('focus', onFocus);
+    }, [search]);
+
+    const pick = async (item: HistoryItem | undefined) => {
+        if (!item) return;
+        try {
+            await invoke('copy_history_item', { id: item.id });
+            setSearch('');
+        } catch (err) {
+            console.error("Failed to copy item:", err);
+        }
+    };
+
+    const handleKeyDown = (e: KeyboardEvent) => {
+        switch (e.key) {
+            case 'ArrowDown':
+                e.preventDefault();
+                setSelected(i => Math.min(i + 1, items.length - 1));
+                break;
+            case 'ArrowUp':
+                e.preventDefault();
+                setSelected(i => Math.max(i - 1, 0));
+                break;
+            case 'Enter':
+                e.preventDefault();
+                pick(items[selected]);
+                break;
+            case 'Escape':
+                invoke('close_history_picker');
+                break;
+        }
+    };
+
+    return (
+        <div className="h-screen flex flex-col gap-2 p-3 bg-background text-foreground" onKeyDown={handleKeyDown}>
+            <div className="relative">
+                <Search size={14} className="absolute left-3 top-1/2 -translate-y-1/2 text-muted-foreground" />
+                <Input
+                    ref={inputRef}
+                    autoFocus
+                    value={search}
+                    onChange={(e) => setSearch(e.target.value)}
+                    placeholder={t('history.search_placeholder', 'Search history...')}
+                    className="h-9 pl-8"
+                />
+            </div>
+
+            <div className="flex-1 overflow-y-auto space-y-1">
+                {items.length === 0 && (
+                    <p className="text-sm text-center text-muted-foreground py-8">{t('history.no_results', 'No matching items')}</p>
+                )}
+                {items.map((item, i) => (
+                    <button
+                        key={item.id}
+                        onClick={() => pick(item)}
+                        onMouseEnter={() => setSelected(i)}
+                        ref={el => { if (i === selected) el?.scrollIntoView({ block: 'nearest' }); }}
+                        className={cn(
+                            "w-full text-left px-3 py-2 rounded-md text-sm truncate border",
+                            i === selected ? "bg-primary/10 border-primary/30" : "border-transparent"
+                        )}
+                    >
+                        {item.content || t('history.empty_content', 'Empty Content')}
+                    </button>
+                ))}
+            </div>
+
+            <p className="text-[10px] text-center text-muted-foreground">{t('history.picker_hint', '↑↓ to select, Enter to copy, Esc to close')}</p>
+        </div>
+    );
+}
+
+export default HistoryPicker;
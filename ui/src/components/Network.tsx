@@ -1,10 +1,12 @@
 import { useState, useEffect } from 'react';
 import { invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
 import { Wifi, Monitor, Smartphone, RefreshCw, Copy, Check } from 'lucide-react';
 import { Card, CardContent, CardDescription, CardHeader, CardTitle } from "./ui/card";
 import { Button } from "./ui/button";
 import { cn } from '../lib/utils';
 import { useTranslation } from 'react-i18next';
+import type { SyncDirection } from '../types';
 
 interface InterfaceInfo {
     name: string;
@@ -18,10 +20,25 @@ interface NetworkInfo {
 }
 
 interface LanDevice {
+    id?: string; // Only devices found by LAN discovery
     name: string;
     ip: string;
     port: number;
     last_active?: number; // Unix timestamp
+    sync_direction?: SyncDirection;
+}
+
+interface DiscoveredDevice {
+    id: string;
+    name: string;
+    ip: string;
+    port: number;
+}
+
+// Pushed by the backend whenever the discovery cache changes
+interface DiscoveryEvent {
+    kind: 'device_added' | 'device_updated' | 'device_restarted' | 'device_expired';
+    device: DiscoveredDevice;
 }
 
 function Network() {
@@ -44,12 +61,12 @@ function Network() {
     const scanLanDevices = async () => {
         setLoading(true);
         try {
-            // 同时获取mDNS扫描设备和已连接设备
-            const [mdnsDevices, connected] = await Promise.all([
-                invoke<LanDevice[]>('get_lan_devices'),
+            // 已发现的设备立即返回，之后的变化通过 discovery-event 推送
+            const [discovered, connected] = await Promise.all([
+                invoke<LanDevice[]>('scan_lan_devices'),
                 invoke<LanDevice[]>('get_connected_clients')
             ]);
-            setLanDevices(mdnsDevices);
+            setLanDevices(discovered);
             setConnectedClients(connected);
         } catch (err) {
             console.error("Failed to scan devices:", err);
@@ -59,8 +76,23 @@ function Network() {
     };
 
     useEffect(() => {
+        const unlisten = listen<DiscoveryEvent>('discovery-event', ({ payload }) => {
+            const { id, name, ip, port } = payload.device;
+            setLanDevices((devices) => {
+                if (payload.kind === 'device_expired') {
+                    return devices.filter((device) => device.id !== id);
+                }
+                const updated = { id, name, ip, port };
+                return devices.some((device) => device.id === id)
+                    ? devices.map((device) => (device.id === id ? updated : device))
+                    : [...devices, updated];
+            });
+        });
         fetchNetworkInfo();
         scanLanDevices();
+        return () => {
+            unlisten.then((stop) => stop());
+        };
     }, []);
 
     const handleCopyIp = (ip: string) => {
@@ -157,12 +189,19 @@ function Network() {
                         <div className="space-y-3">
                             {lanDevices.map((device, idx) => (
                                 <div
-                                    key={idx}
+                                    key={device.id ?? idx}
                                     className="bg-secondary border border-border rounded-lg p-4 hover:border-primary/50 transition-colors"
                                 >
                                     <div className="flex items-center justify-between">
                                         <div>
-                                            <p className="font-medium">{device.name}</p>
+                                            <p className="font-medium">
+                                                {device.name}
+                                                {device.sync_direction && device.sync_direction !== 'both' && (
+                                                    <span className="ml-2 text-[10px] bg-background px-1.5 py-0.5 rounded text-muted-foreground border border-border">
+                                                        {t(`settings.network.direction_${device.sync_direction}`)}
+                                                    </span>
+                                                )}
+                                            </p>
                                             <p className="text-sm text-muted-foreground font-mono">
                                                 {device.ip}:{device.port}
                                             </p>
@@ -1,6 +1,6 @@
 import { useState, useEffect } from 'react';
 import { invoke } from '@tauri-apps/api/core';
-import type { Config } from '../types';
+import type { ClientTlsConfig, ConcealedConfig, Config, HotkeyConfig, MeshConfig, RetentionConfig } from '../types';
 import { Save, Loader2 } from 'lucide-react';
 import { useTranslation } from 'react-i18next';
 
@@ -15,6 +15,44 @@ import { useTheme } from "./theme-provider"
 import i18n from "../i18n";
 import { cn } from "../lib/utils"
 
+const DEFAULT_RETENTION: RetentionConfig = {
+    text_days: 0,
+    image_days: 0,
+    file_days: 0,
+    keep_pinned: true,
+    max_total_mb: 0,
+    interval_secs: 3600,
+};
+
+const DEFAULT_CONCEALED: ConcealedConfig = {
+    mode: 'skip',
+    ttl_secs: 60,
+};
+
+const DEFAULT_CLIENT_TLS: ClientTlsConfig = {
+    ca_cert: null,
+    fingerprint: null,
+    trust_on_first_use: false,
+    known_servers: 'known_servers.json',
+};
+
+const DEFAULT_MESH: MeshConfig = {
+    enabled: false,
+    scheme: 'http',
+    tls: DEFAULT_CLIENT_TLS,
+    devices: [],
+};
+
+const DEFAULT_HOTKEYS: HotkeyConfig = {
+    enabled: false,
+    push: 'Ctrl+Alt+Up',
+    pull: 'Ctrl+Alt+Down',
+    toggle_pause: 'Ctrl+Alt+P',
+    history_picker: 'Ctrl+Alt+V',
+};
+
+const HOTKEY_ACTIONS = ['push', 'pull', 'toggle_pause', 'history_picker'] as const;
+
 interface SettingsProps {
     activeSection: string;
 }
@@ -23,7 +61,7 @@ function Settings({ activeSection }: SettingsProps) {
     const { t } = useTranslation();
     const { setTheme, theme } = useTheme();
     const [config, setConfig] = useState<Config>({
-        server: { host: '0.0.0.0', port: 5033, tls: null, enabled: true },
+        server: { host: '::', port: 5033, tls: null, enabled: true },
         client: { enabled: false, remote_host: '127.0.0.1', remote_port: 5033 },
         auth: { token: '', encrypt_password: '' },
         history: { max_count: 100, log_retention_days: 7 },
@@ -53,6 +91,30 @@ function Settings({ activeSection }: SettingsProps) {
         }));
     };
 
+    const updateRetention = (field: keyof RetentionConfig, value: number | boolean) => {
+        setConfig(prev => ({
+            ...prev,
+            history: {
+                ...prev.history,
+                retention: {
+                    ...DEFAULT_RETENTION,
+                    ...prev.history.retention,
+                    [field]: value
+                }
+            }
+        }));
+    };
+
+    const retention = { ...DEFAULT_RETENTION, ...config.history.retention };
+    const hotkeys = { ...DEFAULT_HOTKEYS, ...config.hotkeys };
+
+    const updateHotkey = (field: keyof HotkeyConfig, value: string | boolean) => {
+        setConfig(prev => ({
+            ...prev,
+            hotkeys: { ...DEFAULT_HOTKEYS, ...prev.hotkeys, [field]: value }
+        }));
+    };
+
     const handleSave = async () => {
         try {
             setStatus({ msg: 'Saving...', type: 'loading' });
@@ -61,7 +123,8 @@ function Settings({ activeSection }: SettingsProps) {
             setTimeout(() => setStatus({ msg: '', type: '' }), 3000);
         } catch (err) {
             console.error("Failed to save:", err);
-            setStatus({ msg: 'Error saving config', type: 'error' });
+            // Validation errors (port in use, invalid hotkeys) come back as readable strings
+            setStatus({ msg: typeof err === 'string' ? err : 'Error saving config', type: 'error' });
         }
     };
 
@@ -92,9 +155,9 @@ function Settings({ activeSection }: SettingsProps) {
                                     id="host"
                                     value={config.server.host || ''}
                                     onChange={e => updateConfig('server', 'host', e.target.value)}
-                                    placeholder="0.0.0.0"
+                                    placeholder="::"
                                 />
-                                <p className="text-[0.8rem] text-muted-foreground">{t('settings.server.host_desc', 'The IP address to bind to. 0.0.0.0 listens on all interfaces.')}</p>
+                                <p className="text-[0.8rem] text-muted-foreground">{t('settings.server.host_desc', 'The IP address to bind to. :: listens on all IPv4 and IPv6 interfaces, 0.0.0.0 on IPv4 only.')}</p>
                             </div>
                             <div className="grid gap-2">
                                 <Label htmlFor="port">{t('settings.server.port', 'Port')}</Label>
@@ -106,6 +169,17 @@ function Settings({ activeSection }: SettingsProps) {
                                     placeholder="5033"
                                 />
                             </div>
+                            <div className="flex items-center space-x-2 border-t border-border pt-4">
+                                <Switch
+                                    id="mesh-enabled"
+                                    checked={!!config.mesh?.enabled}
+                                    onCheckedChange={(checked) => setConfig(prev => ({ ...prev, mesh: { ...DEFAULT_MESH, ...prev.mesh, enabled: checked } }))}
+                                />
+                                <div className="flex flex-col">
+                                    <Label htmlFor="mesh-enabled">{t('settings.server.mesh', 'Peer-to-peer Sync')}</Label>
+                                    <span className="text-[0.8rem] text-muted-foreground">{t('settings.server.mesh_desc', 'Exchange clipboard entries directly with LAN devices that enable it too. All devices need the same token and encryption password.')}</span>
+                                </div>
+                            </div>
                         </CardContent>
                     </Card>
 
@@ -128,6 +202,26 @@ function Settings({ activeSection }: SettingsProps) {
                             </div>
                             {config.client.enabled && (
                                 <>
+                                    {!!config.client.remotes?.length && (
+                                        <p className="text-[0.8rem] text-muted-foreground">
+                                            {t('settings.client.remotes_hint', 'Syncing with {{count}} remotes from config.toml; the address below is not used.', { count: config.client.remotes.length })}
+                                        </p>
+                                    )}
+                                    <div className="grid gap-2">
+                                        <Label>{t('settings.client.scheme', 'Protocol')}</Label>
+                                        <Select
+                                            value={config.client.scheme ?? 'http'}
+                                            onValueChange={val => updateConfig('client', 'scheme', val)}
+                                        >
+                                            <SelectTrigger>
+                                                <SelectValue />
+                                            </SelectTrigger>
+                                            <SelectContent>
+                                                <SelectItem value="http">HTTP</SelectItem>
+                                                <SelectItem value="https">HTTPS</SelectItem>
+                                            </SelectContent>
+                                        </Select>
+                                    </div>
                                     <div className="grid gap-2">
                                         <Label htmlFor="remote-host">{t('settings.client.remote_host', 'Remote Server Address')}</Label>
                                         <Input
@@ -148,6 +242,63 @@ function Settings({ activeSection }: SettingsProps) {
                                             placeholder="5033"
                                         />
                                     </div>
+                                    {config.client.scheme === 'https' && (
+                                        <>
+                                            <div className="grid gap-2">
+                                                <Label htmlFor="tls-fingerprint">{t('settings.client.tls_fingerprint', 'Certificate Fingerprint (SHA-256)')}</Label>
+                                                <Input
+                                                    id="tls-fingerprint"
+                                                    value={config.client.tls?.fingerprint ?? ''}
+                                                    onChange={e => updateConfig('client', 'tls', { ...(config.client.tls ?? DEFAULT_CLIENT_TLS), fingerprint: e.target.value.trim() || null })}
+                                                    placeholder="AB:CD:..."
+                                                />
+                                                <p className="text-[0.8rem] text-muted-foreground">{t('settings.client.tls_fingerprint_desc', 'Only accept this certificate, e.g. a self-signed one. Leave empty to use system CAs.')}</p>
+                                            </div>
+                                            <div className="flex items-center justify-between">
+                                                <div className="space-y-0.5">
+                                                    <Label>{t('settings.client.tls_tofu', 'Trust on First Use')}</Label>
+                                                    <p className="text-[0.8rem] text-muted-foreground">{t('settings.client.tls_tofu_desc', 'Remember the certificate seen on the first connection and reject any other one later.')}</p>
+                                                </div>
+                                                <Switch
+                                                    checked={config.client.tls?.trust_on_first_use ?? false}
+                                                    onCheckedChange={c => updateConfig('client', 'tls', { ...(config.client.tls ?? DEFAULT_CLIENT_TLS), trust_on_first_use: c })}
+                                                />
+                                            </div>
+                                        </>
+                                    )}
+                                    <div className="grid gap-2">
+                                        <Label>{t('settings.client.direction', 'Sync Direction')}</Label>
+                                        <Select
+                                            value={config.client.mode ?? 'both'}
+                                            onValueChange={val => updateConfig('client', 'mode', val)}
+                                        >
+                                            <SelectTrigger>
+                                                <SelectValue />
+                                            </SelectTrigger>
+                                            <SelectContent>
+                                                <SelectItem value="both">{t('settings.client.direction_both', 'Send and receive')}</SelectItem>
+                                                <SelectItem value="send">{t('settings.client.direction_send', 'Send only')}</SelectItem>
+                                                <SelectItem value="receive">{t('settings.client.direction_receive', 'Receive only')}</SelectItem>
+                                            </SelectContent>
+                                        </Select>
+                                        <p className="text-[0.8rem] text-muted-foreground">{t('settings.client.direction_desc', 'Takes effect after restart.')}</p>
+                                    </div>
+                                    <div className="grid gap-2">
+                                        <Label>{t('settings.client.concealed', 'Password Manager Secrets')}</Label>
+                                        <Select
+                                            value={(config.client.concealed ?? DEFAULT_CONCEALED).mode}
+                                            onValueChange={val => updateConfig('client', 'concealed', { ...(config.client.concealed ?? DEFAULT_CONCEALED), mode: val })}
+                                        >
+                                            <SelectTrigger>
+                                                <SelectValue />
+                                            </SelectTrigger>
+                                            <SelectContent>
+                                                <SelectItem value="skip">{t('settings.client.concealed_skip', 'Do not sync')}</SelectItem>
+                                                <SelectItem value="sensitive">{t('settings.client.concealed_sensitive', 'Sync as expiring secret')}</SelectItem>
+                                            </SelectContent>
+                                        </Select>
+                                        <p className="text-[0.8rem] text-muted-foreground">{t('settings.client.concealed_desc', 'Items a password manager marks as concealed are never kept in history.')}</p>
+                                    </div>
                                 </>
                             )}
                         </CardContent>
@@ -248,6 +399,36 @@ function Settings({ activeSection }: SettingsProps) {
                 </Card>
             )}
 
+            {activeSection === 'general' && (
+                <Card>
+                    <CardHeader>
+                        <CardTitle>{t('settings.hotkeys.title', 'Global Hotkeys')}</CardTitle>
+                        <CardDescription>{t('settings.hotkeys.desc', 'Use modifiers plus one key, e.g. Ctrl+Alt+V. Leave empty to disable. Takes effect after restart.')}</CardDescription>
+                    </CardHeader>
+                    <CardContent className="space-y-4">
+                        <div className="flex items-center space-x-2">
+                            <Switch
+                                id="hotkeys-enabled"
+                                checked={hotkeys.enabled}
+                                onCheckedChange={(checked) => updateHotkey('enabled', checked)}
+                            />
+                            <Label htmlFor="hotkeys-enabled">{t('settings.hotkeys.enabled', 'Enable Global Hotkeys')}</Label>
+                        </div>
+                        {hotkeys.enabled && HOTKEY_ACTIONS.map(action => (
+                            <div key={action} className="grid gap-2">
+                                <Label htmlFor={`hotkey-${action}`}>{t(`settings.hotkeys.${action}`)}</Label>
+                                <Input
+                                    id={`hotkey-${action}`}
+                                    value={hotkeys[action]}
+                                    onChange={e => updateHotkey(action, e.target.value)}
+                                    placeholder={DEFAULT_HOTKEYS[action]}
+                                />
+                            </div>
+                        ))}
+                    </CardContent>
+                </Card>
+            )}
+
             {activeSection === 'local' && (
                 <Card>
                     <CardHeader>
@@ -276,6 +457,46 @@ function Settings({ activeSection }: SettingsProps) {
                             />
                             <p className="text-[0.8rem] text-muted-foreground">{t('settings.storage.retention_desc', 'Days to keep log files before auto-deletion.')}</p>
                         </div>
+
+                        <div className="grid gap-2">
+                            <Label>{t('settings.storage.keep_days', 'Keep History (Days)')}</Label>
+                            <div className="grid grid-cols-3 gap-2">
+                                {(['text_days', 'image_days', 'file_days'] as const).map(field => (
+                                    <div key={field} className="grid gap-1">
+                                        <Label htmlFor={field} className="text-xs text-muted-foreground">{t(`settings.storage.${field}`)}</Label>
+                                        <Input
+                                            id={field}
+                                            type="number"
+                                            min={0}
+                                            value={retention[field]}
+                                            onChange={e => updateRetention(field, parseInt(e.target.value) || 0)}
+                                        />
+                                    </div>
+                                ))}
+                            </div>
+                            <p className="text-[0.8rem] text-muted-foreground">{t('settings.storage.keep_days_desc', 'Older items are removed by the server. 0 keeps them forever.')}</p>
+                        </div>
+
+                        <div className="grid gap-2">
+                            <Label htmlFor="max_total_mb">{t('settings.storage.max_total_mb', 'History Size Limit (MB)')}</Label>
+                            <Input
+                                id="max_total_mb"
+                                type="number"
+                                min={0}
+                                value={retention.max_total_mb}
+                                onChange={e => updateRetention('max_total_mb', parseInt(e.target.value) || 0)}
+                            />
+                            <p className="text-[0.8rem] text-muted-foreground">{t('settings.storage.max_total_mb_desc', 'Oldest items are removed first when exceeded. 0 means unlimited.')}</p>
+                        </div>
+
+                        <div className="flex items-center justify-between">
+                            <Label htmlFor="keep_pinned">{t('settings.storage.keep_pinned', 'Keep pinned items forever')}</Label>
+                            <Switch
+                                id="keep_pinned"
+                                checked={retention.keep_pinned}
+                                onCheckedChange={(checked) => updateRetention('keep_pinned', checked)}
+                            />
+                        </div>
                     </CardContent>
                 </Card>
             )}
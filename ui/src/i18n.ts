@@ -50,7 +50,9 @@ i18n
                             "enabled_desc": "Allow other devices to connect to this device.",
                             "host": "Host Address",
                             "port": "Port",
-                            "host_desc": "The IP address to bind to. 0.0.0.0 listens on all interfaces."
+                            "host_desc": "The IP address to bind to. :: listens on all IPv4 and IPv6 interfaces, 0.0.0.0 on IPv4 only.",
+                            "mesh": "Peer-to-peer Sync",
+                            "mesh_desc": "Exchange clipboard entries directly with LAN devices that enable it too. All devices need the same token and encryption password."
                         },
                         "client": {
                             "title": "Client Configuration",
@@ -59,7 +61,22 @@ i18n
                             "enabled_desc": "Sync clipboard with a remote server.",
                             "remote_host": "Remote Server Address",
                             "remote_host_desc": "IP address or hostname of the remote server.",
-                            "remote_port": "Remote Server Port"
+                            "remote_port": "Remote Server Port",
+                            "scheme": "Protocol",
+                            "tls_fingerprint": "Certificate Fingerprint (SHA-256)",
+                            "tls_fingerprint_desc": "Only accept this certificate, e.g. a self-signed one. Leave empty to use system CAs.",
+                            "tls_tofu": "Trust on First Use",
+                            "tls_tofu_desc": "Remember the certificate seen on the first connection and reject any other one later.",
+                            "remotes_hint": "Syncing with {{count}} remotes from config.toml; the address below is not used.",
+                            "direction": "Sync Direction",
+                            "direction_both": "Send and receive",
+                            "direction_send": "Send only",
+                            "direction_receive": "Receive only",
+                            "direction_desc": "Takes effect after restart.",
+                            "concealed": "Password Manager Secrets",
+                            "concealed_skip": "Do not sync",
+                            "concealed_sensitive": "Sync as expiring secret",
+                            "concealed_desc": "Items a password manager marks as concealed are never kept in history."
                         },
                         "general": {
                             "title": "Appearance & Language",
@@ -67,6 +84,15 @@ i18n
                             "device_name": "Device Name",
                             "device_name_desc": "Name displayed on other devices."
                         },
+                        "hotkeys": {
+                            "title": "Global Hotkeys",
+                            "desc": "Use modifiers plus one key, e.g. Ctrl+Alt+V. Leave empty to disable. Takes effect after restart.",
+                            "enabled": "Enable Global Hotkeys",
+                            "push": "Send Current Clipboard",
+                            "pull": "Fetch Latest from Server",
+                            "toggle_pause": "Pause / Resume Sync",
+                            "history_picker": "Open History Picker"
+                        },
                         "security": {
                             "token": "Authentication Token (Optional)",
                             "token_placeholder": "Enter a secure token...",
@@ -81,7 +107,15 @@ i18n
                             "limit": "History Limit",
                             "limit_desc": "Maximum items to keep in history.",
                             "retention": "Log Retention (Days)",
-                            "retention_desc": "Days to keep log files before auto-deletion."
+                            "retention_desc": "Days to keep log files before auto-deletion.",
+                            "keep_days": "Keep History (Days)",
+                            "keep_days_desc": "Older items are removed by the server. 0 keeps them forever.",
+                            "text_days": "Text",
+                            "image_days": "Images",
+                            "file_days": "Files",
+                            "max_total_mb": "History Size Limit (MB)",
+                            "max_total_mb_desc": "Oldest items are removed first when exceeded. 0 means unlimited.",
+                            "keep_pinned": "Keep pinned items forever"
                         },
                         "network": {
                             "local_info": "Local Network Information",
@@ -98,7 +132,9 @@ i18n
                             "physical_adapter": "Physical",
                             "connected_clients": "Connected Devices",
                             "connected_clients_desc": "Devices recently connected to local server",
-                            "last_active": "Last Active"
+                            "last_active": "Last Active",
+                            "direction_send": "Send only",
+                            "direction_receive": "Receive only"
                         },
                         "history": {
                             "recent_items": "Recent Items",
@@ -112,7 +148,10 @@ i18n
                             "clipboard_image": "Clipboard Image",
                             "open_full_size": "Open full size",
                             "unknown_file": "Unknown File",
-                            "copy_to_clipboard": "Copy to Clipboard"
+                            "copy_to_clipboard": "Copy to Clipboard",
+                            "search_placeholder": "Search history...",
+                            "no_results": "No matching items",
+                            "picker_hint": "↑↓ to select, Enter to copy, Esc to close"
                         },
                         "about": {
                             "subtitle": "Cross-device clipboard sync tool",
@@ -189,7 +228,9 @@ i18n
                             "enabled_desc": "允许其他设备连接到此设备。",
                             "host": "监听地址",
                             "port": "端口",
-                            "host_desc": "绑定的IP地址。0.0.0.0 表示监听所有接口。"
+                            "host_desc": "绑定的IP地址。:: 表示监听所有 IPv4 与 IPv6 接口，0.0.0.0 仅监听 IPv4。",
+                            "mesh": "点对点同步",
+                            "mesh_desc": "与局域网内同样启用的设备直接交换剪贴板内容，所有设备需使用相同的令牌与加密密码。"
                         },
                         "client": {
                             "title": "客户端配置",
@@ -198,7 +239,22 @@ i18n
                             "enabled_desc": "与远程服务器同步剪贴板。",
                             "remote_host": "远程服务器地址",
                             "remote_host_desc": "远程服务器的IP地址或主机名。",
-                            "remote_port": "远程服务器端口"
+                            "remote_port": "远程服务器端口",
+                            "scheme": "协议",
+                            "tls_fingerprint": "证书指纹 (SHA-256)",
+                            "tls_fingerprint_desc": "只接受该证书（如自签名证书）。留空则使用系统信任的 CA。",
+                            "tls_tofu": "首次连接时信任",
+                            "tls_tofu_desc": "记住首次连接时的证书，之后拒绝其他证书。",
+                            "remotes_hint": "正在按 config.toml 同步到 {{count}} 个远端，下方地址不会使用。",
+                            "direction": "同步方向",
+                            "direction_both": "发送并接收",
+                            "direction_send": "仅发送",
+                            "direction_receive": "仅接收",
+                            "direction_desc": "重启后生效。",
+                            "concealed": "密码管理器中的密码",
+                            "concealed_skip": "不同步",
+                            "concealed_sensitive": "作为限时敏感内容同步",
+                            "concealed_desc": "密码管理器标记为隐藏的内容不会保存到历史记录。"
                         },
                         "general": {
                             "title": "外观与语言",
@@ -206,6 +262,15 @@ i18n
                             "device_name": "设备名称",
                             "device_name_desc": "在其他设备上显示的名称。"
                         },
+                        "hotkeys": {
+                            "title": "全局快捷键",
+                            "desc": "修饰键加一个按键，如 Ctrl+Alt+V。留空表示不绑定，重启后生效。",
+                            "enabled": "启用全局快捷键",
+                            "push": "发送当前剪贴板",
+                            "pull": "拉取服务器最新内容",
+                            "toggle_pause": "暂停 / 恢复同步",
+                            "history_picker": "打开历史快速选择"
+                        },
                         "security": {
                             "title": "安全与加密",
                             "desc": "使用令牌和端到端加密保护您的数据。",
@@ -222,7 +287,15 @@ i18n
                             "limit": "历史记录上限",
                             "limit_desc": "保留的历史记录最大数量。",
                             "retention": "日志保留天数",
-                            "retention_desc": "自动删除旧日志文件前的保留天数。"
+                            "retention_desc": "自动删除旧日志文件前的保留天数。",
+                            "keep_days": "历史保留天数",
+                            "keep_days_desc": "超过天数的记录由服务端自动删除，0 表示永久保留。",
+                            "text_days": "文本",
+                            "image_days": "图片",
+                            "file_days": "文件",
+                            "max_total_mb": "历史总大小上限 (MB)",
+                            "max_total_mb_desc": "超出时从最旧的记录开始删除，0 表示不限制。",
+                            "keep_pinned": "永久保留置顶记录"
                         },
                         "network": {
                             "local_info": "本地网络信息",
@@ -239,7 +312,9 @@ i18n
                             "physical_adapter": "物理网卡",
                             "connected_clients": "已连接设备",
                             "connected_clients_desc": "最近连接到本机服务器的设备",
-                            "last_active": "最后活跃"
+                            "last_active": "最后活跃",
+                            "direction_send": "仅发送",
+                            "direction_receive": "仅接收"
                         },
                         "history": {
                             "recent_items": "最近记录",
@@ -253,7 +328,10 @@ i18n
                             "clipboard_image": "剪贴板图片",
                             "open_full_size": "打开完整尺寸",
                             "unknown_file": "未知文件",
-                            "copy_to_clipboard": "复制到剪贴板"
+                            "copy_to_clipboard": "复制到剪贴板",
+                            "search_placeholder": "搜索历史记录...",
+                            "no_results": "没有匹配的记录",
+                            "picker_hint": "↑↓ 选择，回车复制，Esc 关闭"
                         },
                         "about": {
                             "subtitle": "跨设备剪贴板同步工具",
@@ -3,13 +3,17 @@ import { createRoot } from 'react-dom/client'
 import './index.css';
 import './i18n';
 import App from './App.tsx'
+import HistoryPicker from './components/HistoryPicker.tsx'
 
 import { ThemeProvider } from "./components/theme-provider"
 
+// The desktop app opens the hotkey history picker as a separate window on index.html?view=picker
+const isPicker = new URLSearchParams(window.location.search).get('view') === 'picker';
+
 createRoot(document.getElementById('root')!).render(
   <StrictMode>
     <ThemeProvider defaultTheme="dark" storageKey="vite-ui-theme">
-      <App />
+      {isPicker ? <HistoryPicker /> : <App />}
     </ThemeProvider>
   </StrictMode>,
 )
@@ -4,14 +4,63 @@ export interface ServerConfig {
     tls?: {
         cert: string;
         key: string;
+        self_signed?: boolean;
     } | null;
     enabled: boolean;
 }
 
+export interface ConcealedConfig {
+    mode: "skip" | "sensitive";
+    ttl_secs: number;
+}
+
 export interface ClientConfig {
     enabled: boolean;
+    scheme?: "http" | "https";
     remote_host: string;
     remote_port: number;
+    tls?: ClientTlsConfig;
+    server_id?: string | null;
+    concealed?: ConcealedConfig;
+    mode?: SyncDirection;
+    push_mode?: SyncMode;
+    remotes?: RemoteConfig[];
+}
+
+export type ContentKind = "text" | "image" | "file";
+
+export interface RouteConfig {
+    types: ContentKind[];
+    include: string[];
+    exclude: string[];
+}
+
+export interface RemoteConfig {
+    name: string;
+    url: string;
+    token?: string | null;
+    encrypt_password?: string | null;
+    direction?: SyncDirection;
+    route?: RouteConfig;
+    tls?: ClientTlsConfig;
+    server_id?: string | null;
+}
+
+export interface ClientTlsConfig {
+    ca_cert: string | null;
+    fingerprint: string | null;
+    trust_on_first_use: boolean;
+    known_servers: string;
+}
+
+export type SyncMode = "auto" | "manual";
+
+export type SyncDirection = "both" | "send" | "receive";
+
+export interface SyncStatus {
+    state: "running" | "paused" | "stopped";
+    push_mode: SyncMode;
+    mode: SyncDirection;
 }
 
 export interface AuthConfig {
@@ -19,21 +68,73 @@ export interface AuthConfig {
     encrypt_password?: string | null;
 }
 
+export interface RetentionConfig {
+    text_days: number;
+    image_days: number;
+    file_days: number;
+    keep_pinned: boolean;
+    max_total_mb: number;
+    interval_secs: number;
+}
+
 export interface HistoryConfig {
     max_count: number;
     log_retention_days: number;
+    retention?: RetentionConfig;
 }
 
 export interface GeneralConfig {
     device_name: string;
 }
 
+export interface FilterConfig {
+    text: boolean;
+    html: boolean;
+    image: boolean;
+    file: boolean;
+    max_text_bytes: number;
+    deny_patterns: string[];
+    min_interval_ms: number;
+}
+
+/** Global shortcuts such as "Ctrl+Alt+V"; an empty string leaves the action unbound */
+export interface HotkeyConfig {
+    enabled: boolean;
+    push: string;
+    pull: string;
+    toggle_pause: string;
+    history_picker: string;
+}
+
+/** LAN discovery announcement authentication */
+export interface DiscoveryConfig {
+    secret: string | null;
+    key_file: string | null;
+    trusted_devices: { id: string; public_key: string }[];
+    /** CIDR ranges probed over HTTP for cross-subnet discovery */
+    scan_ranges: string[];
+    scan_ports: number[];
+}
+
+/** Peer-to-peer sync: servers push entries straight to each other, ordered by Lamport timestamps */
+export interface MeshConfig {
+    enabled: boolean;
+    scheme: string;
+    tls: ClientTlsConfig;
+    /** Device IDs allowed as nodes; empty accepts every discovered node */
+    devices: string[];
+}
+
 export interface Config {
     server: ServerConfig;
     client: ClientConfig;
     auth: AuthConfig;
     history: HistoryConfig;
     general: GeneralConfig;
+    filters?: FilterConfig;
+    hotkeys?: HotkeyConfig;
+    discovery?: DiscoveryConfig;
+    mesh?: MeshConfig;
 }
 
 export interface HistoryItem {
@@ -45,4 +146,16 @@ export interface HistoryItem {
     device?: string;
     timestamp: string;
     pinned?: boolean;
+    snippet?: string;
+}
+
+export interface HistoryQuery {
+    q?: string;
+    type?: "Text" | "Image" | "File";
+    device?: string;
+    pinned?: boolean;
+    since?: string;
+    until?: string;
+    limit?: number;
+    offset?: number;
 }